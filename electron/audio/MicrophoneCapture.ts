@@ -1,6 +1,7 @@
 import { EventEmitter } from 'events';
 import { app } from 'electron';
 import path from 'path';
+import type { CaptureError, CaptureEvent, CaptureOptions, FrameInfo } from 'natively-audio';
 
 // Load the native module
 let NativeModule: any = null;
@@ -17,17 +18,19 @@ export class MicrophoneCapture extends EventEmitter {
     private monitor: any = null;
     private isRecording: boolean = false;
     private deviceId: string | null = null;
+    private options: CaptureOptions;
 
-    constructor(deviceId?: string | null) {
+    constructor(deviceId?: string | null, options: CaptureOptions = {}) {
         super();
         this.deviceId = deviceId || null;
+        this.options = options;
         if (!RustMicCapture) {
             console.error('[MicrophoneCapture] Rust class implementation not found.');
         } else {
             console.log(`[MicrophoneCapture] Initialized wrapper. Device ID: ${this.deviceId || 'default'}`);
             try {
                 console.log('[MicrophoneCapture] Creating native monitor (Eager Init)...');
-                this.monitor = new RustMicCapture(this.deviceId);
+                this.monitor = new RustMicCapture(this.deviceId, this.options);
             } catch (e) {
                 console.error('[MicrophoneCapture] Failed to create native monitor:', e);
                 // We don't throw here to allow app to start, but start() will fail
@@ -36,8 +39,7 @@ export class MicrophoneCapture extends EventEmitter {
     }
 
     public getSampleRate(): number {
-        // Return 16000 default as we effectively downsample to this now
-        return this.monitor?.getSampleRate() || 16000;
+        return this.monitor?.getSampleRate() ?? this.options.sampleRate ?? 16000;
     }
 
     /**
@@ -56,7 +58,7 @@ export class MicrophoneCapture extends EventEmitter {
         if (!this.monitor) {
             console.log('[MicrophoneCapture] Monitor not initialized. Re-initializing...');
             try {
-                this.monitor = new RustMicCapture(this.deviceId);
+                this.monitor = new RustMicCapture(this.deviceId, this.options);
             } catch (e) {
                 this.emit('error', e);
                 return;
@@ -66,14 +68,33 @@ export class MicrophoneCapture extends EventEmitter {
         try {
             console.log('[MicrophoneCapture] Starting native capture...');
 
-            this.monitor.start((chunk: Uint8Array) => {
+            // Backend failures arrive here, on or off the start() call
+            this.monitor.onError((error: CaptureError) => {
+                this.emit('captureError', error);
+                if (error.fatal) {
+                    this.emit('error', new Error(`${error.code}: ${error.message}`));
+                }
+            });
+
+            this.monitor.start((chunk: Buffer | Int16Array | Float32Array, info: FrameInfo) => {
                 if (chunk && chunk.length > 0) {
+                    // Typed arrays (frameType: "typedArray") are viewed as bytes, not copied
+                    const buffer = Buffer.isBuffer(chunk) ? chunk : Buffer.from(chunk.buffer, chunk.byteOffset, chunk.byteLength);
                     // Debug: log occasionally
                     if (Math.random() < 0.05) {
-                        console.log(`[MicrophoneCapture] Emitting chunk: ${chunk.length} bytes to JS`);
+                        console.log(`[MicrophoneCapture] Emitting chunk: ${buffer.length} bytes (${info.frames} frames) to JS`);
                     }
-                    this.emit('data', Buffer.from(chunk));
+                    if (info.gapMs > 0) {
+                        this.emit('gap', info);
+                    }
+                    this.emit('data', buffer, info);
+                }
+            }, (event: CaptureEvent) => {
+                // speechStart / speechEnd / formatChanged / deviceChanged / deviceLost / deviceSwitched
+                if (event.kind === 'formatChanged') {
+                    console.log(`[MicrophoneCapture] Device rate changed: ${event.previousSampleRate}Hz -> ${event.inputSampleRate}Hz`);
                 }
+                this.emit(event.kind, event);
             });
 
             this.isRecording = true;
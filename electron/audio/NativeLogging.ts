@@ -0,0 +1,50 @@
+import type { LogRecord } from 'natively-audio';
+
+let NativeModule: any = null;
+
+try {
+    NativeModule = require('natively-audio');
+} catch (e) {
+    console.error('[NativeLogging] Failed to load native module:', e);
+}
+
+const { setLogLevel, setLogSink } = NativeModule || {};
+
+export type NativeLogLevel = 'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+/**
+ * Route native audio logs through console (and so into the debug log file)
+ */
+export function installNativeLogSink(level: NativeLogLevel = 'info'): void {
+    if (!setLogSink || !setLogLevel) {
+        console.warn('[NativeLogging] Native functionality not available');
+        return;
+    }
+    try {
+        setLogLevel(level);
+        setLogSink((record: LogRecord) => {
+            const line = `[native:${record.target}] ${record.message}`;
+            if (record.level === 'error') {
+                console.error(line);
+            } else if (record.level === 'warn') {
+                console.warn(line);
+            } else {
+                console.log(line);
+            }
+        });
+    } catch (e) {
+        console.error('[NativeLogging] Failed to install log sink:', e);
+    }
+}
+
+/**
+ * Change the native log level, for every module or one `target`
+ * (e.g. "speaker", "pipeline")
+ */
+export function setNativeLogLevel(level: NativeLogLevel, target?: string): void {
+    try {
+        setLogLevel?.(level, target);
+    } catch (e) {
+        console.error('[NativeLogging] Failed to set log level:', e);
+    }
+}
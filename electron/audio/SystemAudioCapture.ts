@@ -1,6 +1,7 @@
 import { EventEmitter } from 'events';
 import { app } from 'electron';
 import path from 'path';
+import type { CaptureError, CaptureEvent, CaptureOptions, FrameInfo } from 'natively-audio';
 
 let NativeModule: any = null;
 
@@ -16,11 +17,14 @@ export class SystemAudioCapture extends EventEmitter {
     private monitor: any = null;
     private isRecording: boolean = false;
     private deviceId: string | null = null;
+    private options: CaptureOptions;
     private detectedSampleRate: number = 16000;
 
-    constructor(deviceId?: string | null) {
+    constructor(deviceId?: string | null, options: CaptureOptions = {}) {
         super();
         this.deviceId = deviceId || null;
+        this.options = options;
+        this.detectedSampleRate = options.sampleRate ?? 16000;
         if (!RustAudioCapture) {
             console.error('[SystemAudioCapture] Rust class implementation not found.');
         } else {
@@ -31,9 +35,8 @@ export class SystemAudioCapture extends EventEmitter {
     }
 
     public getSampleRate(): number {
-        // Return 16000 default as we effectively downsample to this now
-        // Force return 16000 to avoid stale binary issues reporting device rate
-        return 16000;
+        // The native monitor is created lazily; until then report the configured rate
+        return this.monitor?.getSampleRate() ?? this.detectedSampleRate;
     }
 
     /**
@@ -52,7 +55,7 @@ export class SystemAudioCapture extends EventEmitter {
         if (!this.monitor) {
             console.log('[SystemAudioCapture] Creating native monitor (lazy init)...');
             try {
-                this.monitor = new RustAudioCapture(this.deviceId);
+                this.monitor = new RustAudioCapture(this.deviceId, this.options);
             } catch (e) {
                 console.error('[SystemAudioCapture] Failed to create native monitor:', e);
                 this.emit('error', e);
@@ -63,16 +66,35 @@ export class SystemAudioCapture extends EventEmitter {
         try {
             console.log('[SystemAudioCapture] Starting native capture...');
 
-            this.monitor.start((chunk: Uint8Array) => {
-                // The native module sends raw PCM bytes (Uint8Array)
+            // Backend failures arrive here, on or off the start() call
+            this.monitor.onError((error: CaptureError) => {
+                this.emit('captureError', error);
+                if (error.fatal) {
+                    this.emit('error', new Error(`${error.code}: ${error.message}`));
+                }
+            });
+
+            this.monitor.start((chunk: Buffer | Int16Array | Float32Array, info: FrameInfo) => {
+                // The native module sends raw PCM as a Buffer (or a typed
+                // array with frameType: "typedArray") and the frame's
+                // sequence number / timestamps
                 if (chunk && chunk.length > 0) {
-                    const buffer = Buffer.from(chunk);
+                    const buffer = Buffer.isBuffer(chunk) ? chunk : Buffer.from(chunk.buffer, chunk.byteOffset, chunk.byteLength);
                     if (Math.random() < 0.05) {
                         const prefix = buffer.slice(0, 10).toString('hex');
                         console.log(`[SystemAudioCapture] Chunk: ${buffer.length}b, Rate: ${this.detectedSampleRate}, Data(hex): ${prefix}...`);
                     }
-                    this.emit('data', buffer);
+                    if (info.gapMs > 0) {
+                        this.emit('gap', info);
+                    }
+                    this.emit('data', buffer, info);
+                }
+            }, (event: CaptureEvent) => {
+                // speechStart / speechEnd / formatChanged / deviceChanged / deviceLost / deviceSwitched
+                if (event.kind === 'formatChanged') {
+                    console.log(`[SystemAudioCapture] Device rate changed: ${event.previousSampleRate}Hz -> ${event.inputSampleRate}Hz`);
                 }
+                this.emit(event.kind, event);
             });
 
             this.isRecording = true;
@@ -73,6 +73,7 @@ import { ProcessingHelper } from "./ProcessingHelper"
 import { IntelligenceManager } from "./IntelligenceManager"
 import { SystemAudioCapture } from "./audio/SystemAudioCapture"
 import { MicrophoneCapture } from "./audio/MicrophoneCapture"
+import { installNativeLogSink } from "./audio/NativeLogging"
 import { GoogleSTT } from "./audio/GoogleSTT"
 import { RestSTT } from "./audio/RestSTT"
 import { DeepgramStreamingSTT } from "./audio/DeepgramStreamingSTT"
@@ -1527,6 +1528,9 @@ function setMacDockIcon() {
 async function initializeApp() {
   await app.whenReady()
 
+  // Native audio logs go through console so they reach the debug log file
+  installNativeLogSink(isDev ? 'debug' : 'info');
+
   // Initialize CredentialsManager and load keys explicitly
   // This fixes the issue where keys (especially in production) aren't loaded in time for RAG/LLM
   const { CredentialsManager } = require('./services/CredentialsManager');
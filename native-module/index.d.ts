@@ -3,21 +3,428 @@
 
 /* auto-generated by NAPI-RS */
 
+/** One entry of the JS `stages` option */
+export interface StageOptions {
+  /**
+   * "noiseSuppression" | "suppression" | "agc" | "gain" |
+   * "aec" (microphone only, see `setEchoReference`) | "opus"
+   */
+  kind: string
+  /** Skip this stage without removing it from the list. Defaults to true. */
+  enabled?: boolean
+  /** gain: fixed gain in dB (may be negative) */
+  gainDb?: number
+  /** aec: echo tail covered by the adaptive filter in ms (default 16) */
+  tailMs?: number
+  /** aec: largest speaker -> microphone delay searched in ms (default 500) */
+  maxDelayMs?: number
+  /** noiseSuppression: 0.0 (off) .. 1.0 (strongest, default 0.7) */
+  strength?: number
+  /** agc: loudness the output is steered to in dBFS (default -20) */
+  targetDbfs?: number
+  /** agc: largest boost in dB (default 30) */
+  maxGainDb?: number
+  /** agc: time constant for reducing gain in ms (default 20) */
+  attackMs?: number
+  /** agc: time constant for increasing gain in ms (default 1000) */
+  releaseMs?: number
+  /** agc: peak limiter at -1 dBFS instead of hard clipping (default true) */
+  limiter?: boolean
+  /**
+   * suppression: speech detector, "rms" (fixed threshold, default) or
+   * "features" (energy + zero-crossing + spectral flatness)
+   */
+  detector?: string
+  /** opus: target bitrate in bits per second (default 24000) */
+  bitrate?: number
+  /** opus: encoder effort 0 (fastest) .. 10 (best, default 5) */
+  complexity?: number
+  /**
+   * opus: "ogg" (Ogg Opus stream, default) | "raw" (one bare packet per
+   * frame)
+   */
+  container?: string
+}
+/**
+ * Options accepted by the `MicrophoneCapture` and `SystemAudioCapture`
+ * constructors. Device and channel options only apply to microphones.
+ */
+export interface CaptureOptions {
+  /**
+   * Open the default device instead of failing when `deviceId` no longer
+   * exists (e.g. a USB headset that was unplugged). Defaults to false.
+   */
+  fallbackToDefault?: boolean
+  /**
+   * How multi-channel microphones are reduced:
+   * "downmix" (average, default) | "channel" (see `channel`) |
+   * "maxEnergy" (follow the loudest channel) | "all" (keep interleaved)
+   */
+  channelPolicy?: string
+  /** 0-based channel index used with `channelPolicy: "channel"` */
+  channel?: number
+  /**
+   * Ordered processing stages run on every 20ms frame.
+   * Defaults to `[{ kind: "suppression" }]`.
+   */
+  stages?: Array<StageOptions>
+  /**
+   * Detector behind the speechStart/speechEnd events:
+   * "rms" (fixed threshold, default) | "features"
+   */
+  vadDetector?: string
+  /** Output rate of delivered frames: 8000 | 16000 (default) | 24000 | 48000 */
+  sampleRate?: number
+  /** Frame duration in ms: 10 | 20 (default) | 40 | 100 */
+  frameMs?: number
+  /** Sample type of delivered PCM: "i16" (default) | "f32" */
+  sampleFormat?: string
+  /**
+   * JS type of delivered frames: "buffer" (little-endian bytes, default) |
+   * "typedArray" (Int16Array or Float32Array per `sampleFormat`). Opus
+   * frames are always Buffers.
+   */
+  frameType?: string
+  /**
+   * Deliver contiguous frames together, at most every `batchMs` (rounded
+   * up to whole frames, max 1000). 0 (default) delivers every frame.
+   */
+  batchMs?: number
+  /**
+   * Rate conversion: "fast" (linear interpolation, no lookahead,
+   * default) | "high" (anti-aliased windowed sinc, ~1ms lookahead)
+   */
+  resamplerQuality?: string
+  /**
+   * Move to the new system default device whenever it changes (headset
+   * plugged in, Bluetooth disconnected) without interrupting the stream.
+   * Only valid without `deviceId`. Defaults to false.
+   */
+  followDefaultDevice?: boolean
+  /**
+   * Capture backend. MicrophoneCapture: "auto" (default) | "cpal".
+   * SystemAudioCapture: "auto" (default) | "coreAudioTap" |
+   * "screenCaptureKit" (macOS) | "wasapi" (Windows) | "pulseAudio" (Linux).
+   */
+  backend?: string
+}
+/** Options accepted by `startRecording` */
+export interface RecordingOptions {
+  /**
+   * "wav" | "flac" | "opus" (Ogg Opus, needs the processed source);
+   * defaults from the file extension (.flac, .opus/.ogg), else "wav"
+   */
+  format?: string
+  /** "processed" (16kHz frames, default) | "raw" (device rate) */
+  source?: string
+  /** Start a new numbered file (`name-000.wav`, ...) every N seconds */
+  rotateSeconds?: number
+  /** Keep only the newest N rotated files */
+  maxFiles?: number
+  /** opus: bitrate in bits per second (default 24000) */
+  bitrate?: number
+}
+/**
+ * Playback options of the `FileAudioCapture` constructor (pipeline options
+ * go in its `CaptureOptions`)
+ */
+export interface FilePlaybackOptions {
+  /**
+   * Multiple of real time (default 1.0); 0 plays as fast as the pipeline
+   * consumes
+   */
+  speed?: number
+  /**
+   * Start over at the end of the file instead of finishing. Defaults to
+   * false.
+   */
+  repeat?: boolean
+  /**
+   * Capture the file stands in for, selecting its suppression defaults:
+   * "microphone" (default) | "systemAudio"
+   */
+  source?: string
+}
+/** Format of the PCM a `VirtualAudioInput` accepts */
+export interface VirtualInputOptions {
+  /** Default 48000 */
+  sampleRate?: number
+  /** Interleaved channels per frame. Default 1. */
+  channels?: number
+  /** "i16" (default) | "i32" | "f32" | "u8", little-endian */
+  sampleFormat?: string
+}
+/** One event from a capture pipeline */
+export interface CaptureEvent {
+  /**
+   * "speechStart" | "speechEnd" | "formatChanged" | "deviceChanged" |
+   * "deviceLost" | "deviceSwitched" | "fileEnded"
+   */
+  kind: string
+  /** Output-rate sample index (per channel) where the event happened */
+  sampleOffset: number
+  /** `sampleOffset` in milliseconds of stream time */
+  timestampMs: number
+  /**
+   * Frame RMS (i16 scale) when the event fired (0 for format, device and
+   * file events)
+   */
+  rms: number
+  /** formatChanged: new device sample rate */
+  inputSampleRate?: number
+  /** formatChanged: device sample rate before the change */
+  previousSampleRate?: number
+  /**
+   * deviceChanged: the new system default; deviceLost: the device that
+   * disappeared; deviceSwitched: the device now being captured
+   */
+  deviceId?: string
+}
+/** Second argument of the frame callback */
+export interface FrameInfo {
+  /** Delivered frames before this one since `start` (no holes) */
+  seq: number
+  /** Output-rate sample index (per channel) of the first sample */
+  sampleOffset: number
+  /**
+   * `sampleOffset` in milliseconds of stream time (the clock of
+   * `CaptureEvent.timestampMs`)
+   */
+  timestampMs: number
+  /**
+   * Host wall-clock time the first sample was captured, in ms since the
+   * Unix epoch (comparable with `Date.now()`)
+   */
+  capturedAtMs: number
+  /**
+   * Samples (per channel) suppressed since the previous delivered frame,
+   * or since the stream started for the first one
+   */
+  gapSamples: number
+  /** `gapSamples` in milliseconds */
+  gapMs: number
+  /**
+   * Frames concatenated in this delivery (1 unless `batchMs` is set);
+   * `seq` and the offsets describe the first
+   */
+  frames: number
+}
+/** One log line, as handed to the JS sink */
+export interface LogRecord {
+  /** "error" | "warn" | "info" | "debug" | "trace" */
+  level: string
+  /** Module the record came from, e.g. "speaker::linux" */
+  target: string
+  message: string
+  /** Wall clock time in ms since the Unix epoch */
+  timestampMs: number
+}
+/** One failure, as handed to `onError` */
+export interface CaptureError {
+  /**
+   * "DeviceLost" | "PermissionDenied" | "Overflow" | "FormatUnsupported" |
+   * "Timeout" | "StreamError"
+   */
+  code: string
+  message: string
+  /**
+   * Capture has stopped delivering audio; restart it (or pick another
+   * device) to recover
+   */
+  fatal: boolean
+}
+/** Snapshot of the microphone's `aec` stage */
+export interface EchoCancellerStats {
+  /** Echo return loss enhancement in dB (higher = more echo removed) */
+  erleDb: number
+  /** Estimated speaker -> microphone delay in ms */
+  delayMs: number
+  /** Whether a delay estimate has locked yet */
+  converged: boolean
+}
+/** Input levels of a capture source, measured on the DSP thread */
+export interface AudioLevels {
+  /** Peak of the latest frame (0.0 - 1.0) */
+  peak: number
+  /** RMS of the latest frame (0.0 - 1.0) */
+  rms: number
+  /** `rms` in dBFS (-100 for digital silence) */
+  dbfs: number
+  /** Full-scale samples since the capture was created */
+  clipCount: number
+  /**
+   * RMS dBFS of the frames of the last second (1000 / frameMs of them),
+   * oldest first
+   */
+  history: Array<number>
+}
+/** A device and what it supports; empty lists mean the backend can't tell */
 export interface AudioDeviceInfo {
   id: string
   name: string
+  /** The current system default for its direction */
+  isDefault: boolean
+  sampleRates: Array<number>
+  channelCounts: Array<number>
+  /** e.g. "f32", "i16" */
+  sampleFormats: Array<string>
+  /**
+   * "builtIn" | "usb" | "bluetooth" | "hdmi" | "virtual" | ... where the
+   * platform reports it
+   */
+  transport?: string
+}
+/** Both device lists, as pushed by `watchDevices` */
+export interface DeviceList {
+  inputs: Array<AudioDeviceInfo>
+  outputs: Array<AudioDeviceInfo>
 }
 export declare function getInputDevices(): Array<AudioDeviceInfo>
 export declare function getOutputDevices(): Array<AudioDeviceInfo>
+/**
+ * Call `callback` with a `DeviceList` now and whenever a device is added,
+ * removed or renamed, or a default device changes
+ */
+export declare function watchDevices(callback: (...args: any[]) => any): DeviceWatcher
+/**
+ * Set the log level ("off" | "error" | "warn" | "info" | "debug" | "trace")
+ * for `target` (a module path such as "pipeline" or "speaker::linux"), or
+ * for everything without one. Defaults to "info".
+ */
+export declare function setLogLevel(level: string, target?: string | undefined | null): void
+/**
+ * Forward every `LogRecord` to `callback` instead of stdout/stderr (pass
+ * null to go back to the console)
+ */
+export declare function setLogSink(callback?: (...args: any[]) => any | undefined | null): void
 export declare class SystemAudioCapture {
-  constructor(deviceId?: string | undefined | null)
+  constructor(deviceId?: string | undefined | null, options?: CaptureOptions | undefined | null)
+  /**
+   * `callback` receives a `CaptureError` whenever the backend fails
+   * (pass null to remove it); independent of `start`/`stop`
+   */
+  onError(callback?: (...args: any[]) => any | undefined | null): void
+  /** Output rate of delivered frames (the `sampleRate` option, default 16000) */
   getSampleRate(): number
-  start(callback: (...args: any[]) => any): void
+  /** Duration of each delivered frame in ms (the `frameMs` option, default 20) */
+  getFrameMs(): number
+  /** Gain currently applied by the `agc` stage in dB, or null without one */
+  getGainDb(): number | null
+  /** Latest input levels (cheap to poll; zeros until `start`) */
+  getLevels(): AudioLevels
+  /** `eventCallback` receives speech, format and device `CaptureEvent`s */
+  start(callback: (...args: any[]) => any, eventCallback?: (...args: any[]) => any | undefined | null): void
   stop(): void
+  /** Record to a WAV or FLAC file, whether or not `start` was called */
+  startRecording(path: string, options?: RecordingOptions | undefined | null): void
+  /** Finalize the recording; returns the paths of the files written */
+  stopRecording(): Array<string>
 }
 export declare class MicrophoneCapture {
-  constructor(deviceId?: string | undefined | null)
+  constructor(deviceId?: string | undefined | null, options?: CaptureOptions | undefined | null)
+  /**
+   * `callback` receives a `CaptureError` whenever the device fails
+   * (pass null to remove it); independent of `start`/`stop`
+   */
+  onError(callback?: (...args: any[]) => any | undefined | null): void
+  /** Output rate of delivered frames (the `sampleRate` option, default 16000) */
+  getSampleRate(): number
+  /** Duration of each delivered frame in ms (the `frameMs` option, default 20) */
+  getFrameMs(): number
+  /** Interleaved channels per delivered frame (1 unless channelPolicy is "all") */
+  getChannels(): number
+  /**
+   * Cancel the audio of `systemAudio` in this microphone's `aec` stage.
+   * Applies from the next `start`.
+   */
+  setEchoReference(systemAudio: SystemAudioCapture): void
+  /**
+   * Unlink the echo reference; the `aec` stage passes audio through
+   * from the next `start`
+   */
+  clearEchoReference(): void
+  /** Echo canceller state, or null when no `aec` stage is running */
+  getEchoStats(): EchoCancellerStats | null
+  /** Gain currently applied by the `agc` stage in dB, or null without one */
+  getGainDb(): number | null
+  /** Latest input levels (cheap to poll; zeros until `start`) */
+  getLevels(): AudioLevels
+  /** `eventCallback` receives speech, format and device `CaptureEvent`s */
+  start(callback: (...args: any[]) => any, eventCallback?: (...args: any[]) => any | undefined | null): void
+  stop(): void
+  /** Record to a WAV or FLAC file, whether or not `start` was called */
+  startRecording(path: string, options?: RecordingOptions | undefined | null): void
+  /** Finalize the recording; returns the paths of the files written */
+  stopRecording(): Array<string>
+}
+/** Plays a WAV or FLAC file through the same pipeline as a live capture */
+export declare class FileAudioCapture {
+  /**
+   * Decodes `path` up front; `options` are the pipeline options of
+   * `MicrophoneCapture` (device options are ignored)
+   */
+  constructor(path: string, options?: CaptureOptions | undefined | null, playback?: FilePlaybackOptions | undefined | null)
+  /** Output rate of delivered frames (the `sampleRate` option, default 16000) */
   getSampleRate(): number
-  start(callback: (...args: any[]) => any): void
+  /** Duration of each delivered frame in ms (the `frameMs` option, default 20) */
+  getFrameMs(): number
+  /** Interleaved channels per delivered frame (1 unless channelPolicy is "all") */
+  getChannels(): number
+  /** Length of the file in seconds */
+  getDuration(): number
+  /** Latest input levels (cheap to poll; zeros until `start`) */
+  getLevels(): AudioLevels
+  /**
+   * Play the file from the start. `eventCallback` receives speech and
+   * format events, plus `fileEnded` once the whole file was pushed.
+   */
+  start(callback: (...args: any[]) => any, eventCallback?: (...args: any[]) => any | undefined | null): void
+  /** Stop playback; the next `start` plays the file from the beginning */
+  stop(): void
+}
+/**
+ * An input device fed from JS: listed by `getInputDevices` as
+ * "virtual:<name>" and opened by `MicrophoneCapture` like any microphone
+ */
+export declare class VirtualAudioInput {
+  constructor(name: string, options?: VirtualInputOptions | undefined | null)
+  /** Device id to pass to `MicrophoneCapture` */
+  getId(): string
+  /**
+   * Feed interleaved little-endian PCM in the configured format. Returns
+   * the number of frames accepted: fewer than pushed when the capture's
+   * ring buffer is full (push the rest later), 0 while nothing captures.
+   */
+  push(data: Buffer): number
+  /** Remove the device; a capture reading it reports it as lost */
+  close(): void
+}
+/**
+ * Time-aligned stereo stream at 16kHz: microphone on the left, system audio
+ * on the right (for multichannel STT and post-meeting review)
+ */
+export declare class StereoMixer {
+  /**
+   * `path` records the mix to WAV/FLAC (`options.source` is ignored);
+   * without it the mix only goes to the `start` callback
+   */
+  constructor(path?: string | undefined | null, options?: RecordingOptions | undefined | null)
+  getSampleRate(): number
+  getChannels(): number
+  /**
+   * Start mixing; both captures run for the mixer whether or not their own
+   * `start` was called. `callback` receives interleaved stereo i16 PCM
+   * chunks of varying length and their `FrameInfo`.
+   */
+  start(microphone: MicrophoneCapture, systemAudio: SystemAudioCapture, callback?: (...args: any[]) => any | undefined | null): void
+  /**
+   * Finalize the mix; returns the paths of the files written. Captures
+   * that were only running for the mixer release their devices.
+   */
+  stop(): Array<string>
+}
+/** Subscription returned by `watchDevices` */
+export declare class DeviceWatcher {
+  /** Stop polling; the callback is not called again */
   stop(): void
 }
@@ -310,9 +310,16 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { SystemAudioCapture, MicrophoneCapture, getInputDevices, getOutputDevices } = nativeBinding
+const { SystemAudioCapture, MicrophoneCapture, FileAudioCapture, VirtualAudioInput, StereoMixer, getInputDevices, getOutputDevices, DeviceWatcher, watchDevices, setLogLevel, setLogSink } = nativeBinding
 
 module.exports.SystemAudioCapture = SystemAudioCapture
 module.exports.MicrophoneCapture = MicrophoneCapture
+module.exports.FileAudioCapture = FileAudioCapture
+module.exports.VirtualAudioInput = VirtualAudioInput
+module.exports.StereoMixer = StereoMixer
 module.exports.getInputDevices = getInputDevices
 module.exports.getOutputDevices = getOutputDevices
+module.exports.DeviceWatcher = DeviceWatcher
+module.exports.watchDevices = watchDevices
+module.exports.setLogLevel = setLogLevel
+module.exports.setLogSink = setLogSink
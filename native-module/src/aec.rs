@@ -0,0 +1,747 @@
+// Acoustic Echo Cancellation - system audio as far-end reference
+//
+// When the user is on speakers, the remote side played through the system
+// output leaks back into the microphone. Each SystemAudioCapture owns a
+// far-end reference; a microphone linked to it (`setEchoReference`) reads
+// the reference in lockstep from its `aec` stage and cancels the echo. The
+// system pipeline only publishes while a linked `aec` stage is reading.
+//
+// Algorithm:
+// 1. Delay estimation: GCC-PHAT cross-correlation between the recent near-end
+//    history and the far-end history (every ESTIMATE_INTERVAL frames)
+// 2. Cancellation: time-domain NLMS filter over the delay-aligned far-end
+// 3. Double-talk: Geigel detector freezes adaptation while the user speaks
+//
+// Both streams are mono at the output rate after resampling, so alignment
+// only has to absorb the acoustic path plus the difference in pipeline
+// latency. The reference only applies when both captures use the same
+// `sampleRate`; otherwise the canceller sees silence and passes audio
+// through.
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use realfft::num_complex::Complex;
+
+use crate::audio_config::SAMPLE_RATE;
+use crate::metrics::EchoStats;
+use crate::pipeline::{Frame, FrameProcessor, StageOutput};
+
+// ============================================================================
+// FAR-END REFERENCE
+// ============================================================================
+
+/// Far-end samples kept for readers (1s at 48kHz)
+const REFERENCE_CAPACITY: usize = 48_000;
+
+/// A reader further than this behind (or ahead of) the newest published
+/// sample resynchronises; smaller differences are absorbed by the delay
+/// estimate
+const MAX_READER_LAG_MS: u64 = 200;
+
+/// Scheduling margin on top of one published chunk: how far behind the
+/// newest sample a reader starts, so the two DSP threads can jitter
+/// against each other without the reader running out of published audio
+const READER_SAFETY_MS: u64 = 10;
+
+struct ReferenceBuffer {
+    samples: VecDeque<i16>,
+    /// Total samples ever published (absolute stream position)
+    total: u64,
+    /// Length of the latest published chunk (one far-end frame)
+    chunk: u64,
+    sample_rate: u32,
+}
+
+/// Far-end audio of one system audio capture, shared with the `aec` stages
+/// of the microphones linked to it
+pub struct FarEndReference {
+    buffer: Mutex<ReferenceBuffer>,
+    /// Live readers; nothing is published while there are none
+    readers: AtomicUsize,
+}
+
+impl Default for FarEndReference {
+    fn default() -> Self {
+        Self {
+            buffer: Mutex::new(ReferenceBuffer {
+                samples: VecDeque::with_capacity(REFERENCE_CAPACITY),
+                total: 0,
+                chunk: 0,
+                sample_rate: SAMPLE_RATE,
+            }),
+            readers: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl FarEndReference {
+    /// Whether any `aec` stage is reading
+    pub fn has_readers(&self) -> bool {
+        self.readers.load(Ordering::Relaxed) > 0
+    }
+
+    /// Publish far-end audio (called from the system audio DSP thread)
+    pub fn publish(&self, samples: &[i16], sample_rate: u32) {
+        let mut reference = self.buffer.lock().unwrap();
+        if reference.sample_rate != sample_rate {
+            // History at the old rate is useless to readers
+            reference.samples.clear();
+            reference.sample_rate = sample_rate;
+        }
+        reference.samples.extend(samples.iter().copied());
+        let excess = reference.samples.len().saturating_sub(REFERENCE_CAPACITY);
+        reference.samples.drain(0..excess);
+        reference.total += samples.len() as u64;
+        reference.chunk = samples.len() as u64;
+    }
+
+    /// Reader for a near end at `sample_rate`; a reference at any other
+    /// rate reads as silence. It locks on to the stream once the first
+    /// far-end audio is published.
+    pub fn reader(self: &Arc<Self>, sample_rate: u32) -> FarEndReader {
+        self.readers.fetch_add(1, Ordering::Relaxed);
+        FarEndReader {
+            next: None,
+            sample_rate,
+            reference: self.clone(),
+        }
+    }
+}
+
+/// Sequential reader over a far-end reference
+///
+/// The read position advances by exactly the samples read, whether or not
+/// they had been published, so far-end/near-end alignment stays fixed while
+/// the two DSP threads jitter. It only moves on its own (and the canceller
+/// has to re-estimate the delay) when the reader drifts more than
+/// `MAX_READER_LAG_MS` from the publisher, e.g. after either side stalls.
+pub struct FarEndReader {
+    reference: Arc<FarEndReference>,
+    /// Absolute position of the next sample; `None` until synchronised
+    next: Option<u64>,
+    sample_rate: u32,
+}
+
+impl Drop for FarEndReader {
+    fn drop(&mut self) {
+        self.reference.readers.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl FarEndReader {
+    /// Fill `out` with the next far-end samples, zero-padding those not
+    /// published (yet, or at all: system audio stopped or late)
+    pub fn read(&mut self, out: &mut [i16]) {
+        out.iter_mut().for_each(|s| *s = 0);
+        let reference = self.reference.buffer.lock().unwrap();
+        if reference.sample_rate != self.sample_rate || reference.chunk == 0 {
+            self.next = None;
+            return;
+        }
+
+        let max_lag = self.sample_rate as u64 * MAX_READER_LAG_MS / 1000;
+        let in_range = |next: u64| next.abs_diff(reference.total) <= max_lag;
+        let next = match self.next.filter(|&next| in_range(next)) {
+            Some(next) => next,
+            None => {
+                let safety = reference.chunk + self.sample_rate as u64 * READER_SAFETY_MS / 1000;
+                reference.total.saturating_sub(safety)
+            }
+        };
+
+        let oldest = reference.total - reference.samples.len() as u64;
+        let start = next.max(oldest);
+        let end = (next + out.len() as u64).min(reference.total);
+        if start < end {
+            let samples = reference.samples.range((start - oldest) as usize..(end - oldest) as usize);
+            for (dst, src) in out[(start - next) as usize..].iter_mut().zip(samples) {
+                *dst = *src;
+            }
+        }
+        self.next = Some(next + out.len() as u64);
+    }
+}
+
+/// Pipeline stage for the system audio source: publishes every frame while
+/// a linked microphone is reading
+pub struct FarEndPublisher {
+    reference: Arc<FarEndReference>,
+    sample_rate: u32,
+}
+
+impl FarEndPublisher {
+    pub fn new(reference: Arc<FarEndReference>, sample_rate: u32) -> Self {
+        Self { reference, sample_rate }
+    }
+}
+
+impl FrameProcessor for FarEndPublisher {
+    fn name(&self) -> &'static str {
+        "farEnd"
+    }
+
+    fn process(&mut self, frame: &mut Frame) -> StageOutput {
+        if frame.channels == 1 && self.reference.has_readers() {
+            self.reference.publish(&frame.samples, self.sample_rate);
+        }
+        StageOutput::Forward
+    }
+}
+
+// ============================================================================
+// ECHO CANCELLER
+// ============================================================================
+
+/// Near-end samples correlated per delay estimate
+const ESTIMATION_WINDOW: usize = 4096;
+
+/// Frames between delay estimates (~0.5s at 20ms frames)
+const ESTIMATE_INTERVAL: usize = 25;
+
+/// GCC-PHAT peak must exceed the mean correlation by this factor
+const MIN_PEAK_RATIO: f32 = 6.0;
+
+/// Geigel threshold: near-end louder than this fraction of the far-end
+/// peak is treated as double-talk
+const DOUBLE_TALK_RATIO: f32 = 0.6;
+
+/// Far-end RMS below this (f32 scale, ~-60dBFS) is treated as silence
+const FAR_ACTIVE_RMS: f32 = 0.001;
+
+#[derive(Debug, Clone)]
+pub struct EchoCancellerConfig {
+    /// Adaptive filter length in samples (echo tail covered after alignment)
+    pub tail_samples: usize,
+    /// Largest far-end -> near-end delay searched, in samples
+    pub max_delay_samples: usize,
+    /// NLMS step size (0..1]
+    pub step_size: f32,
+    /// Sample rate, only used to report the delay in ms
+    pub sample_rate: u32,
+}
+
+impl EchoCancellerConfig {
+    pub fn new(sample_rate: u32, tail_ms: u32, max_delay_ms: u32) -> Self {
+        Self {
+            tail_samples: (sample_rate * tail_ms / 1000).max(16) as usize,
+            max_delay_samples: (sample_rate * max_delay_ms / 1000) as usize,
+            step_size: 0.5,
+            sample_rate,
+        }
+    }
+}
+
+impl Default for EchoCancellerConfig {
+    /// 16ms tail, up to 500ms delay at 16kHz
+    fn default() -> Self {
+        Self::new(16_000, 16, 500)
+    }
+}
+
+/// NLMS echo canceller with GCC-PHAT delay estimation
+pub struct EchoCanceller {
+    config: EchoCancellerConfig,
+    weights: Vec<f32>,
+    /// Far-end history, newest last; the last frame is time-aligned with
+    /// the near-end frame being processed
+    far: Vec<f32>,
+    /// Near-end history (before cancellation) for delay estimation
+    near: Vec<f32>,
+    delay: Option<usize>,
+    frames_since_estimate: usize,
+    double_talk_hangover: usize,
+    near_power: f32,
+    out_power: f32,
+    stats: Arc<EchoStats>,
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+}
+
+impl EchoCanceller {
+    pub fn new(config: EchoCancellerConfig) -> Self {
+        let fft_len = (ESTIMATION_WINDOW + config.max_delay_samples).next_power_of_two();
+        let mut planner = RealFftPlanner::<f32>::new();
+
+        Self {
+            weights: vec![0.0; config.tail_samples],
+            far: Vec::with_capacity(Self::far_len(&config) + 1024),
+            near: Vec::with_capacity(ESTIMATION_WINDOW + 1024),
+            delay: None,
+            frames_since_estimate: 0,
+            double_talk_hangover: 0,
+            near_power: 0.0,
+            out_power: 0.0,
+            stats: Arc::new(EchoStats::default()),
+            fft: planner.plan_fft_forward(fft_len),
+            ifft: planner.plan_fft_inverse(fft_len),
+            config,
+        }
+    }
+
+    fn far_len(config: &EchoCancellerConfig) -> usize {
+        ESTIMATION_WINDOW + config.max_delay_samples + config.tail_samples
+    }
+
+    /// Shared handle for reading ERLE/delay from another thread
+    pub fn stats(&self) -> Arc<EchoStats> {
+        self.stats.clone()
+    }
+
+    /// Current delay estimate in samples
+    pub fn delay(&self) -> Option<usize> {
+        self.delay
+    }
+
+    /// Cancel the echo of `far` (time-aligned reference) from `near` in place
+    pub fn process(&mut self, near: &mut [i16], far: &[i16]) {
+        let frame_len = near.len();
+        push_history(&mut self.far, far.iter().map(|&s| s as f32 / 32768.0), Self::far_len(&self.config));
+        push_history(&mut self.near, near.iter().map(|&s| s as f32 / 32768.0), ESTIMATION_WINDOW);
+
+        let far_rms = rms(&self.far[self.far.len() - frame_len..]);
+        let far_active = far_rms > FAR_ACTIVE_RMS;
+
+        self.frames_since_estimate += 1;
+        if self.frames_since_estimate >= ESTIMATE_INTERVAL && far_active && self.far.len() >= Self::far_len(&self.config) {
+            self.frames_since_estimate = 0;
+            self.estimate_delay();
+        }
+
+        let Some(delay) = self.delay else {
+            return;
+        };
+
+        // Start the filter slightly before the correlation peak so small
+        // estimation errors do not cut off the direct path
+        let taps = self.config.tail_samples;
+        let lead = (taps / 8).min(delay);
+        let offset = delay - lead;
+        let base = self.far.len() - frame_len;
+        if base < offset + taps {
+            return;
+        }
+
+        let mut near_energy = 0.0;
+        let mut out_energy = 0.0;
+        let mut double_talk = false;
+
+        for (i, sample) in near.iter_mut().enumerate() {
+            let newest = base + i - offset;
+            let x = &self.far[newest + 1 - taps..=newest];
+
+            let d = *sample as f32 / 32768.0;
+            let mut y = 0.0;
+            let mut norm = 0.0;
+            let mut far_peak = 0.0f32;
+            // x is oldest-first; weights[0] applies to the newest sample
+            for (w, &xv) in self.weights.iter().zip(x.iter().rev()) {
+                y += w * xv;
+                norm += xv * xv;
+                far_peak = far_peak.max(xv.abs());
+            }
+            let e = d - y;
+
+            if far_active && d.abs() > DOUBLE_TALK_RATIO * far_peak {
+                self.double_talk_hangover = taps;
+            }
+
+            if self.double_talk_hangover > 0 {
+                self.double_talk_hangover -= 1;
+                double_talk = true;
+            } else if norm > 1e-6 {
+                let mu = self.config.step_size * e / (norm + 1e-6);
+                for (w, &xv) in self.weights.iter_mut().zip(x.iter().rev()) {
+                    *w += mu * xv;
+                }
+            }
+
+            near_energy += d * d;
+            out_energy += e * e;
+            *sample = (e * 32768.0).clamp(-32768.0, 32767.0) as i16;
+        }
+
+        // ERLE only means something while the far-end alone is talking
+        if far_active && !double_talk {
+            const SMOOTHING: f32 = 0.95;
+            self.near_power = SMOOTHING * self.near_power + (1.0 - SMOOTHING) * near_energy;
+            self.out_power = SMOOTHING * self.out_power + (1.0 - SMOOTHING) * out_energy;
+            if self.out_power > 0.0 {
+                self.stats.erle_db.store(10.0 * (self.near_power / self.out_power).log10());
+            }
+        }
+    }
+
+    /// GCC-PHAT between the near-end window and the far-end history
+    fn estimate_delay(&mut self) {
+        let max_delay = self.config.max_delay_samples;
+        let fft_len = self.fft.len();
+        let far_window = &self.far[self.far.len() - (ESTIMATION_WINDOW + max_delay)..];
+
+        let mut near_in = self.fft.make_input_vec();
+        near_in[..ESTIMATION_WINDOW].copy_from_slice(&self.near);
+        let mut far_in = self.fft.make_input_vec();
+        far_in[..far_window.len()].copy_from_slice(far_window);
+
+        let mut near_spec = self.fft.make_output_vec();
+        let mut far_spec = self.fft.make_output_vec();
+        if self.fft.process(&mut near_in, &mut near_spec).is_err()
+            || self.fft.process(&mut far_in, &mut far_spec).is_err()
+        {
+            return;
+        }
+
+        // r[j] = sum_n near[n] * far_window[n + j], phase-transform weighted
+        let mut cross: Vec<Complex<f32>> = near_spec.iter().zip(&far_spec)
+            .map(|(n, f)| {
+                let c = n.conj() * f;
+                c / (c.norm() + 1e-9)
+            })
+            .collect();
+        if let Some(first) = cross.first_mut() {
+            first.im = 0.0;
+        }
+        if let Some(last) = cross.last_mut() {
+            last.im = 0.0;
+        }
+
+        let mut correlation = self.ifft.make_output_vec();
+        if self.ifft.process(&mut cross, &mut correlation).is_err() {
+            return;
+        }
+
+        let lags = &correlation[..=max_delay.min(fft_len - 1)];
+        let (peak_index, peak) = lags.iter().enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, &v)| (i, v))
+            .unwrap_or((0, 0.0));
+        let mean = lags.iter().map(|v| v.abs()).sum::<f32>() / lags.len() as f32;
+
+        if mean <= 0.0 || peak / mean < MIN_PEAK_RATIO {
+            return;
+        }
+
+        // far_window[max_delay + n] is time-aligned with near[n]
+        let delay = max_delay - peak_index;
+        let moved = self.delay.map(|d| d.abs_diff(delay) > self.config.tail_samples / 4).unwrap_or(true);
+        if moved {
+            self.weights.iter_mut().for_each(|w| *w = 0.0);
+            self.near_power = 0.0;
+            self.out_power = 0.0;
+        }
+
+        self.delay = Some(delay);
+        self.stats.delay_ms.store(delay as f32 * 1000.0 / self.config.sample_rate as f32);
+        self.stats.converged.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset(&mut self) {
+        self.weights.iter_mut().for_each(|w| *w = 0.0);
+        self.far.clear();
+        self.near.clear();
+        self.delay = None;
+        self.frames_since_estimate = 0;
+        self.double_talk_hangover = 0;
+        self.near_power = 0.0;
+        self.out_power = 0.0;
+        self.stats.converged.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Append to a history buffer and keep only the newest `max_len` samples
+fn push_history(history: &mut Vec<f32>, samples: impl Iterator<Item = f32>, max_len: usize) {
+    history.extend(samples);
+    let excess = history.len().saturating_sub(max_len);
+    history.drain(0..excess);
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// Run the canceller over a recorded far-end/near-end pair (same rate,
+/// sample-aligned start), e.g. WAV pairs captured from a real meeting
+pub fn process_offline(
+    config: EchoCancellerConfig,
+    far: &[i16],
+    near: &[i16],
+    frame_len: usize,
+) -> (Vec<i16>, Arc<EchoStats>) {
+    let mut canceller = EchoCanceller::new(config);
+    let mut output = Vec::with_capacity(near.len());
+
+    for (near_frame, far_frame) in near.chunks_exact(frame_len).zip(far.chunks_exact(frame_len)) {
+        let mut frame = near_frame.to_vec();
+        canceller.process(&mut frame, far_frame);
+        output.extend(frame);
+    }
+
+    (output, canceller.stats())
+}
+
+/// `process_offline` over a far-end/near-end pair of mono 16-bit WAV files
+pub fn process_wav_pair(
+    config: EchoCancellerConfig,
+    far: &Path,
+    near: &Path,
+    frame_len: usize,
+) -> Result<(Vec<i16>, Arc<EchoStats>)> {
+    let read = |path: &Path| -> Result<Vec<i16>> {
+        let mut reader = hound::WavReader::open(path)?;
+        let spec = reader.spec();
+        if spec.channels != 1 || spec.bits_per_sample != 16 || spec.sample_rate != config.sample_rate {
+            return Err(anyhow::anyhow!(
+                "{}: expected mono 16-bit {}Hz, got {}ch {}-bit {}Hz",
+                path.display(), config.sample_rate, spec.channels, spec.bits_per_sample, spec.sample_rate,
+            ));
+        }
+        Ok(reader.samples::<i16>().collect::<Result<_, _>>()?)
+    };
+    let (far, near) = (read(far)?, read(near)?);
+    Ok(process_offline(config, &far, &near, frame_len))
+}
+
+/// Pipeline stage for the microphone source
+pub struct EchoCancellerStage {
+    canceller: EchoCanceller,
+    reader: FarEndReader,
+    far_frame: Vec<i16>,
+}
+
+impl EchoCancellerStage {
+    /// Cancel the audio of `reference` (a system audio capture's far end)
+    pub fn new(config: EchoCancellerConfig, reference: &Arc<FarEndReference>) -> Self {
+        Self {
+            reader: reference.reader(config.sample_rate),
+            canceller: EchoCanceller::new(config),
+            far_frame: Vec::new(),
+        }
+    }
+
+    pub fn stats(&self) -> Arc<EchoStats> {
+        self.canceller.stats()
+    }
+}
+
+impl FrameProcessor for EchoCancellerStage {
+    fn name(&self) -> &'static str {
+        "aec"
+    }
+
+    fn process(&mut self, frame: &mut Frame) -> StageOutput {
+        // The reference is mono; multi-channel frames pass through untouched
+        if frame.channels != 1 {
+            return StageOutput::Forward;
+        }
+
+        self.far_frame.resize(frame.samples.len(), 0);
+        self.reader.read(&mut self.far_frame);
+        self.canceller.process(&mut frame.samples, &self.far_frame);
+        StageOutput::Forward
+    }
+
+    fn reset(&mut self) {
+        self.canceller.reset();
+        self.reader = self.reader.reference.reader(self.reader.sample_rate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    const RATE: usize = 16_000;
+
+    /// Speech-like signal: low-passed noise gated by a ~4Hz syllable envelope
+    fn synthetic_speech(seconds: f32, seed: u64) -> Vec<f32> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut lowpassed = 0.0f32;
+        (0..(seconds * RATE as f32) as usize)
+            .map(|n| {
+                let white: f32 = rng.gen_range(-1.0..1.0);
+                lowpassed = 0.7 * lowpassed + 0.3 * white;
+                let envelope = (n as f32 * 4.0 * std::f32::consts::TAU / RATE as f32).sin().abs();
+                0.5 * lowpassed * (0.2 + envelope)
+            })
+            .collect()
+    }
+
+    fn to_i16(samples: &[f32]) -> Vec<i16> {
+        samples.iter().map(|&s| (s * 32767.0).clamp(-32768.0, 32767.0) as i16).collect()
+    }
+
+    fn power(samples: &[i16]) -> f32 {
+        samples.iter().map(|&s| (s as f32).powi(2)).sum::<f32>() / samples.len() as f32
+    }
+
+    /// far-end speech, echoed 100ms later through a short room response;
+    /// the near-end talker joins for the last 2 seconds
+    fn synthetic_pair() -> (Vec<i16>, Vec<i16>, Vec<i16>) {
+        let delay = RATE / 10;
+        let far = synthetic_speech(8.0, 1);
+        let talker = synthetic_speech(8.0, 2);
+        let room = [0.5f32, 0.25, -0.1, 0.05];
+
+        let mut near = vec![0.0f32; far.len()];
+        for n in delay..far.len() {
+            for (k, h) in room.iter().enumerate() {
+                if n >= delay + k {
+                    near[n] += h * far[n - delay - k];
+                }
+            }
+        }
+        let talker_start = 6 * RATE;
+        let mut talker_only = vec![0.0f32; far.len()];
+        talker_only[talker_start..].copy_from_slice(&talker[talker_start..]);
+        for (n, t) in near.iter_mut().zip(&talker_only) {
+            *n += t;
+        }
+
+        (to_i16(&far), to_i16(&near), to_i16(&talker_only))
+    }
+
+    fn write_wav(path: &Path, samples: &[i16]) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: RATE as u32,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        samples.iter().for_each(|&s| writer.write_sample(s).unwrap());
+        writer.finalize().unwrap();
+    }
+
+    /// Cancel the synthetic pair after a round trip through WAV files, as a
+    /// recorded meeting would be; returns (near, output, talker, stats)
+    fn cancel_wav_pair(name: &str) -> (Vec<i16>, Vec<i16>, Vec<i16>, Arc<EchoStats>) {
+        let dir = std::env::temp_dir().join(format!("natively-aec-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (far, near, talker) = synthetic_pair();
+        let (far_path, near_path) = (dir.join("far.wav"), dir.join("near.wav"));
+        write_wav(&far_path, &far);
+        write_wav(&near_path, &near);
+
+        let (output, stats) = process_wav_pair(EchoCancellerConfig::default(), &far_path, &near_path, 320).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        (near, output, talker, stats)
+    }
+
+    #[test]
+    fn test_delay_is_estimated() {
+        let (_, _, _, stats) = cancel_wav_pair("delay");
+
+        assert!(stats.converged.load(Ordering::Relaxed));
+        assert!((stats.delay_ms.load() - 100.0).abs() <= 2.0, "delay {}ms", stats.delay_ms.load());
+    }
+
+    #[test]
+    fn test_echo_is_cancelled() {
+        let (near, output, _, stats) = cancel_wav_pair("erle");
+
+        // Far-end only segment after convergence
+        let segment = 3 * RATE..6 * RATE;
+        let erle = 10.0 * (power(&near[segment.clone()]) / power(&output[segment])).log10();
+        assert!(erle > 20.0, "measured ERLE {:.1}dB", erle);
+        assert!(stats.erle_db.load() > 15.0, "reported ERLE {:.1}dB", stats.erle_db.load());
+    }
+
+    #[test]
+    fn test_near_end_talker_survives_double_talk() {
+        let (_, output, talker, _) = cancel_wav_pair("double-talk");
+
+        let segment = 6 * RATE + RATE / 2..8 * RATE;
+        let ratio_db = 10.0 * (power(&output[segment.clone()]) / power(&talker[segment])).log10();
+        assert!(ratio_db.abs() < 3.0, "talker level changed by {:.1}dB", ratio_db);
+    }
+
+    #[test]
+    fn test_reference_is_only_published_while_read() {
+        let reference = Arc::new(FarEndReference::default());
+        let mut publisher = FarEndPublisher::new(reference.clone(), 16000);
+        let mut frame = |value: i16, count: usize| {
+            let mut frame = Frame {
+                samples: vec![value; count],
+                channels: 1,
+                sample_offset: 0,
+                captured_at: std::time::Instant::now(),
+                payload: None,
+            };
+            publisher.process(&mut frame);
+        };
+
+        frame(5, 100);
+        assert_eq!(reference.buffer.lock().unwrap().total, 0);
+
+        let mut reader = reference.reader(16000);
+        frame(7, 100);
+        let mut out = vec![1i16; 160];
+        reader.read(&mut out);
+        assert!(out[..100].iter().all(|&s| s == 7));
+        assert!(out[100..].iter().all(|&s| s == 0));
+
+        frame(9, 160);
+        reader.read(&mut out);
+        assert!(out[..60].iter().all(|&s| s == 9));
+
+        drop(reader);
+        assert!(!reference.has_readers());
+    }
+
+    #[test]
+    fn test_reader_alignment_survives_thread_jitter() {
+        // 20ms far-end frames against 10ms near-end frames, at 4x speed
+        const FAR_FRAME: usize = 320;
+        const NEAR_FRAME: usize = 160;
+        const FRAMES: usize = 100;
+        let period = std::time::Duration::from_millis(5);
+        let reference = Arc::new(FarEndReference::default());
+        let mut reader = reference.reader(16000);
+        let start = std::time::Instant::now() + period;
+
+        // Both sides run at the same nominal rate, each woken up to 80% of
+        // a frame late; the far end is a ramp so positions can be recovered
+        let run = move |seed: u64, frames: usize, mut step: Box<dyn FnMut(usize) + Send>| {
+            let period = period * FRAMES as u32 / frames as u32;
+            std::thread::spawn(move || {
+                let mut rng = StdRng::seed_from_u64(seed);
+                for k in 0..frames {
+                    let jitter = period.mul_f32(rng.gen_range(0.0..0.8));
+                    let due = start + period * k as u32 + jitter;
+                    std::thread::sleep(due.saturating_duration_since(std::time::Instant::now()));
+                    step(k);
+                }
+            })
+        };
+        let publisher_reference = reference.clone();
+        let publisher = run(1, FRAMES, Box::new(move |k| {
+            let ramp: Vec<i16> = (0..FAR_FRAME).map(|i| ((k * FAR_FRAME + i) % 30_000 + 1) as i16).collect();
+            publisher_reference.publish(&ramp, 16000);
+        }));
+        let (tx, rx) = std::sync::mpsc::channel();
+        let consumer = run(2, FRAMES * FAR_FRAME / NEAR_FRAME, Box::new(move |_| {
+            let mut out = vec![0i16; NEAR_FRAME];
+            reader.read(&mut out);
+            tx.send(out).unwrap();
+        }));
+        publisher.join().unwrap();
+        consumer.join().unwrap();
+
+        let read: Vec<i16> = rx.iter().flatten().collect();
+        let offsets: Vec<i64> = read.iter().enumerate()
+            .filter(|(_, &s)| s != 0)
+            .map(|(i, &s)| (s as i64 - i as i64).rem_euclid(30_000))
+            .collect();
+        assert!(offsets.len() > read.len() / 2, "only {} of {} samples read", offsets.len(), read.len());
+        assert!(offsets.windows(2).all(|w| w[0] == w[1]), "alignment moved while reading");
+    }
+}
@@ -0,0 +1,236 @@
+// Automatic Gain Control
+//
+// Brings microphone and system audio to a consistent loudness before STT.
+//
+// Per frame:
+// 1. Measure frame RMS in dBFS; frames below the gate (silence, keepalive
+//    frames from suppression) hold the current gain instead of boosting noise
+// 2. Move the gain toward `target - level`, fast when reducing (attack),
+//    slow when increasing (release), clamped to [MIN_GAIN_DB, max_gain_db]
+// 3. Ramp the gain across the frame to avoid zipper noise
+// 4. Peak limiter at LIMITER_CEILING_DBFS (instant attack) so boosted
+//    transients never clip
+//
+// The applied gain is published through `AgcStats` for the UI.
+
+use std::sync::Arc;
+
+use crate::metrics::AgcStats;
+use crate::pipeline::{Frame, FrameProcessor, StageOutput};
+
+/// Frames quieter than this never change the gain
+const GATE_DBFS: f32 = -60.0;
+
+/// Most attenuation AGC applies to a hot signal
+const MIN_GAIN_DB: f32 = -20.0;
+
+/// Limiter ceiling
+const LIMITER_CEILING_DBFS: f32 = -1.0;
+
+/// Limiter gain recovery time
+const LIMITER_RELEASE_MS: f32 = 50.0;
+
+#[derive(Debug, Clone)]
+pub struct AgcConfig {
+    pub sample_rate: u32,
+    /// Loudness the output is steered to (frame RMS, dBFS)
+    pub target_dbfs: f32,
+    /// Largest boost applied to quiet sources
+    pub max_gain_db: f32,
+    /// Time constant for reducing gain
+    pub attack_ms: f32,
+    /// Time constant for increasing gain
+    pub release_ms: f32,
+    /// Peak limiter after the gain (otherwise hard clip)
+    pub limiter: bool,
+}
+
+impl AgcConfig {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            target_dbfs: -20.0,
+            max_gain_db: 30.0,
+            attack_ms: 20.0,
+            release_ms: 1000.0,
+            limiter: true,
+        }
+    }
+}
+
+pub struct AgcStage {
+    config: AgcConfig,
+    gain_db: f32,
+    /// Linear gain at the end of the previous frame (ramp start)
+    last_gain: f32,
+    /// Limiter gain reduction envelope (1.0 = no reduction)
+    limiter_gain: f32,
+    limiter_release: f32,
+    ceiling: f32,
+    stats: Arc<AgcStats>,
+}
+
+impl AgcStage {
+    pub fn new(config: AgcConfig) -> Self {
+        let limiter_release = time_coefficient(1000.0 / config.sample_rate as f32, LIMITER_RELEASE_MS);
+
+        Self {
+            config,
+            gain_db: 0.0,
+            last_gain: 1.0,
+            limiter_gain: 1.0,
+            limiter_release,
+            ceiling: db_to_linear(LIMITER_CEILING_DBFS) * 32767.0,
+            stats: Arc::new(AgcStats::default()),
+        }
+    }
+
+    pub fn stats(&self) -> Arc<AgcStats> {
+        self.stats.clone()
+    }
+
+    fn update_gain(&mut self, samples: &[i16], frame_ms: f32) {
+        let energy: f64 = samples.iter().map(|&s| (s as f64).powi(2)).sum();
+        let rms = (energy / samples.len().max(1) as f64).sqrt() as f32 / 32768.0;
+        let level_dbfs = 20.0 * rms.max(1e-9).log10();
+
+        if level_dbfs < GATE_DBFS {
+            return;
+        }
+
+        let desired = (self.config.target_dbfs - level_dbfs)
+            .clamp(MIN_GAIN_DB, self.config.max_gain_db.max(MIN_GAIN_DB));
+        let time_ms = if desired < self.gain_db { self.config.attack_ms } else { self.config.release_ms };
+        let coef = time_coefficient(frame_ms, time_ms);
+        self.gain_db = coef * self.gain_db + (1.0 - coef) * desired;
+    }
+
+    fn apply(&mut self, samples: &mut [i16]) {
+        let target = db_to_linear(self.gain_db);
+        let step = (target - self.last_gain) / samples.len().max(1) as f32;
+        let mut gain = self.last_gain;
+
+        for sample in samples.iter_mut() {
+            gain += step;
+            let mut value = *sample as f32 * gain;
+
+            if self.config.limiter {
+                // Recover toward unity, then clamp instantly if still too hot
+                self.limiter_gain = 1.0 - self.limiter_release * (1.0 - self.limiter_gain);
+                if value.abs() * self.limiter_gain > self.ceiling {
+                    self.limiter_gain = self.ceiling / value.abs();
+                }
+                value *= self.limiter_gain;
+            }
+
+            *sample = value.clamp(-32768.0, 32767.0) as i16;
+        }
+
+        self.last_gain = target;
+    }
+}
+
+impl FrameProcessor for AgcStage {
+    fn name(&self) -> &'static str {
+        "agc"
+    }
+
+    fn process(&mut self, frame: &mut Frame) -> StageOutput {
+        let per_channel = frame.samples.len() / frame.channels.max(1);
+        let frame_ms = per_channel as f32 * 1000.0 / self.config.sample_rate as f32;
+
+        self.update_gain(&frame.samples, frame_ms);
+        self.apply(&mut frame.samples);
+        self.stats.gain_db.store(self.gain_db);
+
+        StageOutput::Forward
+    }
+
+    fn reset(&mut self) {
+        self.gain_db = 0.0;
+        self.last_gain = 1.0;
+        self.limiter_gain = 1.0;
+        self.stats.gain_db.store(0.0);
+    }
+}
+
+fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// One-pole smoothing coefficient for a step of `step_ms` and time constant `time_ms`
+fn time_coefficient(step_ms: f32, time_ms: f32) -> f32 {
+    if time_ms <= 0.0 {
+        0.0
+    } else {
+        (-step_ms / time_ms).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine_frames(amplitude: f32, seconds: f32) -> Vec<Vec<i16>> {
+        let total = (16000.0 * seconds) as usize;
+        let samples: Vec<i16> = (0..total)
+            .map(|n| (amplitude * 32767.0 * (n as f32 * 440.0 * std::f32::consts::TAU / 16000.0).sin()) as i16)
+            .collect();
+        samples.chunks(320).map(|c| c.to_vec()).collect()
+    }
+
+    fn rms_dbfs(samples: &[i16]) -> f32 {
+        let energy: f32 = samples.iter().map(|&s| (s as f32 / 32768.0).powi(2)).sum();
+        10.0 * (energy / samples.len() as f32).log10()
+    }
+
+    fn run(stage: &mut AgcStage, frames: Vec<Vec<i16>>) -> Vec<Frame> {
+        frames.into_iter()
+            .map(|samples| {
+                let mut frame = Frame { samples, channels: 1, sample_offset: 0, captured_at: std::time::Instant::now(), payload: None };
+                stage.process(&mut frame);
+                frame
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_quiet_and_loud_sources_converge_to_target() {
+        for amplitude in [0.005, 0.8] {
+            let mut stage = AgcStage::new(AgcConfig::new(16000));
+            let frames = run(&mut stage, sine_frames(amplitude, 6.0));
+            let level = rms_dbfs(&frames.last().unwrap().samples);
+            assert!((level + 20.0).abs() < 1.5, "amplitude {} settled at {:.1}dBFS", amplitude, level);
+        }
+    }
+
+    #[test]
+    fn test_gain_is_capped_and_reported() {
+        let mut config = AgcConfig::new(16000);
+        config.max_gain_db = 10.0;
+        let mut stage = AgcStage::new(config);
+        let stats = stage.stats();
+
+        run(&mut stage, sine_frames(0.005, 6.0));
+        assert!((stats.gain_db.load() - 10.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_silence_does_not_raise_gain() {
+        let mut stage = AgcStage::new(AgcConfig::new(16000));
+        run(&mut stage, vec![vec![0i16; 320]; 200]);
+        assert_eq!(stage.stats().gain_db.load(), 0.0);
+    }
+
+    #[test]
+    fn test_limiter_holds_ceiling_on_transients() {
+        let mut stage = AgcStage::new(AgcConfig::new(16000));
+        // Settle a high gain on a quiet source, then hit it with a loud burst
+        run(&mut stage, sine_frames(0.01, 4.0));
+        let frames = run(&mut stage, sine_frames(0.9, 0.04));
+
+        let ceiling = db_to_linear(LIMITER_CEILING_DBFS) * 32767.0;
+        let peak = frames.iter().flat_map(|f| f.samples.iter()).map(|&s| (s as f32).abs()).fold(0.0, f32::max);
+        assert!(peak <= ceiling + 1.0, "peak {} over ceiling {}", peak, ceiling);
+    }
+}
@@ -1,10 +1,10 @@
 // Audio Configuration Constants
 // Optimized for low-latency streaming STT
 
-/// Output sample rate for Google STT
+/// Default output sample rate (captures can override it with `sampleRate`)
 pub const SAMPLE_RATE: u32 = 16_000;
 
-/// Frame duration in milliseconds
+/// Default frame duration in milliseconds (`frameMs` overrides it)
 /// 20ms provides good balance of latency vs overhead
 /// - Old: 100ms = 100ms minimum latency
 /// - New: 20ms = 20ms minimum latency
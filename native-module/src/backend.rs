@@ -0,0 +1,158 @@
+// Capture Backends - the common interface of every audio source
+//
+// cpal microphones, virtual inputs, the per-OS system audio backends and
+// file playback all end the same way: a lock-free ring buffer of f32 samples
+// at some device rate, drained by a DSP thread. `CaptureBackend` is that
+// contract. Opening is backend specific (`microphone::open`, `speaker::open`,
+// `file_source::FileBackend::open`); after that the capture classes, the
+// runner and the device monitor only see `Box<dyn CaptureBackend>`.
+//
+// Backends are not `Send`: each is started, stopped and dropped on the
+// thread that opened it (the JS thread, or the device monitor thread in
+// follow mode).
+
+use anyhow::Result;
+use ringbuf::HeapCons;
+
+use crate::pipeline::RateHandle;
+
+/// What a backend can do
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackendCapabilities {
+    /// Name as accepted by the `backend` option, e.g. "cpal", "screenCaptureKit"
+    pub name: &'static str,
+    /// `stop` pauses and a later `start` resumes on the same consumer;
+    /// otherwise capture runs until the backend is dropped
+    pub pausable: bool,
+    /// The device rate can change while running (see `sample_rate`)
+    pub variable_rate: bool,
+    /// Captures the device it was opened on rather than whatever the system
+    /// plays
+    pub selects_device: bool,
+}
+
+pub trait CaptureBackend {
+    fn capabilities(&self) -> BackendCapabilities;
+
+    /// Start (or resume) filling the ring buffer. Backends that capture from
+    /// the moment they open treat this as a no-op.
+    fn start(&mut self) -> Result<()>;
+
+    /// Pause filling the ring buffer; a no-op for backends that can't pause
+    /// (see `BackendCapabilities::pausable`)
+    fn stop(&mut self) -> Result<()>;
+
+    /// Device rate; backends with a variable rate keep it current
+    fn sample_rate(&self) -> RateHandle;
+
+    /// Interleaved channels per frame in the ring buffer
+    fn channels(&self) -> usize;
+
+    /// The ring buffer consumer; handed out once
+    fn take_consumer(&mut self) -> Option<HeapCons<f32>>;
+
+    /// Id of the device actually opened, where the backend knows it
+    fn device_id(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Start a freshly opened backend and take its consumer
+pub fn start_and_take(backend: &mut dyn CaptureBackend) -> Result<HeapCons<f32>> {
+    backend.start()?;
+    backend.take_consumer()
+        .ok_or_else(|| anyhow::anyhow!("{} backend has no consumer", backend.capabilities().name))
+}
+
+/// `backend` option of `MicrophoneCapture`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum InputBackend {
+    /// cpal, or a virtual device when the id names one
+    #[default]
+    Auto,
+    Cpal,
+}
+
+impl InputBackend {
+    /// Parse the JS-facing name ("auto" | "cpal")
+    pub fn from_name(name: Option<&str>) -> Result<Self> {
+        match name {
+            None | Some("auto") => Ok(InputBackend::Auto),
+            Some("cpal") => Ok(InputBackend::Cpal),
+            Some(other) => Err(anyhow::anyhow!("Unknown microphone backend: {}", other)),
+        }
+    }
+}
+
+/// `backend` option of `SystemAudioCapture`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputBackend {
+    /// The platform default (CoreAudio tap falling back to ScreenCaptureKit
+    /// on macOS, WASAPI loopback on Windows, PulseAudio monitors on Linux)
+    #[default]
+    Auto,
+    CoreAudioTap,
+    ScreenCaptureKit,
+    Wasapi,
+    PulseAudio,
+}
+
+impl OutputBackend {
+    /// Parse the JS-facing name ("auto" | "coreAudioTap" |
+    /// "screenCaptureKit" | "wasapi" | "pulseAudio"), rejecting backends
+    /// this platform doesn't have
+    pub fn from_name(name: Option<&str>) -> Result<Self> {
+        let backend = match name {
+            None | Some("auto") => OutputBackend::Auto,
+            Some("coreAudioTap") => OutputBackend::CoreAudioTap,
+            Some("screenCaptureKit") => OutputBackend::ScreenCaptureKit,
+            Some("wasapi") => OutputBackend::Wasapi,
+            Some("pulseAudio") => OutputBackend::PulseAudio,
+            Some(other) => return Err(anyhow::anyhow!("Unknown system audio backend: {}", other)),
+        };
+        if !backend.is_available() {
+            return Err(anyhow::anyhow!("System audio backend {} is not available on this platform", backend.name()));
+        }
+        Ok(backend)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputBackend::Auto => "auto",
+            OutputBackend::CoreAudioTap => "coreAudioTap",
+            OutputBackend::ScreenCaptureKit => "screenCaptureKit",
+            OutputBackend::Wasapi => "wasapi",
+            OutputBackend::PulseAudio => "pulseAudio",
+        }
+    }
+
+    fn is_available(self) -> bool {
+        match self {
+            OutputBackend::Auto => true,
+            OutputBackend::CoreAudioTap | OutputBackend::ScreenCaptureKit => cfg!(target_os = "macos"),
+            OutputBackend::Wasapi => cfg!(target_os = "windows"),
+            OutputBackend::PulseAudio => cfg!(target_os = "linux"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_names_are_checked_against_the_platform() {
+        assert_eq!(OutputBackend::from_name(None).unwrap(), OutputBackend::Auto);
+        assert!(OutputBackend::from_name(Some("sck")).is_err());
+        assert_eq!(
+            OutputBackend::from_name(Some("pulseAudio")).is_ok(),
+            cfg!(target_os = "linux")
+        );
+        assert_eq!(
+            OutputBackend::from_name(Some("screenCaptureKit")).is_ok(),
+            cfg!(target_os = "macos")
+        );
+        assert_eq!(InputBackend::from_name(Some("cpal")).unwrap(), InputBackend::Cpal);
+        assert!(InputBackend::from_name(Some("wasapi")).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+// Channel Mixing - Real-Time Safe
+//
+// Reduces interleaved multi-channel device frames to what the DSP thread
+// expects. Runs inside the CPAL callback, so:
+// - No allocations after construction
+// - No locks, only lock-free ring buffer pushes
+// - Every sample format is converted to f32 [-1.0, 1.0] via cpal's Sample trait
+
+use anyhow::Result;
+use cpal::{FromSample, Sample};
+use ringbuf::traits::Producer;
+
+/// How multi-channel input is reduced before it reaches the ring buffer
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ChannelPolicy {
+    /// Average all channels into mono (default)
+    #[default]
+    Downmix,
+    /// Take a single channel (0-based)
+    Channel(usize),
+    /// Follow the channel carrying the most energy (array mics, interfaces
+    /// where the voice sits on an unknown input)
+    MaxEnergy,
+    /// Keep every channel, interleaved
+    KeepAll,
+}
+
+impl ChannelPolicy {
+    /// Parse the JS-facing policy name ("downmix" | "channel" | "maxEnergy" | "all")
+    pub fn from_options(policy: Option<&str>, channel: Option<u32>) -> Result<Self> {
+        match policy {
+            None | Some("downmix") => Ok(ChannelPolicy::Downmix),
+            Some("channel") => Ok(ChannelPolicy::Channel(channel.unwrap_or(0) as usize)),
+            Some("maxEnergy") => Ok(ChannelPolicy::MaxEnergy),
+            Some("all") => Ok(ChannelPolicy::KeepAll),
+            Some(other) => Err(anyhow::anyhow!("Unknown channel policy: {}", other)),
+        }
+    }
+
+    /// Number of channels written to the ring buffer for a device with
+    /// `input_channels` channels
+    pub fn output_channels(&self, input_channels: usize) -> usize {
+        match self {
+            ChannelPolicy::KeepAll => input_channels,
+            _ => 1,
+        }
+    }
+}
+
+/// A challenger must carry this much more energy than the current channel
+/// before MaxEnergy switches (~3dB, avoids flapping between equal channels)
+const SWITCH_RATIO: f32 = 2.0;
+
+/// Stateful channel reducer owned by the capture callback
+pub struct ChannelMixer {
+    policy: ChannelPolicy,
+    channels: usize,
+    /// Per-channel energy of the current callback block (MaxEnergy only)
+    energy: Vec<f32>,
+    selected: usize,
+}
+
+impl ChannelMixer {
+    pub fn new(policy: ChannelPolicy, channels: usize) -> Result<Self> {
+        if let ChannelPolicy::Channel(index) = policy {
+            if index >= channels {
+                return Err(anyhow::anyhow!(
+                    "Channel {} out of range (device has {} channels)", index, channels
+                ));
+            }
+        }
+
+        Ok(Self {
+            policy,
+            channels: channels.max(1),
+            energy: vec![0.0; channels.max(1)],
+            selected: 0,
+        })
+    }
+
+    pub fn output_channels(&self) -> usize {
+        self.policy.output_channels(self.channels)
+    }
+
+    /// Reduce one interleaved callback block and push it to the ring
+    /// buffer; returns the samples pushed
+    pub fn push<T, P>(&mut self, data: &[T], producer: &mut P) -> usize
+    where
+        T: Sample,
+        f32: FromSample<T>,
+        P: Producer<Item = f32>,
+    {
+        let channels = self.channels;
+        let mut pushed = 0;
+
+        if channels == 1 {
+            for &sample in data {
+                pushed += producer.try_push(sample.to_sample::<f32>()).is_ok() as usize;
+            }
+            return pushed;
+        }
+
+        match self.policy {
+            ChannelPolicy::Downmix => {
+                let scale = 1.0 / channels as f32;
+                for frame in data.chunks_exact(channels) {
+                    let sum: f32 = frame.iter().map(|&s| s.to_sample::<f32>()).sum();
+                    pushed += producer.try_push(sum * scale).is_ok() as usize;
+                }
+            }
+            ChannelPolicy::Channel(index) => {
+                for frame in data.chunks_exact(channels) {
+                    pushed += producer.try_push(frame[index].to_sample::<f32>()).is_ok() as usize;
+                }
+            }
+            ChannelPolicy::MaxEnergy => {
+                self.update_selection(data);
+                let index = self.selected;
+                for frame in data.chunks_exact(channels) {
+                    pushed += producer.try_push(frame[index].to_sample::<f32>()).is_ok() as usize;
+                }
+            }
+            ChannelPolicy::KeepAll => {
+                for frame in data.chunks_exact(channels) {
+                    // Never push a partial frame - it would shift every channel after it
+                    if producer.vacant_len() < channels {
+                        break;
+                    }
+                    for &sample in frame {
+                        pushed += producer.try_push(sample.to_sample::<f32>()).is_ok() as usize;
+                    }
+                }
+            }
+        }
+        pushed
+    }
+
+    fn update_selection<T>(&mut self, data: &[T])
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        self.energy.iter_mut().for_each(|e| *e = 0.0);
+        for frame in data.chunks_exact(self.channels) {
+            for (energy, &sample) in self.energy.iter_mut().zip(frame) {
+                let s = sample.to_sample::<f32>();
+                *energy += s * s;
+            }
+        }
+
+        let (loudest, &max_energy) = self.energy.iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap_or((0, &0.0));
+
+        if loudest != self.selected && max_energy > self.energy[self.selected] * SWITCH_RATIO {
+            self.selected = loudest;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ringbuf::{traits::{Consumer, Observer, Split}, HeapRb};
+
+    fn run<T>(mixer: &mut ChannelMixer, data: &[T]) -> Vec<f32>
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        let (mut producer, mut consumer) = HeapRb::<f32>::new(1024).split();
+        mixer.push(data, &mut producer);
+        consumer.pop_iter().collect()
+    }
+
+    #[test]
+    fn test_downmix_averages_channels() {
+        let mut mixer = ChannelMixer::new(ChannelPolicy::Downmix, 2).unwrap();
+        let out = run(&mut mixer, &[0.5f32, 0.0, 0.5, 0.0]);
+        assert_eq!(out, vec![0.25, 0.25]);
+    }
+
+    #[test]
+    fn test_max_energy_follows_voice_channel() {
+        // Voice on channel 2 of a 4-channel interface, silence elsewhere
+        let mut mixer = ChannelMixer::new(ChannelPolicy::MaxEnergy, 4).unwrap();
+        let data: Vec<i16> = (0..64).flat_map(|_| [0i16, 0, 16384, 0]).collect();
+        let out = run(&mut mixer, &data);
+        assert_eq!(out.len(), 64);
+        assert!(out.iter().all(|&s| (s - 0.5).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_unsigned_formats_are_centered() {
+        let mut mixer = ChannelMixer::new(ChannelPolicy::Channel(1), 2).unwrap();
+        let out = run(&mut mixer, &[0u8, 128u8, 255u8, 128u8]);
+        assert_eq!(out, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_keep_all_never_splits_frames() {
+        let mut mixer = ChannelMixer::new(ChannelPolicy::KeepAll, 3).unwrap();
+        let (mut producer, consumer) = HeapRb::<f32>::new(4).split();
+        assert_eq!(mixer.push(&[0.1f32, 0.2, 0.3, 0.4, 0.5, 0.6], &mut producer), 3);
+        assert_eq!(consumer.occupied_len(), 3);
+    }
+
+    #[test]
+    fn test_channel_out_of_range_is_rejected() {
+        assert!(ChannelMixer::new(ChannelPolicy::Channel(2), 2).is_err());
+    }
+}
@@ -0,0 +1,361 @@
+// Frame Delivery - metadata handed to JS with every frame
+//
+// The frame callback receives the frame bytes plus a `FrameInfo`: a sequence
+// number counting delivered frames, the frame's position on the stream
+// timeline, and when its first sample was captured by the host clock.
+// Frames a stage drops (silence suppression, a failed encode) still advance
+// the timeline, so the first frame delivered after a suppressed span reports
+// the span as `gapSamples`/`gapMs` instead of JS having to infer it from
+// byte counts.
+//
+// Frame data is moved into an externally backed `Buffer`, `Int16Array` or
+// `Float32Array` rather than converted to a JS array and copied again by the
+// wrapper (runtimes that refuse external memory, such as Electron's V8
+// sandbox, get a single copy instead). i16 PCM on a little-endian host is
+// already the `Buffer`'s bytes, so the sample allocation itself backs the
+// `Buffer` and is never re-encoded. With `batchMs` the `FrameBatcher`
+// concatenates contiguous frames so JS sees fewer, larger callbacks; a gap
+// always starts a new batch so `FrameInfo` stays exact.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+
+use crate::pipeline::{Frame, FrameSink, OutputFormat, SampleFormat};
+
+/// Longest accepted `batchMs`
+pub const MAX_BATCH_MS: u32 = 1000;
+
+/// JS type frames are delivered as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameType {
+    /// `Buffer` of little-endian bytes in the configured sample format
+    #[default]
+    Buffer,
+    /// `Int16Array` (i16) or `Float32Array` (f32)
+    TypedArray,
+}
+
+/// How frames reach the JS callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DeliveryConfig {
+    pub frame_type: FrameType,
+    /// Deliver contiguous frames together every `batch_ms`; 0 delivers each
+    /// frame on its own
+    pub batch_ms: u32,
+}
+
+impl DeliveryConfig {
+    /// Parse the JS-facing options; anything left out keeps the default
+    pub fn from_options(frame_type: Option<&str>, batch_ms: Option<u32>) -> Result<Self> {
+        let frame_type = match frame_type {
+            None | Some("buffer") => FrameType::Buffer,
+            Some("typedArray") => FrameType::TypedArray,
+            Some(other) => return Err(anyhow::anyhow!("Unknown frame type: {} (use \"buffer\" or \"typedArray\")", other)),
+        };
+        let batch_ms = batch_ms.unwrap_or(0);
+        if batch_ms > MAX_BATCH_MS {
+            return Err(anyhow::anyhow!("batchMs must be at most {}, got {}", MAX_BATCH_MS, batch_ms));
+        }
+        Ok(Self { frame_type, batch_ms })
+    }
+}
+
+/// Second argument of the frame callback
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameInfo {
+    /// Delivered frames before this one since `start` (no holes)
+    pub seq: f64,
+    /// Output-rate sample index (per channel) of the first sample
+    pub sample_offset: f64,
+    /// `sampleOffset` in milliseconds of stream time (the clock of
+    /// `CaptureEvent.timestampMs`)
+    pub timestamp_ms: f64,
+    /// Host wall-clock time the first sample was captured, in ms since the
+    /// Unix epoch (comparable with `Date.now()`)
+    pub captured_at_ms: f64,
+    /// Samples (per channel) suppressed since the previous delivered frame,
+    /// or since the stream started for the first one
+    pub gap_samples: f64,
+    /// `gapSamples` in milliseconds
+    pub gap_ms: f64,
+    /// Frames concatenated in this delivery (1 unless `batchMs` is set);
+    /// `seq` and the offsets describe the first
+    pub frames: f64,
+}
+
+/// Frame data as it is handed to JS, without further copies
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameData {
+    /// PCM bytes in the configured sample format, or Opus bytes
+    Bytes(Vec<u8>),
+    /// i16 PCM for a `Buffer`, which takes over the allocation; only built
+    /// on little-endian hosts, where its bytes are little-endian PCM
+    I16Bytes(Vec<i16>),
+    I16(Vec<i16>),
+    F32(Vec<f32>),
+}
+
+impl FrameData {
+    /// `samples` as `Buffer` data in `output`'s sample format
+    pub fn buffer(output: &OutputFormat, samples: Vec<i16>) -> Self {
+        match output.sample_format {
+            SampleFormat::I16 if cfg!(target_endian = "little") => FrameData::I16Bytes(samples),
+            _ => FrameData::Bytes(output.encode(&samples)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            FrameData::Bytes(bytes) => bytes.len(),
+            FrameData::I16Bytes(samples) => std::mem::size_of_val(samples.as_slice()),
+            FrameData::I16(samples) => samples.len(),
+            FrameData::F32(samples) => samples.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A frame (or batch of frames) on its way to the JS callback
+#[derive(Debug, Clone)]
+pub struct DeliveredFrame {
+    pub data: FrameData,
+    pub info: FrameInfo,
+}
+
+/// Numbers delivered frames and measures the gaps between them (DSP thread)
+pub struct FrameSequencer {
+    sample_rate: f64,
+    seq: u64,
+    /// Where the next frame starts if nothing is suppressed
+    next_offset: u64,
+    /// One moment on both clocks, to turn `Instant`s into wall-clock time
+    anchor: Instant,
+    anchor_ms: f64,
+}
+
+impl FrameSequencer {
+    /// `sample_rate` is the output rate frames are delivered at
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate.max(1) as f64,
+            seq: 0,
+            next_offset: 0,
+            anchor: Instant::now(),
+            anchor_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs_f64() * 1000.0)
+                .unwrap_or(0.0),
+        }
+    }
+
+    /// Info for the next delivered frame
+    pub fn next(&mut self, frame: &Frame) -> FrameInfo {
+        let gap = frame.sample_offset.saturating_sub(self.next_offset);
+        let captured_at_ms = if frame.captured_at >= self.anchor {
+            self.anchor_ms + frame.captured_at.duration_since(self.anchor).as_secs_f64() * 1000.0
+        } else {
+            self.anchor_ms - self.anchor.duration_since(frame.captured_at).as_secs_f64() * 1000.0
+        };
+
+        let info = FrameInfo {
+            seq: self.seq as f64,
+            sample_offset: frame.sample_offset as f64,
+            timestamp_ms: frame.sample_offset as f64 * 1000.0 / self.sample_rate,
+            captured_at_ms,
+            gap_samples: gap as f64,
+            gap_ms: gap as f64 * 1000.0 / self.sample_rate,
+            frames: 1.0,
+        };
+        self.seq += 1;
+        self.next_offset = frame.sample_offset + (frame.samples.len() / frame.channels.max(1)) as u64;
+        info
+    }
+}
+
+/// Frames collected for the next delivery
+struct Batch {
+    info: FrameInfo,
+    samples: Vec<i16>,
+    /// When the first frame was added, for the `batch_ms` deadline
+    started: Instant,
+}
+
+/// Pipeline sink that numbers frames, converts them to the configured JS
+/// type and groups contiguous ones into batches (DSP thread)
+pub struct FrameBatcher<D: FnMut(DeliveredFrame) + Send> {
+    output: OutputFormat,
+    config: DeliveryConfig,
+    sequencer: FrameSequencer,
+    /// Frames that fill a batch; 1 without batching
+    batch_frames: usize,
+    batch: Option<Batch>,
+    deliver: D,
+}
+
+impl<D: FnMut(DeliveredFrame) + Send> FrameBatcher<D> {
+    pub fn new(output: OutputFormat, config: DeliveryConfig, deliver: D) -> Self {
+        let batch_frames = config.batch_ms.div_ceil(output.frame_ms.max(1)).max(1) as usize;
+        Self {
+            output,
+            config,
+            sequencer: FrameSequencer::new(output.sample_rate),
+            batch_frames,
+            batch: None,
+            deliver,
+        }
+    }
+
+    /// Deliver whatever has been collected
+    pub fn flush(&mut self) {
+        let Some(batch) = self.batch.take() else {
+            return;
+        };
+        let data = match (self.config.frame_type, self.output.sample_format) {
+            (FrameType::Buffer, _) => FrameData::buffer(&self.output, batch.samples),
+            (FrameType::TypedArray, SampleFormat::I16) => FrameData::I16(batch.samples),
+            (FrameType::TypedArray, SampleFormat::F32) => {
+                FrameData::F32(batch.samples.iter().map(|&s| s as f32 / 32768.0).collect())
+            }
+        };
+        (self.deliver)(DeliveredFrame { data, info: batch.info });
+    }
+}
+
+impl<D: FnMut(DeliveredFrame) + Send> FrameSink for FrameBatcher<D> {
+    fn frame(&mut self, frame: Frame) {
+        let info = self.sequencer.next(&frame);
+        // A batch only spans audio without holes, and Opus packets are
+        // delivered one by one so JS can still tell them apart
+        if info.gap_samples > 0.0 || frame.payload.is_some() {
+            self.flush();
+        }
+        if let Some(payload) = frame.payload {
+            (self.deliver)(DeliveredFrame { data: FrameData::Bytes(payload), info });
+            return;
+        }
+
+        match self.batch.as_mut() {
+            Some(batch) => {
+                batch.samples.extend_from_slice(&frame.samples);
+                batch.info.frames += 1.0;
+            }
+            None => {
+                self.batch = Some(Batch { info, samples: frame.samples, started: frame.captured_at });
+            }
+        }
+        if self.batch.as_ref().is_some_and(|batch| batch.info.frames as usize >= self.batch_frames) {
+            self.flush();
+        }
+    }
+
+    /// Deliver a partial batch once it is `batch_ms` old, so suppression or
+    /// a stalled device cannot hold audio back
+    fn idle(&mut self, now: Instant) {
+        let deadline = Duration::from_millis(self.config.batch_ms as u64);
+        if self.batch.as_ref().is_some_and(|batch| now.saturating_duration_since(batch.started) >= deadline) {
+            self.flush();
+        }
+    }
+}
+
+impl<D: FnMut(DeliveredFrame) + Send> Drop for FrameBatcher<D> {
+    /// The DSP thread is stopping; hand over the tail
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_suppressed_frames_show_up_as_gaps() {
+        // 16kHz, 20ms frames
+        let mut sequencer = FrameSequencer::new(16000);
+        let start = Instant::now();
+        let frame = |index: u64| Frame {
+            samples: vec![0; 320],
+            channels: 1,
+            sample_offset: index * 320,
+            captured_at: start + Duration::from_millis(index * 20),
+            payload: None,
+        };
+
+        let first = sequencer.next(&frame(0));
+        assert_eq!((first.seq, first.gap_samples), (0.0, 0.0));
+        let second = sequencer.next(&frame(1));
+        assert_eq!((second.seq, second.timestamp_ms, second.gap_ms), (1.0, 20.0, 0.0));
+        assert!((second.captured_at_ms - first.captured_at_ms - 20.0).abs() < 1e-6);
+
+        // Frames 2-4 were suppressed
+        let resumed = sequencer.next(&frame(5));
+        assert_eq!(resumed.seq, 2.0);
+        assert_eq!((resumed.gap_samples, resumed.gap_ms), (960.0, 60.0));
+        assert_eq!(resumed.timestamp_ms, 100.0);
+    }
+
+    #[test]
+    fn test_batches_break_at_gaps() {
+        use std::sync::mpsc;
+
+        // 16kHz, 20ms frames in 60ms batches
+        let output = OutputFormat::default();
+        let config = DeliveryConfig::from_options(Some("typedArray"), Some(60)).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut batcher = FrameBatcher::new(output, config, move |frame| tx.send(frame).unwrap());
+        let start = Instant::now();
+        let frame = |index: u64| Frame {
+            samples: vec![index as i16; 320],
+            channels: 1,
+            sample_offset: index * 320,
+            captured_at: start + Duration::from_millis(index * 20),
+            payload: None,
+        };
+
+        for index in [0, 1, 2, 3, 6] {
+            batcher.frame(frame(index));
+        }
+        let full = rx.try_recv().unwrap();
+        assert_eq!((full.info.frames, full.data.len()), (3.0, 960));
+        // Frames 4-5 were suppressed: frame 3 goes out alone
+        let partial = rx.try_recv().unwrap();
+        assert_eq!((partial.info.seq, partial.info.frames), (3.0, 1.0));
+        assert_eq!(partial.data, FrameData::I16(vec![3; 320]));
+        assert!(rx.try_recv().is_err());
+
+        // Frame 6 waits for the deadline, and then carries the gap
+        batcher.idle(start + Duration::from_millis(140));
+        assert!(rx.try_recv().is_err());
+        batcher.idle(start + Duration::from_millis(180));
+        let late = rx.try_recv().unwrap();
+        assert_eq!((late.info.seq, late.info.gap_ms, late.info.frames), (4.0, 40.0, 1.0));
+    }
+
+    #[test]
+    #[cfg(target_endian = "little")]
+    fn test_i16_buffers_keep_the_sample_allocation() {
+        use std::sync::mpsc;
+
+        let config = DeliveryConfig::from_options(Some("buffer"), None).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut batcher = FrameBatcher::new(OutputFormat::default(), config, move |frame| tx.send(frame).unwrap());
+        let samples: Vec<i16> = (0..320).collect();
+        let allocation = samples.as_ptr();
+        batcher.frame(Frame { samples, channels: 1, sample_offset: 0, captured_at: Instant::now(), payload: None });
+
+        let delivered = rx.try_recv().unwrap();
+        assert_eq!(delivered.data.len(), 640);
+        let FrameData::I16Bytes(samples) = delivered.data else { panic!("re-encoded: {:?}", delivered.data) };
+        assert_eq!(samples.as_ptr(), allocation);
+        // The bytes JS sees are the little-endian encoding
+        let bytes = unsafe { std::slice::from_raw_parts(samples.as_ptr().cast::<u8>(), 640) };
+        assert_eq!(bytes, OutputFormat::default().encode(&samples).as_slice());
+    }
+}
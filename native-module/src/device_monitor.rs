@@ -0,0 +1,310 @@
+// Device Monitor - hot-plug notifications and follow-the-default-device
+//
+// Neither cpal nor the system audio backends offer a portable change
+// notification, so each open capture source gets a monitor thread that polls
+// the device list every DEVICE_POLL_MS and reports:
+// - the system default device for its direction changing
+// - the device the capture was opened on disappearing
+//
+// `watch_lists` polls both directions for the `watchDevices` subscription.
+//
+// In follow mode the monitor thread also owns the backend stream: when the
+// default changes it opens the new default, hands the new consumer to the
+// running DSP thread through `PipelineInput` and drops the old stream, so the
+// pipeline (and the JS session on top of it) never restarts. Streams are
+// opened and dropped on the monitor thread, so they never cross threads.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use ringbuf::HeapCons;
+
+use crate::backend::{start_and_take, CaptureBackend};
+use crate::microphone;
+use crate::pipeline::PipelineInput;
+use crate::runner::SourceInput;
+use crate::speaker;
+
+/// How often the device list is polled
+const DEVICE_POLL_MS: u64 = 1000;
+
+/// How long `follow` waits for the first stream to open
+const OPEN_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeviceDirection {
+    /// Microphones
+    Input,
+    /// Speakers / headphones (system audio)
+    Output,
+}
+
+impl DeviceDirection {
+    fn default_device(self) -> Option<String> {
+        match self {
+            Self::Input => microphone::default_input_device(),
+            Self::Output => speaker::default_output_device(),
+        }
+    }
+
+    /// (id, name) of every device in this direction
+    fn devices(self) -> Vec<(String, String)> {
+        let devices = match self {
+            Self::Input => microphone::list_input_devices(),
+            Self::Output => speaker::list_output_devices(),
+        };
+        devices.unwrap_or_default()
+    }
+}
+
+/// Something changed about the devices of a capture source
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceNotice {
+    /// The system default device changed to this id
+    DefaultChanged(String),
+    /// The device the capture was opened on is gone
+    Lost(String),
+    /// Follow mode reopened the stream on this device
+    Switched(String),
+}
+
+/// Receives notices together with the stream position they happened at
+pub type NoticeSink = Box<dyn FnMut(DeviceNotice, u64) + Send>;
+
+/// Opens the current default device (called on the monitor thread, which
+/// starts, keeps and eventually drops the backend)
+pub type Opener = Box<dyn FnMut() -> Result<Box<dyn CaptureBackend>> + Send>;
+
+/// Previous poll result, turned into notices by `observe`
+#[derive(Debug, Default)]
+struct DeviceState {
+    default: Option<String>,
+    /// Device to report as lost when it disappears
+    watched: Option<String>,
+    lost: bool,
+}
+
+impl DeviceState {
+    fn new(default: Option<String>, watched: Option<String>) -> Self {
+        Self { default, watched, lost: false }
+    }
+
+    /// Compare a poll against the previous one
+    fn observe(&mut self, default: Option<String>, devices: &[(String, String)]) -> Vec<DeviceNotice> {
+        let mut notices = Vec::new();
+        if default != self.default {
+            if let Some(id) = default.clone() {
+                notices.push(DeviceNotice::DefaultChanged(id));
+            }
+            self.default = default;
+        }
+
+        if let Some(watched) = self.watched.as_ref() {
+            // Ids from older builds were display names
+            let present = devices.iter().any(|(id, name)| id == watched || name == watched);
+            if !present && !self.lost {
+                notices.push(DeviceNotice::Lost(watched.clone()));
+            }
+            self.lost = !present;
+        }
+        notices
+    }
+}
+
+/// Ids, names and defaults of both directions; cheap enough to poll,
+/// unlike capabilities
+#[derive(Debug, Default, PartialEq)]
+struct DeviceListSnapshot {
+    inputs: Vec<(String, String)>,
+    outputs: Vec<(String, String)>,
+    default_input: Option<String>,
+    default_output: Option<String>,
+}
+
+impl DeviceListSnapshot {
+    fn take() -> Self {
+        Self {
+            inputs: DeviceDirection::Input.devices(),
+            outputs: DeviceDirection::Output.devices(),
+            default_input: DeviceDirection::Input.default_device(),
+            default_output: DeviceDirection::Output.default_device(),
+        }
+    }
+}
+
+/// Runs until dropped
+pub struct DeviceMonitor {
+    stop_signal: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl DeviceMonitor {
+    /// Report default changes and the loss of `device_id` (if given) for a
+    /// stream owned by the caller
+    pub fn watch(
+        label: &'static str,
+        direction: DeviceDirection,
+        device_id: Option<String>,
+        input: SourceInput,
+        mut notices: NoticeSink,
+    ) -> Self {
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop_signal.clone();
+        let thread = thread::spawn(move || {
+            let mut state = DeviceState::new(direction.default_device(), device_id);
+            while sleep_unless_stopped(&thread_stop) {
+                for notice in state.observe(direction.default_device(), &direction.devices()) {
+                    log::info!("[{}] {:?}", label, notice);
+                    notices(notice, input.position());
+                }
+            }
+        });
+        Self { stop_signal, thread: Some(thread) }
+    }
+
+    /// Call `on_change` whenever a device is added, removed or renamed, or a
+    /// default changes
+    pub fn watch_lists(mut on_change: Box<dyn FnMut() + Send>) -> Self {
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop_signal.clone();
+        let thread = thread::spawn(move || {
+            let mut previous = DeviceListSnapshot::take();
+            while sleep_unless_stopped(&thread_stop) {
+                let current = DeviceListSnapshot::take();
+                if current != previous {
+                    log::info!("[DeviceMonitor] Device list changed");
+                    on_change();
+                    previous = current;
+                }
+            }
+        });
+        Self { stop_signal, thread: Some(thread) }
+    }
+
+    /// Open the default device on a monitor thread and reopen it whenever
+    /// the default changes. Returns the first consumer, the input to hand
+    /// to the runner and the channel count every reopened stream must keep.
+    pub fn follow(
+        label: &'static str,
+        direction: DeviceDirection,
+        mut opener: Opener,
+        mut notices: NoticeSink,
+    ) -> Result<(Self, HeapCons<f32>, SourceInput, usize)> {
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let (init_tx, init_rx) = mpsc::channel();
+
+        let thread_stop = stop_signal.clone();
+        let thread = thread::spawn(move || {
+            let mut opened_on = direction.default_device();
+            let (mut stream, consumer) = match open_started(&mut opener) {
+                Ok(opened) => opened,
+                Err(e) => {
+                    let _ = init_tx.send(Err(e));
+                    return;
+                }
+            };
+            let channels = stream.channels();
+            let input: SourceInput = Arc::new(PipelineInput::new(stream.sample_rate()));
+            if init_tx.send(Ok((consumer, input.clone(), channels))).is_err() {
+                return;
+            }
+
+            // Replaced streams are dropped here, on the thread that opened them
+            let mut state = DeviceState::new(opened_on.clone(), None);
+            while sleep_unless_stopped(&thread_stop) {
+                let default = direction.default_device();
+                for notice in state.observe(default.clone(), &[]) {
+                    log::info!("[{}] {:?}", label, notice);
+                    notices(notice, input.position());
+                }
+
+                // Retried every poll until the new default opens; the old
+                // stream keeps running until then
+                let Some(target) = default.filter(|id| opened_on.as_ref() != Some(id)) else { continue };
+                match open_started(&mut opener) {
+                    Ok((opened, _)) if opened.channels() != channels => {
+                        log::warn!(
+                            "[{}] {} delivers {} channels instead of {}; not switching",
+                            label, target, opened.channels(), channels
+                        );
+                        opened_on = Some(target);
+                    }
+                    Ok((opened, consumer)) => {
+                        input.replace(consumer, opened.sample_rate());
+                        drop(std::mem::replace(&mut stream, opened));
+                        opened_on = Some(target.clone());
+                        log::info!("[{}] Following default device: {}", label, target);
+                        notices(DeviceNotice::Switched(target), input.position());
+                    }
+                    Err(e) => log::error!("[{}] Failed to open {}: {}", label, target, e),
+                }
+            }
+        });
+
+        let (consumer, input, channels) = match init_rx.recv_timeout(OPEN_TIMEOUT) {
+            Ok(result) => result?,
+            Err(_) => {
+                stop_signal.store(true, Ordering::SeqCst);
+                return Err(anyhow::anyhow!("Timed out opening the default device"));
+            }
+        };
+        Ok((Self { stop_signal, thread: Some(thread) }, consumer, input, channels))
+    }
+}
+
+impl Drop for DeviceMonitor {
+    fn drop(&mut self) {
+        self.stop_signal.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn open_started(opener: &mut Opener) -> Result<(Box<dyn CaptureBackend>, HeapCons<f32>)> {
+    let mut backend = opener()?;
+    let consumer = start_and_take(backend.as_mut())?;
+    Ok((backend, consumer))
+}
+
+/// Wait one poll interval in short steps; false once stopped
+fn sleep_unless_stopped(stop_signal: &AtomicBool) -> bool {
+    for _ in 0..DEVICE_POLL_MS / 50 {
+        if stop_signal.load(Ordering::SeqCst) {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    !stop_signal.load(Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn devices(ids: &[&str]) -> Vec<(String, String)> {
+        ids.iter().map(|id| (id.to_string(), format!("{} name", id))).collect()
+    }
+
+    #[test]
+    fn test_default_changes_and_loss_are_reported_once() {
+        let mut state = DeviceState::new(Some("builtin".into()), Some("headset".into()));
+        assert!(state.observe(Some("builtin".into()), &devices(&["builtin", "headset"])).is_empty());
+
+        // Headset unplugged; the default was already the built-in device
+        let notices = state.observe(Some("builtin".into()), &devices(&["builtin"]));
+        assert_eq!(notices, vec![DeviceNotice::Lost("headset".into())]);
+        assert!(state.observe(Some("builtin".into()), &devices(&["builtin"])).is_empty());
+
+        // Plugged back in and made the default
+        let notices = state.observe(Some("headset".into()), &devices(&["builtin", "headset"]));
+        assert_eq!(notices, vec![DeviceNotice::DefaultChanged("headset".into())]);
+
+        // Legacy name ids count as present
+        let mut state = DeviceState::new(None, Some("headset name".into()));
+        assert!(state.observe(None, &devices(&["headset"])).is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+// Device Enumeration - ids plus what each device supports
+//
+// Microphones are enumerated through cpal. System audio devices keep the ids
+// of the platform backend (CoreAudio UID, WASAPI endpoint id, PulseAudio sink
+// name); their capabilities come from the cpal output device with the same
+// native id where one exists (CoreAudio, WASAPI), and are left empty
+// otherwise.
+
+use anyhow::Result;
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::{InterfaceType, SupportedStreamConfigRange};
+
+use crate::microphone;
+use crate::speaker;
+use crate::virtual_input;
+
+/// Common rates reported when a device supports a continuous range
+const STANDARD_RATES: &[u32] = &[
+    8000, 11025, 16000, 22050, 24000, 32000, 44100, 48000, 88200, 96000, 176400, 192000,
+];
+
+/// One device and its capabilities; empty lists mean "unknown"
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DeviceDetails {
+    pub id: String,
+    pub name: String,
+    /// The current system default for its direction
+    pub is_default: bool,
+    pub sample_rates: Vec<u32>,
+    pub channel_counts: Vec<u16>,
+    /// cpal sample format names, e.g. "f32", "i16"
+    pub sample_formats: Vec<String>,
+    /// "builtIn" | "usb" | "bluetooth" | ... when the backend reports it
+    pub transport: Option<String>,
+}
+
+/// Microphones, with the "default" entry first (see `list_input_devices`)
+pub fn input_devices() -> Result<Vec<DeviceDetails>> {
+    let host = cpal::default_host();
+    let default_id = microphone::default_input_device();
+    let mut list = Vec::new();
+
+    for (id, name) in microphone::list_input_devices()? {
+        let device = match id.as_str() {
+            "default" => host.default_input_device(),
+            _ => id.parse::<cpal::DeviceId>().ok().and_then(|parsed| host.device_by_id(&parsed)),
+        };
+        let mut details = DeviceDetails {
+            is_default: default_id.as_deref() == Some(id.as_str()),
+            id,
+            name,
+            ..Default::default()
+        };
+        if let Some(device) = virtual_input::find(&details.id) {
+            details.sample_rates = vec![device.sample_rate()];
+            details.channel_counts = vec![device.channels() as u16];
+            details.sample_formats = vec![device.format().name().to_string()];
+            details.transport = Some("virtual".to_string());
+        } else if let Some(device) = device {
+            let ranges: Vec<_> = device.supported_input_configs()
+                .map(|configs| configs.collect())
+                .unwrap_or_default();
+            describe(&mut details, &device, &ranges);
+        }
+        list.push(details);
+    }
+    Ok(list)
+}
+
+/// System audio devices, under the ids `SystemAudioCapture` accepts
+pub fn output_devices() -> Result<Vec<DeviceDetails>> {
+    let host = cpal::default_host();
+    let default_id = speaker::default_output_device();
+    let cpal_devices: Vec<(String, cpal::Device)> = host.output_devices()
+        .map(|devices| devices
+            .filter_map(|device| Some((device.id().ok()?.1, device)))
+            .collect())
+        .unwrap_or_default();
+
+    let mut list = Vec::new();
+    for (id, name) in speaker::list_output_devices()? {
+        let mut details = DeviceDetails {
+            is_default: default_id.as_deref() == Some(id.as_str()),
+            id,
+            name,
+            ..Default::default()
+        };
+        if let Some((_, device)) = cpal_devices.iter().find(|(native_id, _)| *native_id == details.id) {
+            let ranges: Vec<_> = device.supported_output_configs()
+                .map(|configs| configs.collect())
+                .unwrap_or_default();
+            describe(&mut details, device, &ranges);
+        }
+        list.push(details);
+    }
+    Ok(list)
+}
+
+fn describe(details: &mut DeviceDetails, device: &cpal::Device, ranges: &[SupportedStreamConfigRange]) {
+    details.sample_rates = supported_rates(ranges);
+    details.channel_counts = dedup_sorted(ranges.iter().map(|r| r.channels()));
+    details.sample_formats = dedup_sorted(ranges.iter().map(|r| r.sample_format().to_string()));
+    details.transport = device.description().ok().and_then(|d| transport_name(d.interface_type()));
+}
+
+/// Standard rates inside any supported range, plus the range ends
+fn supported_rates(ranges: &[SupportedStreamConfigRange]) -> Vec<u32> {
+    dedup_sorted(ranges.iter().flat_map(|range| {
+        let (min, max) = (range.min_sample_rate(), range.max_sample_rate());
+        STANDARD_RATES.iter().copied()
+            .filter(move |&rate| rate >= min && rate <= max)
+            .chain([min, max])
+    }))
+}
+
+fn dedup_sorted<T: Ord>(values: impl Iterator<Item = T>) -> Vec<T> {
+    let mut values: Vec<T> = values.collect();
+    values.sort();
+    values.dedup();
+    values
+}
+
+fn transport_name(interface: InterfaceType) -> Option<String> {
+    let name = match interface {
+        InterfaceType::BuiltIn => "builtIn",
+        InterfaceType::Usb => "usb",
+        InterfaceType::Bluetooth => "bluetooth",
+        InterfaceType::Pci => "pci",
+        InterfaceType::FireWire => "firewire",
+        InterfaceType::Thunderbolt => "thunderbolt",
+        InterfaceType::Hdmi => "hdmi",
+        InterfaceType::DisplayPort => "displayPort",
+        InterfaceType::Line => "line",
+        InterfaceType::Spdif => "spdif",
+        InterfaceType::Network => "network",
+        InterfaceType::Virtual => "virtual",
+        InterfaceType::Aggregate => "aggregate",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cpal::{SampleFormat, SupportedBufferSize};
+
+    #[test]
+    fn test_rate_ranges_expand_to_standard_rates() {
+        let range = |min, max, channels, format| SupportedStreamConfigRange::new(
+            channels, min, max, SupportedBufferSize::Unknown, format,
+        );
+        let ranges = [
+            range(44100, 48000, 2, SampleFormat::F32),
+            range(8000, 16000, 1, SampleFormat::I16),
+            range(12345, 12345, 1, SampleFormat::I16),
+        ];
+
+        assert_eq!(supported_rates(&ranges), vec![8000, 11025, 12345, 16000, 44100, 48000]);
+        assert_eq!(dedup_sorted(ranges.iter().map(|r| r.channels())), vec![1, 2]);
+        assert_eq!(
+            dedup_sorted(ranges.iter().map(|r| r.sample_format().to_string())),
+            vec!["f32".to_string(), "i16".to_string()]
+        );
+    }
+}
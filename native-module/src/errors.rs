@@ -0,0 +1,157 @@
+// Capture Errors - typed failures delivered to the JS `onError` callback
+//
+// Every backend reports through the `ErrorReporter` of the capture class
+// that opened it. Non-real-time threads (cpal's error callback, stream
+// setup, capture threads) call `report` directly. Real-time audio callbacks
+// must not lock or allocate, so they only `flag` a code; the DSP thread turns
+// flags into reports on its next iteration.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// What went wrong
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCode {
+    /// The device was unplugged or otherwise disappeared
+    DeviceLost,
+    /// The OS refused access (microphone / screen recording permission)
+    PermissionDenied,
+    /// Audio was dropped because the consumer fell behind
+    Overflow,
+    /// The device can't deliver a format we can read
+    FormatUnsupported,
+    /// The backend did not come up in time
+    Timeout,
+    /// Any other backend failure
+    StreamError,
+}
+
+const CODES: [ErrorCode; 6] = [
+    ErrorCode::DeviceLost,
+    ErrorCode::PermissionDenied,
+    ErrorCode::Overflow,
+    ErrorCode::FormatUnsupported,
+    ErrorCode::Timeout,
+    ErrorCode::StreamError,
+];
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::DeviceLost => "DeviceLost",
+            Self::PermissionDenied => "PermissionDenied",
+            Self::Overflow => "Overflow",
+            Self::FormatUnsupported => "FormatUnsupported",
+            Self::Timeout => "Timeout",
+            Self::StreamError => "StreamError",
+        }
+    }
+
+    /// Flag bit; fatal flags use the upper half
+    fn bit(self, fatal: bool) -> u32 {
+        let index = CODES.iter().position(|&c| c == self).unwrap_or(0) as u32;
+        1 << (index + if fatal { 16 } else { 0 })
+    }
+}
+
+/// One failure, as handed to `onError`
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureError {
+    /// "DeviceLost" | "PermissionDenied" | "Overflow" | "FormatUnsupported" |
+    /// "Timeout" | "StreamError"
+    pub code: String,
+    pub message: String,
+    /// Capture has stopped delivering audio; restart it (or pick another
+    /// device) to recover
+    pub fatal: bool,
+}
+
+impl CaptureError {
+    pub fn new(code: ErrorCode, message: impl Into<String>, fatal: bool) -> Self {
+        Self { code: code.as_str().to_string(), message: message.into(), fatal }
+    }
+}
+
+/// Receives errors on whichever thread reported them
+pub type ErrorListener = Arc<dyn Fn(CaptureError) + Send + Sync>;
+
+/// Shared by a capture class, its backend and its DSP thread
+pub struct ErrorReporter {
+    label: &'static str,
+    listener: Mutex<Option<ErrorListener>>,
+    /// Codes raised from real-time callbacks, not yet reported
+    flags: AtomicU32,
+}
+
+impl ErrorReporter {
+    pub fn new(label: &'static str) -> Self {
+        Self { label, listener: Mutex::new(None), flags: AtomicU32::new(0) }
+    }
+
+    pub fn set_listener(&self, listener: Option<ErrorListener>) {
+        *self.listener.lock().unwrap_or_else(|e| e.into_inner()) = listener;
+    }
+
+    /// Log and deliver an error (not from a real-time callback)
+    pub fn report(&self, error: CaptureError) {
+        let level = if error.fatal { log::Level::Error } else { log::Level::Warn };
+        log::log!(level, "[{}] {} error: {}", self.label, error.code, error.message);
+        let listener = self.listener.lock().ok().and_then(|l| l.clone());
+        if let Some(listener) = listener {
+            listener(error);
+        }
+    }
+
+    /// Real-time safe: remember `code` until the next `flush_flags`
+    pub fn flag(&self, code: ErrorCode, fatal: bool) {
+        self.flags.fetch_or(code.bit(fatal), Ordering::Relaxed);
+    }
+
+    /// Report everything flagged since the last call
+    pub fn flush_flags(&self) {
+        if self.flags.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let flags = self.flags.swap(0, Ordering::Relaxed);
+        for code in CODES {
+            for fatal in [false, true] {
+                if flags & code.bit(fatal) != 0 {
+                    self.report(CaptureError::new(code, flagged_message(code), fatal));
+                }
+            }
+        }
+    }
+}
+
+fn flagged_message(code: ErrorCode) -> &'static str {
+    match code {
+        ErrorCode::Overflow => "Audio buffer overflow: samples were dropped",
+        ErrorCode::StreamError => "Audio callback failed to read a buffer: samples were lost",
+        _ => "Raised by the audio callback",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_are_reported_once_from_the_dsp_side() {
+        let reporter = ErrorReporter::new("test");
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        reporter.set_listener(Some(Arc::new(move |e| sink.lock().unwrap().push(e))));
+
+        reporter.flag(ErrorCode::Overflow, false);
+        reporter.flag(ErrorCode::Overflow, false);
+        reporter.flag(ErrorCode::Overflow, true);
+        reporter.flush_flags();
+        reporter.flush_flags();
+        reporter.report(CaptureError::new(ErrorCode::DeviceLost, "gone", true));
+
+        let received = received.lock().unwrap();
+        let codes: Vec<(&str, bool)> = received.iter().map(|e| (e.code.as_str(), e.fatal)).collect();
+        assert_eq!(codes, vec![("Overflow", false), ("Overflow", true), ("DeviceLost", true)]);
+    }
+}
@@ -0,0 +1,69 @@
+// Capture Events - delivered to the optional second `start()` callback
+//
+// Events are produced on the DSP thread and handed to an `EventSink`; in
+// lib.rs the sink is a ThreadsafeFunction, in tests a plain closure.
+
+use crate::device_monitor::DeviceNotice;
+use crate::pipeline::FormatChange;
+
+/// One event from a capture pipeline
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureEvent {
+    /// "speechStart" | "speechEnd" | "formatChanged" | "deviceChanged" |
+    /// "deviceLost" | "deviceSwitched" | "fileEnded"
+    pub kind: String,
+    /// Output-rate sample index (per channel) where the event happened
+    pub sample_offset: f64,
+    /// `sampleOffset` in milliseconds of stream time
+    pub timestamp_ms: f64,
+    /// Frame RMS (i16 scale) when the event fired (0 for format, device and
+    /// file events)
+    pub rms: f64,
+    /// formatChanged: new device sample rate
+    pub input_sample_rate: Option<f64>,
+    /// formatChanged: device sample rate before the change
+    pub previous_sample_rate: Option<f64>,
+    /// deviceChanged: the new system default; deviceLost: the device that
+    /// disappeared; deviceSwitched: the device now being captured
+    pub device_id: Option<String>,
+}
+
+impl CaptureEvent {
+    pub fn new(kind: &str, sample_offset: u64, sample_rate: u32, rms: f32) -> Self {
+        Self {
+            kind: kind.to_string(),
+            sample_offset: sample_offset as f64,
+            timestamp_ms: sample_offset as f64 * 1000.0 / sample_rate.max(1) as f64,
+            rms: rms as f64,
+            input_sample_rate: None,
+            previous_sample_rate: None,
+            device_id: None,
+        }
+    }
+
+    /// The device rate changed; `sample_offset` is where the new rate starts
+    pub fn format_changed(change: &FormatChange, output_sample_rate: u32) -> Self {
+        Self {
+            input_sample_rate: Some(change.sample_rate),
+            previous_sample_rate: Some(change.previous_rate),
+            ..Self::new("formatChanged", change.sample_offset, output_sample_rate, 0.0)
+        }
+    }
+
+    /// A device notice from the monitor thread, at stream position `sample_offset`
+    pub fn device(notice: DeviceNotice, sample_offset: u64, output_sample_rate: u32) -> Self {
+        let (kind, device_id) = match notice {
+            DeviceNotice::DefaultChanged(id) => ("deviceChanged", id),
+            DeviceNotice::Lost(id) => ("deviceLost", id),
+            DeviceNotice::Switched(id) => ("deviceSwitched", id),
+        };
+        Self {
+            device_id: Some(device_id),
+            ..Self::new(kind, sample_offset, output_sample_rate, 0.0)
+        }
+    }
+}
+
+/// Receives events from the DSP thread
+pub type EventSink = Box<dyn FnMut(CaptureEvent) + Send>;
@@ -0,0 +1,293 @@
+// File Source - WAV/FLAC playback through a capture pipeline
+//
+// Lets demos, regression tests and CI (no audio hardware) exercise the live
+// path. The file is decoded up front; a player thread then pushes it into the
+// same lock-free ring buffer a device callback fills, BLOCK_MS at a time,
+// through the same `ChannelMixer`. From there the DSP thread cannot tell it
+// apart from a microphone: resampling, stages and delivery are unchanged.
+//
+// Pacing: at `speed` 1.0 blocks are released against the wall clock, 2.0
+// plays twice as fast, and 0 pushes whenever the ring buffer has room, so
+// playback runs as fast as the pipeline drains it. The player never drops
+// audio; it waits for space instead.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use ringbuf::traits::{Observer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+
+use crate::audio_config::RING_BUFFER_SAMPLES;
+use crate::backend::{BackendCapabilities, CaptureBackend};
+use crate::channel_mix::{ChannelMixer, ChannelPolicy};
+use crate::pipeline::{fixed_rate, RateHandle};
+
+/// Audio pushed per block
+const BLOCK_MS: u32 = 10;
+
+/// Decoded file contents
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedAudio {
+    /// Interleaved, [-1.0, 1.0]
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: usize,
+}
+
+impl DecodedAudio {
+    pub fn duration_secs(&self) -> f64 {
+        (self.samples.len() / self.channels) as f64 / self.sample_rate as f64
+    }
+}
+
+/// Decode a WAV or FLAC file (picked by extension)
+pub fn decode_file(path: &Path) -> Result<DecodedAudio> {
+    let is_flac = path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("flac"));
+    let audio = if is_flac { decode_flac(path)? } else { decode_wav(path)? };
+
+    if audio.channels == 0 || audio.sample_rate == 0 || audio.samples.is_empty() {
+        return Err(anyhow::anyhow!("{} contains no audio", path.display()));
+    }
+    log::info!(
+        "[FileSource] Decoded {}: {}Hz, {}ch, {:.1}s",
+        path.display(), audio.sample_rate, audio.channels, audio.duration_secs()
+    );
+    Ok(audio)
+}
+
+fn decode_wav(path: &Path) -> Result<DecodedAudio> {
+    let reader = hound::WavReader::open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.into_samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader.into_samples::<i32>()
+                .map(|s| s.map(|s| s as f32 * scale))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+    Ok(DecodedAudio { samples, sample_rate: spec.sample_rate, channels: spec.channels as usize })
+}
+
+fn decode_flac(path: &Path) -> Result<DecodedAudio> {
+    let mut reader = claxon::FlacReader::open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))?;
+    let info = reader.streaminfo();
+    let scale = 1.0 / (1u64 << (info.bits_per_sample - 1)) as f32;
+    let samples = reader.samples()
+        .map(|s| s.map(|s| s as f32 * scale))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(DecodedAudio { samples, sample_rate: info.sample_rate, channels: info.channels as usize })
+}
+
+/// How the player paces the file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Playback {
+    /// Multiple of real time; 0 = as fast as the pipeline drains
+    pub speed: f64,
+    /// Start over at the end instead of finishing
+    pub repeat: bool,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self { speed: 1.0, repeat: false }
+    }
+}
+
+/// Called once when the file has been pushed completely (never with `repeat`)
+pub type EndListener = Box<dyn FnOnce() + Send>;
+
+/// Runs until the file ends or it is dropped
+pub struct FilePlayer {
+    stop_signal: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl FilePlayer {
+    pub fn start(
+        audio: Arc<DecodedAudio>,
+        mut mixer: ChannelMixer,
+        mut producer: HeapProd<f32>,
+        playback: Playback,
+        on_end: EndListener,
+    ) -> Self {
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop_signal.clone();
+
+        let thread = thread::spawn(move || {
+            let channels = audio.channels;
+            let block_len = (audio.sample_rate * BLOCK_MS / 1000).max(1) as usize * channels;
+            let pushed_len = block_len / channels * mixer.output_channels();
+            let started = Instant::now();
+            let mut played_frames: u64 = 0;
+
+            loop {
+                for block in audio.samples.chunks(block_len) {
+                    if playback.speed > 0.0 {
+                        let due = played_frames as f64 / audio.sample_rate as f64 / playback.speed;
+                        let wait = Duration::from_secs_f64(due).saturating_sub(started.elapsed());
+                        if !wait.is_zero() {
+                            thread::sleep(wait);
+                        }
+                    }
+                    while producer.vacant_len() < pushed_len {
+                        if thread_stop.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        thread::sleep(Duration::from_millis(1));
+                    }
+                    if thread_stop.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    mixer.push(block, &mut producer);
+                    played_frames += (block.len() / channels) as u64;
+                }
+                if !playback.repeat {
+                    break;
+                }
+            }
+
+            log::info!("[FileSource] Playback finished ({} frames)", played_frames);
+            on_end();
+        });
+
+        Self { stop_signal, thread: Some(thread) }
+    }
+}
+
+/// File playback behind the `CaptureBackend` interface. The player starts
+/// with `start` and runs until the file ends or the backend is dropped.
+pub struct FileBackend {
+    audio: Arc<DecodedAudio>,
+    playback: Playback,
+    /// Handed to the player on `start`
+    pending: Option<(ChannelMixer, HeapProd<f32>, EndListener)>,
+    consumer: Option<HeapCons<f32>>,
+    channels: usize,
+    player: Option<FilePlayer>,
+}
+
+impl FileBackend {
+    pub fn open(audio: Arc<DecodedAudio>, policy: ChannelPolicy, playback: Playback, on_end: EndListener) -> Result<Self> {
+        let mixer = ChannelMixer::new(policy, audio.channels)?;
+        let channels = mixer.output_channels();
+        let (producer, consumer) = HeapRb::<f32>::new(RING_BUFFER_SAMPLES).split();
+        Ok(Self {
+            audio,
+            playback,
+            pending: Some((mixer, producer, on_end)),
+            consumer: Some(consumer),
+            channels,
+            player: None,
+        })
+    }
+}
+
+impl CaptureBackend for FileBackend {
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            name: "file",
+            pausable: false,
+            variable_rate: false,
+            selects_device: false,
+        }
+    }
+
+    fn start(&mut self) -> Result<()> {
+        if let Some((mixer, producer, on_end)) = self.pending.take() {
+            self.player = Some(FilePlayer::start(self.audio.clone(), mixer, producer, self.playback, on_end));
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn sample_rate(&self) -> RateHandle {
+        fixed_rate(self.audio.sample_rate)
+    }
+
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+        self.consumer.take()
+    }
+}
+
+impl Drop for FilePlayer {
+    fn drop(&mut self) {
+        self.stop_signal.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ringbuf::traits::Consumer;
+    use std::fs;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_wav_plays_through_the_ring_buffer_without_drops() {
+        let dir = std::env::temp_dir().join(format!("natively-file-source-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("stereo.wav");
+
+        // 0.5s of 48kHz stereo, left and right of opposite sign
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 48_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for n in 0..24_000 {
+            writer.write_sample((n % 1000) as i16).unwrap();
+            writer.write_sample(-((n % 1000) as i16) / 2).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let audio = decode_file(&path).unwrap();
+        assert_eq!((audio.sample_rate, audio.channels, audio.samples.len()), (48_000, 2, 48_000));
+        assert!((audio.duration_secs() - 0.5).abs() < 1e-9);
+
+        // A ring buffer smaller than the file: the player has to wait for
+        // the consumer instead of dropping samples
+        let (producer, mut consumer) = HeapRb::<f32>::new(4096).split();
+        let mixer = ChannelMixer::new(ChannelPolicy::Downmix, 2).unwrap();
+        let (end_tx, end_rx) = mpsc::channel();
+        let playback = Playback { speed: 0.0, repeat: false };
+        let _player = FilePlayer::start(Arc::new(audio), mixer, producer, playback, Box::new(move || {
+            let _ = end_tx.send(());
+        }));
+
+        let mut received = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while received.len() < 24_000 && Instant::now() < deadline {
+            received.extend(consumer.pop_iter());
+            thread::sleep(Duration::from_millis(1));
+        }
+        end_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+
+        assert_eq!(received.len(), 24_000);
+        // Downmixed: (x - x/2) / 2
+        let expected = (999.0 - 499.0) / 2.0 / 32768.0;
+        assert!((received[999] - expected).abs() < 1e-6);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,30 +3,262 @@
 #[macro_use]
 extern crate napi_derive;
 
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::thread;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
 
 use napi::bindgen_prelude::*;
+use napi::JsBuffer;
 use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode, ErrorStrategy};
-use ringbuf::traits::Consumer;
 
 pub mod vad; 
+pub mod backend;
+pub mod delivery;
 pub mod microphone;
 pub mod speaker;
 pub mod streaming_resampler;
 pub mod audio_config;
 pub mod silence_suppression;
+pub mod options;
+pub mod channel_mix;
+pub mod pipeline;
+pub mod stages;
+pub mod metrics;
+pub mod aec;
+pub mod noise_suppression;
+pub mod agc;
+pub mod speech_detector;
+pub mod events;
+pub mod recorder;
+pub mod runner;
+pub mod mixer;
+pub mod opus;
+pub mod device_monitor;
+pub mod devices;
+pub mod errors;
+pub mod logging;
+pub mod file_source;
+pub mod virtual_input;
 
 // Keep old resampler module for compatibility
 pub mod resampler;
 
-use crate::streaming_resampler::StreamingResampler;
-use crate::audio_config::{FRAME_SAMPLES, DSP_POLL_MS};
-use crate::silence_suppression::{
-    SilenceSuppressor, SilenceSuppressionConfig, FrameAction, generate_silence_frame
-};
+use crate::aec::FarEndReference;
+use crate::backend::{start_and_take, CaptureBackend, InputBackend, OutputBackend};
+use crate::delivery::{DeliveredFrame, DeliveryConfig, FrameData, FrameSequencer};
+use crate::device_monitor::{DeviceDirection, DeviceMonitor, DeviceNotice, NoticeSink, Opener};
+use crate::errors::{CaptureError, ErrorCode, ErrorListener, ErrorReporter};
+use crate::events::{CaptureEvent, EventSink};
+use crate::file_source::{DecodedAudio, FileBackend, Playback};
+use crate::options::{CaptureOptions, FilePlaybackOptions, RecordingOptions, VirtualInputOptions};
+use crate::channel_mix::ChannelPolicy;
+use crate::pipeline::{FormatListener, FrameProcessor, OutputFormat, PipelineTap};
+use crate::pipeline::PipelineInput;
+use crate::runner::{FrameCallback, PipelineRunner};
+use crate::stages::{SourceKind, StageOptions};
+use crate::streaming_resampler::ResamplerQuality;
+use crate::virtual_input::{PcmFormat, VirtualDevice};
+
+#[napi::module_init]
+fn init() {
+    logging::init();
+}
+
+type JsFrameCallback = ThreadsafeFunction<DeliveredFrame, ErrorStrategy::Fatal>;
+
+/// Wrap the JS frame callback; frames arrive as a `Buffer` of little-endian
+/// PCM (or Opus) bytes or as a typed array, followed by their `FrameInfo`.
+/// The data is handed over without copying where the runtime allows it.
+fn create_frame_callback(callback: JsFunction) -> napi::Result<JsFrameCallback> {
+    callback.create_threadsafe_function(0, |ctx| {
+        let frame: DeliveredFrame = ctx.value;
+        let data = match frame.data {
+            FrameData::Bytes(bytes) => Either3::A(ctx.env.create_buffer_with_data(bytes)?.into_raw()),
+            FrameData::I16Bytes(samples) => Either3::A(i16_buffer(&ctx.env, samples)?),
+            FrameData::I16(samples) => Either3::B(Int16Array::new(samples)),
+            FrameData::F32(samples) => Either3::C(Float32Array::new(samples)),
+        };
+        Ok(vec![Either::A(data), Either::B(frame.info)])
+    })
+}
+
+/// A `Buffer` over the bytes of `samples`, keeping the allocation alive
+/// until it is collected
+fn i16_buffer(env: &Env, mut samples: Vec<i16>) -> napi::Result<JsBuffer> {
+    if samples.is_empty() {
+        return Ok(env.create_buffer(0)?.into_raw());
+    }
+    let data = samples.as_mut_ptr().cast::<u8>();
+    let len = std::mem::size_of_val(samples.as_slice());
+    // SAFETY: moving the Vec into the finalize hint doesn't move its heap
+    // data, which stays valid until the finalizer drops it
+    let buffer = unsafe { env.create_buffer_with_borrowed_data(data, len, samples, |samples, _| drop(samples))? };
+    Ok(buffer.into_raw())
+}
+
+/// Runner callback queueing frames for the JS frame callback
+fn frame_sender(tsfn: JsFrameCallback) -> FrameCallback {
+    Box::new(move |frame| {
+        tsfn.call(frame, ThreadsafeFunctionCallMode::NonBlocking);
+    })
+}
+
+type EventCallback = ThreadsafeFunction<CaptureEvent, ErrorStrategy::Fatal>;
+
+/// Wrap the optional JS event callback
+fn create_event_callback(event_callback: Option<JsFunction>) -> napi::Result<Option<EventCallback>> {
+    event_callback
+        .map(|callback| callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value])))
+        .transpose()
+}
+
+/// Event callback of the running `start()`, shared with the device monitor
+type EventSlot = Arc<Mutex<Option<EventCallback>>>;
+
+/// Report device notices as deviceChanged/deviceLost/deviceSwitched events
+/// (dropped while `start()` isn't running); a lost device is also an error
+fn create_notice_sink(events: &EventSlot, errors: &Arc<ErrorReporter>, sample_rate: u32) -> NoticeSink {
+    let events = events.clone();
+    let errors = errors.clone();
+    Box::new(move |notice, sample_offset| {
+        if let DeviceNotice::Lost(id) = &notice {
+            errors.report(CaptureError::new(ErrorCode::DeviceLost, format!("Device disconnected: {}", id), true));
+        }
+        let callback = events.lock().ok().and_then(|events| events.clone());
+        if let Some(callback) = callback {
+            callback.call(CaptureEvent::device(notice, sample_offset, sample_rate), ThreadsafeFunctionCallMode::NonBlocking);
+        }
+    })
+}
+
+/// Wrap the JS `onError` callback
+fn create_error_listener(callback: Option<JsFunction>) -> napi::Result<Option<ErrorListener>> {
+    let Some(callback) = callback else { return Ok(None) };
+    let tsfn: ThreadsafeFunction<CaptureError, ErrorStrategy::Fatal> =
+        callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+    Ok(Some(Arc::new(move |error| {
+        tsfn.call(error, ThreadsafeFunctionCallMode::NonBlocking);
+    })))
+}
+
+/// `followDefaultDevice` picks the device itself
+fn follow_default(options: &CaptureOptions, device_id: Option<&str>) -> napi::Result<bool> {
+    let follow = options.follow_default_device.unwrap_or(false);
+    if follow && device_id.is_some_and(|id| !id.is_empty() && id != "default") {
+        return Err(napi::Error::new(
+            napi::Status::InvalidArg,
+            "followDefaultDevice cannot be combined with a deviceId".to_string(),
+        ));
+    }
+    Ok(follow)
+}
+
+/// Report device rate changes as formatChanged events
+fn create_format_listener(events: Option<EventCallback>, sample_rate: u32) -> Option<FormatListener> {
+    let events = events?;
+    Some(Arc::new(move |change| {
+        events.call(CaptureEvent::format_changed(&change, sample_rate), ThreadsafeFunctionCallMode::NonBlocking);
+    }))
+}
+
+/// Build the VAD stage feeding the JS event callback
+fn create_vad_stage(
+    events: Option<EventCallback>,
+    detector: Option<&str>,
+    sample_rate: u32,
+) -> napi::Result<Option<Box<dyn FrameProcessor>>> {
+    let Some(events) = events else { return Ok(None) };
+
+    let sink: EventSink = Box::new(move |event| {
+        events.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+    });
+
+    let indicator = match detector {
+        None => vad::VadIndicator::new(),
+        Some(name) => {
+            let detector = speech_detector::detector_from_name(Some(name), audio_config::VAD_START_RMS, sample_rate)
+                .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e.to_string()))?;
+            vad::VadIndicator::with_detector(detector)
+        }
+    };
+    Ok(Some(Box::new(vad::VadStage::new(indicator, sample_rate, sink))))
+}
+
+/// Parse `sampleRate`, `frameMs`, `sampleFormat` and `resamplerQuality`
+fn output_format(options: &CaptureOptions) -> napi::Result<OutputFormat> {
+    let output = OutputFormat::from_options(options.sample_rate, options.frame_ms, options.sample_format.as_deref())
+        .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e.to_string()))?;
+    let resampler = ResamplerQuality::from_name(options.resampler_quality.as_deref())
+        .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e.to_string()))?;
+    Ok(OutputFormat { resampler, ..output })
+}
+
+/// Parse `frameType` and `batchMs`
+fn delivery_config(options: &CaptureOptions) -> napi::Result<DeliveryConfig> {
+    DeliveryConfig::from_options(options.frame_type.as_deref(), options.batch_ms)
+        .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e.to_string()))
+}
+
+/// Validate the `stages` and `vadDetector` options up front so bad configs
+/// fail in the constructor; `channels` is the interleaved channel count of
+/// delivered frames
+fn stage_options(
+    options: &CaptureOptions,
+    source: SourceKind,
+    output: &OutputFormat,
+    channels: usize,
+) -> napi::Result<Vec<StageOptions>> {
+    speech_detector::detector_from_name(options.vad_detector.as_deref(), 1.0, output.sample_rate)
+        .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e.to_string()))?;
+    let stages = options.stages.clone().unwrap_or_else(stages::default_stages);
+    stages::validate_stages(&stages, source, output, channels)
+        .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e.to_string()))?;
+    Ok(stages)
+}
+
+/// Parse `startRecording` arguments
+fn recorder_config(path: &str, options: Option<RecordingOptions>) -> napi::Result<recorder::RecorderConfig> {
+    let options = options.unwrap_or_default();
+    let config = recorder::RecorderConfig::from_options(
+        path,
+        options.format.as_deref(),
+        options.source.as_deref(),
+        options.rotate_seconds,
+        options.max_files,
+    ).map_err(|e| napi::Error::new(napi::Status::InvalidArg, e.to_string()))?;
+    Ok(recorder::RecorderConfig { bitrate: options.bitrate, ..config })
+}
+
+fn paths_to_strings(paths: Vec<std::path::PathBuf>) -> Vec<String> {
+    paths.into_iter().map(|p| p.to_string_lossy().into_owned()).collect()
+}
+
+/// Input levels of a capture source, measured on the DSP thread
+#[napi(object)]
+pub struct AudioLevels {
+    /// Peak of the latest frame (0.0 - 1.0)
+    pub peak: f64,
+    /// RMS of the latest frame (0.0 - 1.0)
+    pub rms: f64,
+    /// `rms` in dBFS (-100 for digital silence)
+    pub dbfs: f64,
+    /// Full-scale samples since the capture was created
+    pub clip_count: f64,
+    /// RMS dBFS of the frames of the last second (1000 / frameMs of
+    /// them), oldest first
+    pub history: Vec<f64>,
+}
+
+impl From<metrics::LevelSnapshot> for AudioLevels {
+    fn from(levels: metrics::LevelSnapshot) -> Self {
+        Self {
+            peak: levels.peak as f64,
+            rms: levels.rms as f64,
+            dbfs: levels.dbfs as f64,
+            clip_count: levels.clip_count as f64,
+            history: levels.history.into_iter().map(|db| db as f64).collect(),
+        }
+    }
+}
 
 // ============================================================================
 // SYSTEM AUDIO CAPTURE (ScreenCaptureKit on macOS)
@@ -34,144 +266,193 @@ use crate::silence_suppression::{
 
 #[napi]
 pub struct SystemAudioCapture {
-    stop_signal: Arc<AtomicBool>,
-    capture_thread: Option<thread::JoinHandle<()>>,
-    sample_rate: u32,
     device_id: Option<String>,
-    input: Option<speaker::SpeakerInput>,
-    stream: Option<speaker::SpeakerStream>,
+    vad_detector: Option<String>,
+    follow_default: bool,
+    backend: OutputBackend,
+    runner: PipelineRunner,
+    /// Unused in follow mode, where the monitor thread owns the stream
+    stream: Option<Box<dyn CaptureBackend>>,
+    monitor: Option<DeviceMonitor>,
+    events: EventSlot,
+    errors: Arc<ErrorReporter>,
 }
 
 #[napi]
 impl SystemAudioCapture {
     #[napi(constructor)]
-    pub fn new(device_id: Option<String>) -> napi::Result<Self> {
-        println!("[SystemAudioCapture] Created with lazy init (device: {:?})", device_id);
+    pub fn new(mut device_id: Option<String>, options: Option<CaptureOptions>) -> napi::Result<Self> {
+        log::info!("[SystemAudioCapture] Created with lazy init (device: {:?})", device_id);
+        let options = options.unwrap_or_default();
+        let output = output_format(&options)?;
+        let delivery = delivery_config(&options)?;
+        // Every system audio backend delivers mono
+        let stages = stage_options(&options, SourceKind::SystemAudio, &output, 1)?;
+
+        let mut backend = options.backend.clone();
+        if device_id.as_deref() == Some("sck") {
+            log::warn!("[SystemAudioCapture] deviceId \"sck\" is deprecated, use the backend option");
+            device_id = None;
+            backend.get_or_insert_with(|| "screenCaptureKit".to_string());
+        }
+        let backend = OutputBackend::from_name(backend.as_deref())
+            .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e.to_string()))?;
+        let follow_default = follow_default(&options, device_id.as_deref())?;
+        let errors = Arc::new(ErrorReporter::new("SystemAudioCapture"));
         
         Ok(SystemAudioCapture {
-            stop_signal: Arc::new(AtomicBool::new(false)),
-            capture_thread: None,
-            sample_rate: 16000,
             device_id,
-            input: None,
+            vad_detector: options.vad_detector,
+            follow_default,
+            backend,
+            runner: PipelineRunner::new("SystemAudioCapture", SourceKind::SystemAudio, stages, output)
+                .with_delivery(delivery)
+                .with_echo_reference(Arc::new(FarEndReference::default()))
+                .with_errors(errors.clone()),
             stream: None,
+            monitor: None,
+            events: EventSlot::default(),
+            errors,
         })
     }
 
+    /// `callback` receives a `CaptureError` whenever the backend fails
+    /// (pass null to remove it); independent of `start`/`stop`
+    #[napi]
+    pub fn on_error(&mut self, callback: Option<JsFunction>) -> napi::Result<()> {
+        self.errors.set_listener(create_error_listener(callback)?);
+        Ok(())
+    }
+
+    /// Output rate of delivered frames (the `sampleRate` option, default 16000)
     #[napi]
     pub fn get_sample_rate(&self) -> u32 {
-        self.sample_rate
+        self.runner.output().sample_rate
     }
 
+    /// Duration of each delivered frame in ms (the `frameMs` option, default 20)
     #[napi]
-    pub fn start(&mut self, callback: JsFunction) -> napi::Result<()> {
-        let tsfn: ThreadsafeFunction<Vec<i16>, ErrorStrategy::Fatal> = callback
-            .create_threadsafe_function(0, |ctx| {
-                let vec: Vec<i16> = ctx.value;
-                let mut pcm_bytes = Vec::with_capacity(vec.len() * 2);
-                for sample in vec {
-                    pcm_bytes.extend_from_slice(&sample.to_le_bytes());
-                }
-                Ok(vec![pcm_bytes])
-            })?;
+    pub fn get_frame_ms(&self) -> u32 {
+        self.runner.output().frame_ms
+    }
 
-        self.stop_signal.store(false, Ordering::SeqCst);
-        let stop_signal = self.stop_signal.clone();
-        
-        // Lazy init: Create SpeakerInput now
-        let input = if let Some(existing) = self.input.take() {
-            existing
-        } else {
-            println!("[SystemAudioCapture] Creating ScreenCaptureKit stream...");
-            match speaker::SpeakerInput::new(self.device_id.take()) {
-                Ok(i) => i,
-                Err(e) => {
-                    println!("[SystemAudioCapture] Failed: {}. Trying default...", e);
-                    match speaker::SpeakerInput::new(None) {
-                        Ok(i) => i,
-                        Err(e2) => return Err(napi::Error::from_reason(format!("Failed: {}", e2))),
-                    }
-                }
-            }
-        };
-        
-        let mut stream = input.stream();
-        let input_sample_rate = stream.sample_rate() as f64;
-        let mut consumer = stream.take_consumer()
-            .ok_or_else(|| napi::Error::from_reason("Failed to get consumer"))?;
-        
-        self.stream = Some(stream);
+    /// Gain currently applied by the `agc` stage in dB, or null without one
+    #[napi]
+    pub fn get_gain_db(&self) -> Option<f64> {
+        self.runner.metrics().agc.as_ref().map(|stats| stats.gain_db.load() as f64)
+    }
 
-        // DSP thread with silence suppression
-        self.capture_thread = Some(thread::spawn(move || {
-            let mut resampler = StreamingResampler::new(input_sample_rate, 16000.0);
-            let mut frame_buffer: Vec<i16> = Vec::with_capacity(FRAME_SAMPLES * 4);
-            let mut raw_batch: Vec<f32> = Vec::with_capacity(4096);
-            
-            // Use system audio config (lower threshold for quieter system audio)
-            let mut suppressor = SilenceSuppressor::new(
-                SilenceSuppressionConfig::for_system_audio()
-            );
-
-            println!("[SystemAudioCapture] DSP thread started (suppression active)");
-
-            loop {
-                if stop_signal.load(Ordering::Relaxed) {
-                    break;
-                }
-                
-                // 1. Drain ring buffer (lock-free)
-                let mut batch_count = 0;
-                while let Some(sample) = consumer.try_pop() {
-                    raw_batch.push(sample);
-                    batch_count += 1;
-                    if raw_batch.len() >= 480 {
-                        break;
-                    }
-                }
-                
-                // 2. Resample
-                if !raw_batch.is_empty() {
-                    let resampled = resampler.resample(&raw_batch);
-                    frame_buffer.extend(resampled);
-                    raw_batch.clear();
-                }
-
-                // 3. Process frames with Silence Suppression
-                while frame_buffer.len() >= FRAME_SAMPLES {
-                    let frame: Vec<i16> = frame_buffer.drain(0..FRAME_SAMPLES).collect();
-                    match suppressor.process(&frame) {
-                        FrameAction::Send(audio) => {
-                             tsfn.call(audio, ThreadsafeFunctionCallMode::NonBlocking);
-                        },
-                        FrameAction::SendSilence => {
-                             tsfn.call(generate_silence_frame(FRAME_SAMPLES), ThreadsafeFunctionCallMode::NonBlocking);
-                        },
-                        FrameAction::Suppress => {
-                            // Do nothing (bandwidth saving)
-                        }
-                    }
-                }
-                
-                // 4. Short sleep
-                if frame_buffer.len() < FRAME_SAMPLES {
-                    thread::sleep(Duration::from_millis(DSP_POLL_MS));
-                }
-            }
-            
-            println!("[SystemAudioCapture] DSP thread stopped.");
-        }));
+    /// Latest input levels (cheap to poll; zeros until `start`)
+    #[napi]
+    pub fn get_levels(&self) -> AudioLevels {
+        AudioLevels::from(self.runner.levels().snapshot())
+    }
 
-        Ok(())
+    /// `eventCallback` receives speech, format and device `CaptureEvent`s
+    #[napi]
+    pub fn start(&mut self, callback: JsFunction, event_callback: Option<JsFunction>) -> napi::Result<()> {
+        let tsfn = create_frame_callback(callback)?;
+        self.open_stream()?;
+
+        // DSP thread (suppression uses the quieter system audio thresholds)
+        let events = create_event_callback(event_callback)?;
+        *self.events.lock().unwrap_or_else(|e| e.into_inner()) = events.clone();
+        let vad_stage = create_vad_stage(events.clone(), self.vad_detector.as_deref(), self.get_sample_rate())?;
+        let format_listener = create_format_listener(events, self.get_sample_rate());
+        self.runner.start_streaming(frame_sender(tsfn), vad_stage, format_listener)
+            .map_err(|e| napi::Error::from_reason(e.to_string()))
     }
 
     #[napi]
     pub fn stop(&mut self) {
-        self.stop_signal.store(true, Ordering::SeqCst);
-        if let Some(handle) = self.capture_thread.take() {
-            let _ = handle.join();
+        if let Err(e) = self.runner.stop_streaming() {
+            log::error!("[SystemAudioCapture] Failed to keep recording: {}", e);
         }
-        self.stream = None;
+        *self.events.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.close_stream_if_idle();
+    }
+
+    /// Record to a WAV or FLAC file, whether or not `start` was called
+    #[napi]
+    pub fn start_recording(&mut self, path: String, options: Option<RecordingOptions>) -> napi::Result<()> {
+        let config = recorder_config(&path, options)?;
+        self.open_stream()?;
+        let result = self.runner.start_recording(config)
+            .map_err(|e| napi::Error::from_reason(e.to_string()));
+        self.close_stream_if_idle();
+        result
+    }
+
+    /// Finalize the recording; returns the paths of the files written
+    #[napi]
+    pub fn stop_recording(&mut self) -> napi::Result<Vec<String>> {
+        let result = self.runner.stop_recording()
+            .map(paths_to_strings)
+            .map_err(|e| napi::Error::from_reason(e.to_string()));
+        self.close_stream_if_idle();
+        result
+    }
+
+    /// Lazy init: open the backend on first use
+    fn open_stream(&mut self) -> napi::Result<()> {
+        if self.runner.has_input() {
+            return Ok(());
+        }
+        let notices = create_notice_sink(&self.events, &self.errors, self.get_sample_rate());
+        let backend = self.backend;
+
+        if self.follow_default {
+            let errors = self.errors.clone();
+            let opener: Opener = Box::new(move || speaker::open(backend, None, errors.clone()));
+            let (monitor, consumer, input, channels) =
+                DeviceMonitor::follow("SystemAudioCapture", DeviceDirection::Output, opener, notices)
+                    .map_err(|e| napi::Error::from_reason(format!("Failed: {}", e)))?;
+            self.runner.attach_input(consumer, input, channels);
+            self.monitor = Some(monitor);
+            return Ok(());
+        }
+
+        let watched = self.device_id.clone().filter(|id| !id.is_empty() && id != "default");
+        log::info!("[SystemAudioCapture] Opening {} backend...", backend.name());
+        let mut stream = match speaker::open(backend, self.device_id.clone(), self.errors.clone()) {
+            Ok(stream) => stream,
+            Err(e) if watched.is_some() => {
+                log::warn!("[SystemAudioCapture] Failed: {}. Trying default...", e);
+                speaker::open(backend, None, self.errors.clone())
+                    .map_err(|e2| napi::Error::from_reason(format!("Failed: {}", e2)))?
+            }
+            Err(e) => return Err(napi::Error::from_reason(format!("Failed: {}", e))),
+        };
+        let consumer = start_and_take(stream.as_mut())
+            .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+
+        let input = Arc::new(PipelineInput::new(stream.sample_rate()));
+        self.runner.attach_input(consumer, input.clone(), stream.channels());
+        self.stream = Some(stream);
+        self.monitor = Some(DeviceMonitor::watch("SystemAudioCapture", DeviceDirection::Output, watched, input, notices));
+        Ok(())
+    }
+
+    fn close_stream_if_idle(&mut self) {
+        if !self.runner.is_active() {
+            self.runner.detach_input();
+            self.stream = None;
+            self.monitor = None;
+        }
+    }
+
+    /// Feed this source's frames to a tap (the stereo mixer)
+    fn attach_tap(&mut self, tap: Arc<dyn PipelineTap>) -> napi::Result<()> {
+        self.open_stream()?;
+        let result = self.runner.attach_tap(tap)
+            .map_err(|e| napi::Error::from_reason(e.to_string()));
+        self.close_stream_if_idle();
+        result
+    }
+
+    fn detach_tap(&mut self, tap: &Arc<dyn PipelineTap>) {
+        self.runner.detach_tap(tap);
+        self.close_stream_if_idle();
     }
 }
 
@@ -179,133 +460,575 @@ impl SystemAudioCapture {
 // MICROPHONE CAPTURE (CPAL)
 // ============================================================================
 
+/// Snapshot of the microphone's `aec` stage
+#[napi(object)]
+pub struct EchoCancellerStats {
+    /// Echo return loss enhancement in dB (higher = more echo removed)
+    pub erle_db: f64,
+    /// Estimated speaker -> microphone delay in ms
+    pub delay_ms: f64,
+    /// Whether a delay estimate has locked yet
+    pub converged: bool,
+}
+
 #[napi]
 pub struct MicrophoneCapture {
-    stop_signal: Arc<AtomicBool>,
-    capture_thread: Option<thread::JoinHandle<()>>,
-    sample_rate: u32,
-    input: Option<microphone::MicrophoneStream>,
+    channels: u32,
+    vad_detector: Option<String>,
+    /// Explicitly requested device, reported when it disappears
+    device_id: Option<String>,
+    config: microphone::MicrophoneConfig,
+    backend: InputBackend,
+    follow_default: bool,
+    runner: PipelineRunner,
+    /// Unused in follow mode, where the monitor thread owns the stream
+    input: Option<Box<dyn CaptureBackend>>,
+    monitor: Option<DeviceMonitor>,
+    events: EventSlot,
+    errors: Arc<ErrorReporter>,
 }
 
 #[napi]
 impl MicrophoneCapture {
     #[napi(constructor)]
-    pub fn new(device_id: Option<String>) -> napi::Result<Self> {
-        let input = match microphone::MicrophoneStream::new(device_id) {
+    pub fn new(device_id: Option<String>, options: Option<CaptureOptions>) -> napi::Result<Self> {
+        let options = options.unwrap_or_default();
+        let output = output_format(&options)?;
+        let delivery = delivery_config(&options)?;
+        let channel_policy = ChannelPolicy::from_options(
+            options.channel_policy.as_deref(),
+            options.channel,
+        ).map_err(|e| napi::Error::new(napi::Status::InvalidArg, e.to_string()))?;
+
+        let backend = InputBackend::from_name(options.backend.as_deref())
+            .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e.to_string()))?;
+        let follow_default = follow_default(&options, device_id.as_deref())?;
+        let config = microphone::MicrophoneConfig {
+            fallback_to_default: options.fallback_to_default.unwrap_or(false),
+            channel_policy,
+        };
+
+        let errors = Arc::new(ErrorReporter::new("MicrophoneCapture"));
+        let input = match microphone::open(backend, device_id.clone(), config.clone(), errors.clone()) {
             Ok(i) => i,
+            Err(e) if e.is::<microphone::DeviceNotFound>() => {
+                return Err(napi::Error::new(napi::Status::InvalidArg, e.to_string()));
+            }
             Err(e) => return Err(napi::Error::from_reason(format!("Failed: {}", e))),
         };
         
-        let sample_rate = 16000;
+        let channels = input.channels() as u32;
+        // Checked against the opened device: "all" keeps its channel count
+        let stages = stage_options(&options, SourceKind::Microphone, &output, input.channels())?;
+        // Only watched when it is the device that actually opened
+        let device_id = device_id.filter(|id| input.device_id().as_ref() == Some(id));
 
         Ok(MicrophoneCapture {
-            stop_signal: Arc::new(AtomicBool::new(false)),
-            capture_thread: None,
-            sample_rate,
-            input: Some(input),
+            channels,
+            vad_detector: options.vad_detector,
+            device_id,
+            config,
+            backend,
+            follow_default,
+            runner: PipelineRunner::new("MicrophoneCapture", SourceKind::Microphone, stages, output)
+                .with_delivery(delivery)
+                .with_errors(errors.clone()),
+            // In follow mode the probe stream only validated the device
+            input: (!follow_default).then_some(input),
+            monitor: None,
+            events: EventSlot::default(),
+            errors,
         })
     }
 
+    /// `callback` receives a `CaptureError` whenever the device fails
+    /// (pass null to remove it); independent of `start`/`stop`
+    #[napi]
+    pub fn on_error(&mut self, callback: Option<JsFunction>) -> napi::Result<()> {
+        self.errors.set_listener(create_error_listener(callback)?);
+        Ok(())
+    }
+
+    /// Output rate of delivered frames (the `sampleRate` option, default 16000)
     #[napi]
     pub fn get_sample_rate(&self) -> u32 {
-        self.sample_rate
+        self.runner.output().sample_rate
     }
 
+    /// Duration of each delivered frame in ms (the `frameMs` option, default 20)
     #[napi]
-    pub fn start(&mut self, callback: JsFunction) -> napi::Result<()> {
-        let tsfn: ThreadsafeFunction<Vec<i16>, ErrorStrategy::Fatal> = callback
-            .create_threadsafe_function(0, |ctx| {
-                let vec: Vec<i16> = ctx.value;
-                let mut pcm_bytes = Vec::with_capacity(vec.len() * 2);
-                for sample in vec {
-                    pcm_bytes.extend_from_slice(&sample.to_le_bytes());
-                }
-                Ok(vec![pcm_bytes])
-            })?;
+    pub fn get_frame_ms(&self) -> u32 {
+        self.runner.output().frame_ms
+    }
 
-        self.stop_signal.store(false, Ordering::SeqCst);
-        let stop_signal = self.stop_signal.clone();
-        
+    /// Interleaved channels per delivered frame (1 unless channelPolicy is "all")
+    #[napi]
+    pub fn get_channels(&self) -> u32 {
+        self.channels
+    }
+
+    /// Cancel the audio of `systemAudio` in this microphone's `aec` stage.
+    /// Applies from the next `start`.
+    #[napi]
+    pub fn set_echo_reference(&mut self, system_audio: &SystemAudioCapture) {
+        self.runner.set_echo_reference(system_audio.runner.echo_reference());
+    }
+
+    /// Unlink the echo reference; the `aec` stage passes audio through
+    /// from the next `start`
+    #[napi]
+    pub fn clear_echo_reference(&mut self) {
+        self.runner.set_echo_reference(None);
+    }
+
+    /// Echo canceller state, or null when no `aec` stage is running
+    #[napi]
+    pub fn get_echo_stats(&self) -> Option<EchoCancellerStats> {
+        self.runner.metrics().echo.as_ref().map(|stats| EchoCancellerStats {
+            erle_db: stats.erle_db.load() as f64,
+            delay_ms: stats.delay_ms.load() as f64,
+            converged: stats.converged.load(Ordering::Relaxed),
+        })
+    }
+
+    /// Gain currently applied by the `agc` stage in dB, or null without one
+    #[napi]
+    pub fn get_gain_db(&self) -> Option<f64> {
+        self.runner.metrics().agc.as_ref().map(|stats| stats.gain_db.load() as f64)
+    }
+
+    /// Latest input levels (cheap to poll; zeros until `start`)
+    #[napi]
+    pub fn get_levels(&self) -> AudioLevels {
+        AudioLevels::from(self.runner.levels().snapshot())
+    }
+
+    /// `eventCallback` receives speech, format and device `CaptureEvent`s
+    #[napi]
+    pub fn start(&mut self, callback: JsFunction, event_callback: Option<JsFunction>) -> napi::Result<()> {
+        let tsfn = create_frame_callback(callback)?;
+        self.open_input()?;
+
+        // DSP thread (suppression uses the standard microphone thresholds)
+        let events = create_event_callback(event_callback)?;
+        *self.events.lock().unwrap_or_else(|e| e.into_inner()) = events.clone();
+        let vad_stage = create_vad_stage(events.clone(), self.vad_detector.as_deref(), self.get_sample_rate())?;
+        let format_listener = create_format_listener(events, self.get_sample_rate());
+        self.runner.start_streaming(frame_sender(tsfn), vad_stage, format_listener)
+            .map_err(|e| napi::Error::from_reason(e.to_string()))
+    }
+
+    #[napi]
+    pub fn stop(&mut self) {
+        if let Err(e) = self.runner.stop_streaming() {
+            log::error!("[MicrophoneCapture] Failed to keep recording: {}", e);
+        }
+        *self.events.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.pause_if_idle();
+    }
+
+    /// Record to a WAV or FLAC file, whether or not `start` was called
+    #[napi]
+    pub fn start_recording(&mut self, path: String, options: Option<RecordingOptions>) -> napi::Result<()> {
+        let config = recorder_config(&path, options)?;
+        self.open_input()?;
+        let result = self.runner.start_recording(config)
+            .map_err(|e| napi::Error::from_reason(e.to_string()));
+        self.pause_if_idle();
+        result
+    }
+
+    /// Finalize the recording; returns the paths of the files written
+    #[napi]
+    pub fn stop_recording(&mut self) -> napi::Result<Vec<String>> {
+        let result = self.runner.stop_recording()
+            .map(paths_to_strings)
+            .map_err(|e| napi::Error::from_reason(e.to_string()));
+        self.pause_if_idle();
+        result
+    }
+
+    /// Start the device and hand its consumer to the runner (first use only)
+    fn open_input(&mut self) -> napi::Result<()> {
+        if self.follow_default {
+            return self.open_following();
+        }
         let input_ref = self.input.as_mut()
             .ok_or_else(|| napi::Error::from_reason("Input missing"))?;
         
-        input_ref.play().map_err(|e| napi::Error::from_reason(format!("{}", e)))?;
-        
-        let input_sample_rate = input_ref.sample_rate() as f64;
-        let mut consumer = input_ref.take_consumer()
-            .ok_or_else(|| napi::Error::from_reason("Failed to get consumer"))?;
-
-        // DSP thread with silence suppression
-        self.capture_thread = Some(thread::spawn(move || {
-            let mut resampler = StreamingResampler::new(input_sample_rate, 16000.0);
-            let mut frame_buffer: Vec<i16> = Vec::with_capacity(FRAME_SAMPLES * 4);
-            let mut raw_batch: Vec<f32> = Vec::with_capacity(4096);
-            
-            // Use microphone config (standard threshold)
-            let mut suppressor = SilenceSuppressor::new(
-                SilenceSuppressionConfig::for_microphone()
-            );
-
-            println!("[MicrophoneCapture] DSP thread started (suppression active)");
-
-            loop {
-                if stop_signal.load(Ordering::Relaxed) {
-                    break;
-                }
-                
-                // 1. Drain ring buffer (lock-free)
-                let mut batch_count = 0;
-                while let Some(sample) = consumer.try_pop() {
-                    raw_batch.push(sample);
-                    batch_count += 1;
-                    if raw_batch.len() >= 480 {
-                        break;
-                    }
-                }
-                
-                // 2. Resample
-                if !raw_batch.is_empty() {
-                    let resampled = resampler.resample(&raw_batch);
-                    frame_buffer.extend(resampled);
-                    raw_batch.clear();
-                }
-
-                // 3. Process frames with Silence Suppression
-                while frame_buffer.len() >= FRAME_SAMPLES {
-                    let frame: Vec<i16> = frame_buffer.drain(0..FRAME_SAMPLES).collect();
-                    match suppressor.process(&frame) {
-                        FrameAction::Send(audio) => {
-                             tsfn.call(audio, ThreadsafeFunctionCallMode::NonBlocking);
-                        },
-                        FrameAction::SendSilence => {
-                             tsfn.call(generate_silence_frame(FRAME_SAMPLES), ThreadsafeFunctionCallMode::NonBlocking);
-                        },
-                         FrameAction::Suppress => {
-                            // Do nothing
-                        }
-                    }
-                }
-                
-                // 4. Short sleep
-                if frame_buffer.len() < FRAME_SAMPLES {
-                    thread::sleep(Duration::from_millis(DSP_POLL_MS));
-                }
-            }
-            
-            println!("[MicrophoneCapture] DSP thread stopped.");
-        }));
+        input_ref.start().map_err(|e| napi::Error::from_reason(format!("{}", e)))?;
 
+        if !self.runner.has_input() {
+            let input_sample_rate = input_ref.sample_rate();
+            let channels = input_ref.channels();
+            let consumer = input_ref.take_consumer()
+                .ok_or_else(|| napi::Error::from_reason("Failed to get consumer"))?;
+            self.runner.attach_input(consumer, Arc::new(PipelineInput::new(input_sample_rate)), channels);
+        }
+        if let (None, Some(input)) = (self.monitor.as_ref(), self.runner.input()) {
+            let notices = create_notice_sink(&self.events, &self.errors, self.get_sample_rate());
+            self.monitor = Some(DeviceMonitor::watch(
+                "MicrophoneCapture", DeviceDirection::Input, self.device_id.clone(), input, notices,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Follow mode: the monitor thread opens the default device
+    fn open_following(&mut self) -> napi::Result<()> {
+        if self.runner.has_input() {
+            return Ok(());
+        }
+        let (backend, config, errors) = (self.backend, self.config.clone(), self.errors.clone());
+        let opener: Opener = Box::new(move || microphone::open(backend, None, config.clone(), errors.clone()));
+        let notices = create_notice_sink(&self.events, &self.errors, self.get_sample_rate());
+        let (monitor, consumer, input, channels) =
+            DeviceMonitor::follow("MicrophoneCapture", DeviceDirection::Input, opener, notices)
+                .map_err(|e| napi::Error::from_reason(format!("Failed: {}", e)))?;
+        self.runner.attach_input(consumer, input, channels);
+        self.monitor = Some(monitor);
         Ok(())
     }
 
+    fn pause_if_idle(&mut self) {
+        if !self.runner.is_active() {
+            if let Some(input) = self.input.as_mut() {
+                let _ = input.stop();
+            }
+            if self.follow_default {
+                self.runner.detach_input();
+            }
+            self.monitor = None;
+        }
+    }
+
+    /// Feed this source's frames to a tap (the stereo mixer)
+    fn attach_tap(&mut self, tap: Arc<dyn PipelineTap>) -> napi::Result<()> {
+        self.open_input()?;
+        let result = self.runner.attach_tap(tap)
+            .map_err(|e| napi::Error::from_reason(e.to_string()));
+        self.pause_if_idle();
+        result
+    }
+
+    fn detach_tap(&mut self, tap: &Arc<dyn PipelineTap>) {
+        self.runner.detach_tap(tap);
+        self.pause_if_idle();
+    }
+}
+
+// ============================================================================
+// FILE CAPTURE (WAV/FLAC playback for demos and tests)
+// ============================================================================
+
+/// Plays a WAV or FLAC file through the same pipeline as a live capture
+#[napi]
+pub struct FileAudioCapture {
+    audio: Arc<DecodedAudio>,
+    channel_policy: ChannelPolicy,
+    channels: u32,
+    playback: Playback,
+    vad_detector: Option<String>,
+    runner: PipelineRunner,
+    backend: Option<Box<dyn CaptureBackend>>,
+    events: EventSlot,
+}
+
+#[napi]
+impl FileAudioCapture {
+    /// Decodes `path` up front; `options` are the pipeline options of
+    /// `MicrophoneCapture` (device options are ignored)
+    #[napi(constructor)]
+    pub fn new(path: String, options: Option<CaptureOptions>, playback: Option<FilePlaybackOptions>) -> napi::Result<Self> {
+        let options = options.unwrap_or_default();
+        let playback = playback.unwrap_or_default();
+        let output = output_format(&options)?;
+        let delivery = delivery_config(&options)?;
+        let invalid = |e: anyhow::Error| napi::Error::new(napi::Status::InvalidArg, e.to_string());
+        let source = SourceKind::from_name(playback.source.as_deref()).map_err(invalid)?;
+        let speed = playback.speed.unwrap_or(1.0);
+        if !speed.is_finite() || speed < 0.0 {
+            return Err(napi::Error::new(napi::Status::InvalidArg, format!("Invalid playback speed: {}", speed)));
+        }
+
+        let audio = file_source::decode_file(std::path::Path::new(&path))
+            .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+        let channel_policy = ChannelPolicy::from_options(options.channel_policy.as_deref(), options.channel)
+            .map_err(invalid)?;
+        // Validates the channel index against the file
+        let mixer = channel_mix::ChannelMixer::new(channel_policy, audio.channels).map_err(invalid)?;
+        let stages = stage_options(&options, source, &output, mixer.output_channels())?;
+
+        Ok(FileAudioCapture {
+            audio: Arc::new(audio),
+            channel_policy,
+            channels: mixer.output_channels() as u32,
+            playback: Playback { speed, repeat: playback.repeat.unwrap_or(false) },
+            vad_detector: options.vad_detector,
+            runner: PipelineRunner::new("FileAudioCapture", source, stages, output).with_delivery(delivery),
+            backend: None,
+            events: EventSlot::default(),
+        })
+    }
+
+    /// Output rate of delivered frames (the `sampleRate` option, default 16000)
+    #[napi]
+    pub fn get_sample_rate(&self) -> u32 {
+        self.runner.output().sample_rate
+    }
+
+    /// Duration of each delivered frame in ms (the `frameMs` option, default 20)
+    #[napi]
+    pub fn get_frame_ms(&self) -> u32 {
+        self.runner.output().frame_ms
+    }
+
+    /// Interleaved channels per delivered frame (1 unless channelPolicy is "all")
+    #[napi]
+    pub fn get_channels(&self) -> u32 {
+        self.channels
+    }
+
+    /// Length of the file in seconds
+    #[napi]
+    pub fn get_duration(&self) -> f64 {
+        self.audio.duration_secs()
+    }
+
+    /// Latest input levels (cheap to poll; zeros until `start`)
+    #[napi]
+    pub fn get_levels(&self) -> AudioLevels {
+        AudioLevels::from(self.runner.levels().snapshot())
+    }
+
+    /// Play the file from the start. `eventCallback` receives speech and
+    /// format events, plus `fileEnded` once the whole file was pushed.
+    #[napi]
+    pub fn start(&mut self, callback: JsFunction, event_callback: Option<JsFunction>) -> napi::Result<()> {
+        let tsfn = create_frame_callback(callback)?;
+        let events = create_event_callback(event_callback)?;
+        *self.events.lock().unwrap_or_else(|e| e.into_inner()) = events.clone();
+        self.open_player()?;
+
+        let vad_stage = create_vad_stage(events.clone(), self.vad_detector.as_deref(), self.get_sample_rate())?;
+        let format_listener = create_format_listener(events, self.get_sample_rate());
+        self.runner.start_streaming(frame_sender(tsfn), vad_stage, format_listener)
+            .map_err(|e| napi::Error::from_reason(e.to_string()))
+    }
+
+    /// Stop playback; the next `start` plays the file from the beginning
     #[napi]
     pub fn stop(&mut self) {
-        self.stop_signal.store(true, Ordering::SeqCst);
-        if let Some(handle) = self.capture_thread.take() {
-            let _ = handle.join();
+        if let Err(e) = self.runner.stop_streaming() {
+            log::error!("[FileAudioCapture] Failed to stop: {}", e);
         }
-        if let Some(input) = self.input.as_ref() {
-            let _ = input.pause();
+        *self.events.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.backend = None;
+        self.runner.detach_input();
+    }
+
+    /// Start the player thread and hand its consumer to the runner
+    fn open_player(&mut self) -> napi::Result<()> {
+        if self.backend.is_some() {
+            return Ok(());
+        }
+
+        // Where the last sample lands on the output timeline
+        let output_rate = self.get_sample_rate();
+        let frames = (self.audio.samples.len() / self.audio.channels) as u64;
+        let end_offset = frames * output_rate as u64 / self.audio.sample_rate as u64;
+        let events = self.events.clone();
+        let on_end = Box::new(move || {
+            let callback = events.lock().ok().and_then(|events| events.clone());
+            if let Some(callback) = callback {
+                callback.call(CaptureEvent::new("fileEnded", end_offset, output_rate, 0.0), ThreadsafeFunctionCallMode::NonBlocking);
+            }
+        });
+        let mut backend = FileBackend::open(self.audio.clone(), self.channel_policy, self.playback, on_end)
+            .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+        let consumer = start_and_take(&mut backend)
+            .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+        self.runner.attach_input(consumer, Arc::new(PipelineInput::new(backend.sample_rate())), backend.channels());
+        self.backend = Some(Box::new(backend));
+        Ok(())
+    }
+}
+
+// ============================================================================
+// VIRTUAL INPUT (JS-fed microphone for integration tests)
+// ============================================================================
+
+/// An input device fed from JS: listed by `getInputDevices` as
+/// "virtual:<name>" and opened by `MicrophoneCapture` like any microphone
+#[napi]
+pub struct VirtualAudioInput {
+    device: Option<Arc<VirtualDevice>>,
+}
+
+#[napi]
+impl VirtualAudioInput {
+    #[napi(constructor)]
+    pub fn new(name: String, options: Option<VirtualInputOptions>) -> napi::Result<Self> {
+        let options = options.unwrap_or_default();
+        let invalid = |e: anyhow::Error| napi::Error::new(napi::Status::InvalidArg, e.to_string());
+        let format = PcmFormat::from_name(options.sample_format.as_deref()).map_err(invalid)?;
+        let device = VirtualDevice::register(
+            &name,
+            options.sample_rate.unwrap_or(48000),
+            options.channels.unwrap_or(1) as usize,
+            format,
+        ).map_err(invalid)?;
+        Ok(VirtualAudioInput { device: Some(device) })
+    }
+
+    /// Device id to pass to `MicrophoneCapture`
+    #[napi]
+    pub fn get_id(&self) -> napi::Result<String> {
+        Ok(self.device()?.id().to_string())
+    }
+
+    /// Feed interleaved little-endian PCM in the configured format. Returns
+    /// the number of frames accepted: fewer than pushed when the capture's
+    /// ring buffer is full (push the rest later), 0 while nothing captures.
+    #[napi]
+    pub fn push(&self, data: Buffer) -> napi::Result<u32> {
+        self.device()?.push(&data)
+            .map(|frames| frames as u32)
+            .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e.to_string()))
+    }
+
+    /// Remove the device; a capture reading it reports it as lost
+    #[napi]
+    pub fn close(&mut self) {
+        if let Some(device) = self.device.take() {
+            device.unregister();
+        }
+    }
+
+    fn device(&self) -> napi::Result<&Arc<VirtualDevice>> {
+        self.device.as_ref().ok_or_else(|| napi::Error::from_reason("Virtual input is closed"))
+    }
+}
+
+impl Drop for VirtualAudioInput {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+// ============================================================================
+// STEREO MIXER
+// ============================================================================
+
+/// Time-aligned stereo stream at 16kHz: microphone on the left, system audio
+/// on the right (for multichannel STT and post-meeting review)
+#[napi]
+pub struct StereoMixer {
+    recording: Option<recorder::RecorderConfig>,
+    mixer: Option<mixer::Mixer>,
+    /// Captures the lanes are attached to; weak so a collected capture
+    /// (which stops its own pipeline) is simply skipped
+    microphone: Option<WeakReference<MicrophoneCapture>>,
+    system_audio: Option<WeakReference<SystemAudioCapture>>,
+}
+
+#[napi]
+impl StereoMixer {
+    /// `path` records the mix to WAV/FLAC (`options.source` is ignored);
+    /// without it the mix only goes to the `start` callback
+    #[napi(constructor)]
+    pub fn new(path: Option<String>, options: Option<RecordingOptions>) -> napi::Result<Self> {
+        let recording = path.map(|path| recorder_config(&path, options)).transpose()?;
+        Ok(StereoMixer { recording, mixer: None, microphone: None, system_audio: None })
+    }
+
+    #[napi]
+    pub fn get_sample_rate(&self) -> u32 {
+        mixer::MIXER_SAMPLE_RATE
+    }
+
+    #[napi]
+    pub fn get_channels(&self) -> u32 {
+        mixer::MIXER_CHANNELS as u32
+    }
+
+    /// Start mixing; both captures run for the mixer whether or not their own
+    /// `start` was called. `callback` receives interleaved stereo i16 PCM
+    /// chunks of varying length and their `FrameInfo`.
+    #[napi]
+    pub fn start(
+        &mut self,
+        mut microphone: Reference<MicrophoneCapture>,
+        mut system_audio: Reference<SystemAudioCapture>,
+        callback: Option<JsFunction>,
+    ) -> napi::Result<()> {
+        if self.mixer.is_some() {
+            return Err(napi::Error::from_reason("Mixer is already running"));
+        }
+        if microphone.get_sample_rate() != mixer::MIXER_SAMPLE_RATE || system_audio.get_sample_rate() != mixer::MIXER_SAMPLE_RATE {
+            return Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("Both captures must output {}Hz to be mixed", mixer::MIXER_SAMPLE_RATE),
+            ));
+        }
+
+        let sink = callback.map(create_frame_callback).transpose()?
+            .map(|tsfn| -> mixer::MixSink {
+                let mut sequencer = FrameSequencer::new(mixer::MIXER_SAMPLE_RATE);
+                Box::new(move |frame| {
+                    let info = sequencer.next(&frame);
+                    let data = FrameData::buffer(&OutputFormat::default(), frame.samples);
+                    tsfn.call(DeliveredFrame { data, info }, ThreadsafeFunctionCallMode::NonBlocking);
+                })
+            });
+        let mixer = mixer::Mixer::start(self.recording.clone(), sink)
+            .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+
+        let [left, right] = mixer.lanes();
+        microphone.attach_tap(left)?;
+        if let Err(e) = system_audio.attach_tap(right) {
+            microphone.detach_tap(&mixer.lanes()[0]);
+            return Err(e);
+        }
+        self.mixer = Some(mixer);
+        self.microphone = Some(microphone.downgrade());
+        self.system_audio = Some(system_audio.downgrade());
+        Ok(())
+    }
+
+    /// Finalize the mix; returns the paths of the files written. Captures
+    /// that were only running for the mixer release their devices.
+    #[napi]
+    pub fn stop(&mut self) -> napi::Result<Vec<String>> {
+        let mixer = self.mixer.take()
+            .ok_or_else(|| napi::Error::from_reason("Mixer is not running"))?;
+        let lanes = mixer.lanes();
+        let result = mixer.stop();
+        self.detach_lanes(&lanes);
+        result
+            .map(paths_to_strings)
+            .map_err(|e| napi::Error::from_reason(e.to_string()))
+    }
+
+    fn detach_lanes(&mut self, [left, right]: &[Arc<dyn PipelineTap>; mixer::MIXER_CHANNELS]) {
+        if let Some(microphone) = self.microphone.take().as_mut().and_then(|m| m.get_mut()) {
+            microphone.detach_tap(left);
+        }
+        if let Some(system_audio) = self.system_audio.take().as_mut().and_then(|s| s.get_mut()) {
+            system_audio.detach_tap(right);
+        }
+    }
+}
+
+impl Drop for StereoMixer {
+    /// Collected while mixing: finalize the mix and release the captures
+    fn drop(&mut self) {
+        if let Some(mixer) = self.mixer.take() {
+            let lanes = mixer.lanes();
+            if let Err(e) = mixer.stop() {
+                log::error!("[StereoMixer] Failed to finalize mix: {}", e);
+            }
+            self.detach_lanes(&lanes);
         }
     }
 }
@@ -314,20 +1037,49 @@ impl MicrophoneCapture {
 // DEVICE ENUMERATION
 // ============================================================================
 
+/// A device and what it supports; empty lists mean the backend can't tell
 #[napi(object)]
 pub struct AudioDeviceInfo {
     pub id: String,
     pub name: String,
+    /// The current system default for its direction
+    pub is_default: bool,
+    pub sample_rates: Vec<u32>,
+    pub channel_counts: Vec<u32>,
+    /// e.g. "f32", "i16"
+    pub sample_formats: Vec<String>,
+    /// "builtIn" | "usb" | "bluetooth" | "hdmi" | "virtual" | ... where the
+    /// platform reports it
+    pub transport: Option<String>,
+}
+
+impl From<devices::DeviceDetails> for AudioDeviceInfo {
+    fn from(details: devices::DeviceDetails) -> Self {
+        Self {
+            id: details.id,
+            name: details.name,
+            is_default: details.is_default,
+            sample_rates: details.sample_rates,
+            channel_counts: details.channel_counts.into_iter().map(u32::from).collect(),
+            sample_formats: details.sample_formats,
+            transport: details.transport,
+        }
+    }
+}
+
+/// Both device lists, as pushed by `watchDevices`
+#[napi(object)]
+pub struct DeviceList {
+    pub inputs: Vec<AudioDeviceInfo>,
+    pub outputs: Vec<AudioDeviceInfo>,
 }
 
 #[napi]
 pub fn get_input_devices() -> Vec<AudioDeviceInfo> {
-    match microphone::list_input_devices() {
-        Ok(devs) => devs.into_iter()
-            .map(|(id, name)| AudioDeviceInfo { id, name })
-            .collect(),
+    match devices::input_devices() {
+        Ok(devs) => devs.into_iter().map(AudioDeviceInfo::from).collect(),
         Err(e) => {
-            eprintln!("[get_input_devices] Error: {}", e);
+            log::error!("[get_input_devices] Error: {}", e);
             Vec::new()
         }
     }
@@ -335,13 +1087,72 @@ pub fn get_input_devices() -> Vec<AudioDeviceInfo> {
 
 #[napi]
 pub fn get_output_devices() -> Vec<AudioDeviceInfo> {
-    match speaker::list_output_devices() {
-        Ok(devs) => devs.into_iter()
-            .map(|(id, name)| AudioDeviceInfo { id, name })
-            .collect(),
+    match devices::output_devices() {
+        Ok(devs) => devs.into_iter().map(AudioDeviceInfo::from).collect(),
         Err(e) => {
-            eprintln!("[get_output_devices] Error: {}", e);
+            log::error!("[get_output_devices] Error: {}", e);
             Vec::new()
         }
     }
 }
+
+/// Subscription returned by `watchDevices`
+#[napi]
+pub struct DeviceWatcher {
+    monitor: Option<DeviceMonitor>,
+}
+
+#[napi]
+impl DeviceWatcher {
+    /// Stop polling; the callback is not called again
+    #[napi]
+    pub fn stop(&mut self) {
+        self.monitor = None;
+    }
+}
+
+/// Call `callback` with a `DeviceList` now and whenever a device is added,
+/// removed or renamed, or a default device changes
+#[napi]
+pub fn watch_devices(callback: JsFunction) -> napi::Result<DeviceWatcher> {
+    let tsfn: ThreadsafeFunction<DeviceList, ErrorStrategy::Fatal> =
+        callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+    let push = move || {
+        let list = DeviceList { inputs: get_input_devices(), outputs: get_output_devices() };
+        tsfn.call(list, ThreadsafeFunctionCallMode::NonBlocking);
+    };
+    push();
+    Ok(DeviceWatcher { monitor: Some(DeviceMonitor::watch_lists(Box::new(push))) })
+}
+
+// ============================================================================
+// LOGGING
+// ============================================================================
+
+/// Set the log level ("off" | "error" | "warn" | "info" | "debug" | "trace")
+/// for `target` (a module path such as "pipeline" or "speaker::linux"), or
+/// for everything without one. Defaults to "info".
+#[napi]
+pub fn set_log_level(level: String, target: Option<String>) -> napi::Result<()> {
+    let level = logging::parse_level(&level)
+        .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e.to_string()))?;
+    logging::set_level(level, target.as_deref());
+    Ok(())
+}
+
+/// Forward every `LogRecord` to `callback` instead of stdout/stderr (pass
+/// null to go back to the console)
+#[napi]
+pub fn set_log_sink(callback: Option<JsFunction>) -> napi::Result<()> {
+    let sink = callback
+        .map(|callback| -> napi::Result<logging::LogSink> {
+            let tsfn: ThreadsafeFunction<logging::LogRecord, ErrorStrategy::Fatal> =
+                callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+            Ok(Arc::new(move |record| {
+                tsfn.call(record, ThreadsafeFunctionCallMode::NonBlocking);
+            }))
+        })
+        .transpose()?;
+    logging::set_sink(sink);
+    Ok(())
+}
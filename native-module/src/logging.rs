@@ -0,0 +1,173 @@
+// Logging - `log` facade backend with runtime levels and a JS sink
+//
+// Every module logs through the `log` macros; the target is the module path
+// without the crate name ("pipeline", "speaker::linux", ...). Levels are set
+// from JS with `setLogLevel`, either globally or for a target prefix (the
+// longest matching prefix wins). Records go to the console until `setLogSink`
+// installs a JS callback, after which they are forwarded there instead so
+// they end up in the app's log files.
+
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+
+const CRATE_PREFIX: &str = "natively_audio::";
+
+/// One log line, as handed to the JS sink
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord {
+    /// "error" | "warn" | "info" | "debug" | "trace"
+    pub level: String,
+    /// Module the record came from, e.g. "speaker::linux"
+    pub target: String,
+    pub message: String,
+    /// Wall clock time in ms since the Unix epoch
+    pub timestamp_ms: f64,
+}
+
+/// Receives records on whichever thread logged them
+pub type LogSink = Arc<dyn Fn(LogRecord) + Send + Sync>;
+
+/// Global level plus per-target overrides
+#[derive(Debug, Clone, PartialEq)]
+struct LevelConfig {
+    default: LevelFilter,
+    /// (target prefix, level), matched on whole path segments
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl Default for LevelConfig {
+    fn default() -> Self {
+        Self { default: LevelFilter::Info, targets: Vec::new() }
+    }
+}
+
+impl LevelConfig {
+    fn set(&mut self, level: LevelFilter, target: Option<&str>) {
+        match target.filter(|t| !t.is_empty()) {
+            None => self.default = level,
+            Some(target) => {
+                self.targets.retain(|(t, _)| t != target);
+                self.targets.push((target.to_string(), level));
+            }
+        }
+    }
+
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets.iter()
+            .filter(|(prefix, _)| target_matches(target, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    /// Most verbose level anything is enabled at (for `log::set_max_level`)
+    fn max(&self) -> LevelFilter {
+        self.targets.iter().map(|(_, level)| *level).fold(self.default, Ord::max)
+    }
+}
+
+/// "speaker" matches "speaker" and "speaker::linux", not "speakers"
+fn target_matches(target: &str, prefix: &str) -> bool {
+    target.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+fn short_target(target: &str) -> &str {
+    target.strip_prefix(CRATE_PREFIX).unwrap_or(target)
+}
+
+struct NativeLogger {
+    levels: RwLock<LevelConfig>,
+    sink: RwLock<Option<LogSink>>,
+}
+
+impl Log for NativeLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let levels = self.levels.read().unwrap_or_else(|e| e.into_inner());
+        metadata.level() <= levels.level_for(short_target(metadata.target()))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let target = short_target(record.target());
+        let sink = self.sink.read().ok().and_then(|sink| sink.clone());
+        match sink {
+            Some(sink) => sink(LogRecord {
+                level: record.level().as_str().to_ascii_lowercase(),
+                target: target.to_string(),
+                message: record.args().to_string(),
+                timestamp_ms: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs_f64() * 1000.0)
+                    .unwrap_or(0.0),
+            }),
+            None => match record.level() {
+                Level::Error | Level::Warn => eprintln!("{} {}", record.level(), record.args()),
+                _ => println!("{}", record.args()),
+            },
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Lazy<NativeLogger> = Lazy::new(|| NativeLogger {
+    levels: RwLock::new(LevelConfig::default()),
+    sink: RwLock::new(None),
+});
+
+/// Install the logger (once, when the addon loads)
+pub fn init() {
+    if log::set_logger(&*LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
+
+/// Parse a JS level name ("off" | "error" | "warn" | "info" | "debug" | "trace")
+pub fn parse_level(name: &str) -> Result<LevelFilter> {
+    name.parse::<LevelFilter>()
+        .map_err(|_| anyhow::anyhow!("Unknown log level: {}", name))
+}
+
+/// Set the level for `target` (a module path such as "pipeline" or
+/// "speaker::linux"), or the default level without one
+pub fn set_level(level: LevelFilter, target: Option<&str>) {
+    let mut levels = LOGGER.levels.write().unwrap_or_else(|e| e.into_inner());
+    levels.set(level, target);
+    log::set_max_level(levels.max());
+}
+
+/// Forward records to `sink` instead of the console (None restores it)
+pub fn set_sink(sink: Option<LogSink>) {
+    *LOGGER.sink.write().unwrap_or_else(|e| e.into_inner()) = sink;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_target_prefix_wins() {
+        let mut levels = LevelConfig::default();
+        levels.set(LevelFilter::Warn, None);
+        levels.set(LevelFilter::Debug, Some("speaker"));
+        levels.set(LevelFilter::Off, Some("speaker::sck"));
+
+        assert_eq!(levels.level_for("pipeline"), LevelFilter::Warn);
+        assert_eq!(levels.level_for("speaker"), LevelFilter::Debug);
+        assert_eq!(levels.level_for("speaker::linux"), LevelFilter::Debug);
+        assert_eq!(levels.level_for("speaker::sck"), LevelFilter::Off);
+        assert_eq!(levels.level_for("speakers"), LevelFilter::Warn);
+        assert_eq!(levels.max(), LevelFilter::Debug);
+
+        assert_eq!(short_target("natively_audio::speaker::linux"), "speaker::linux");
+        assert_eq!(parse_level("TRACE").unwrap(), LevelFilter::Trace);
+        assert!(parse_level("loud").is_err());
+    }
+}
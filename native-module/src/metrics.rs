@@ -0,0 +1,188 @@
+// Lock-free metrics shared between the DSP thread and the JS thread
+//
+// The DSP thread writes, N-API getters read. Everything is a relaxed
+// atomic: readers only ever need a recent value, never a consistent
+// snapshot across fields.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+/// f32 stored as its bit pattern in an AtomicU32
+#[derive(Debug, Default)]
+pub struct AtomicF32(AtomicU32);
+
+impl AtomicF32 {
+    pub fn new(value: f32) -> Self {
+        Self(AtomicU32::new(value.to_bits()))
+    }
+
+    pub fn load(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn store(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Echo canceller state published by the `aec` stage
+#[derive(Debug, Default)]
+pub struct EchoStats {
+    /// Echo return loss enhancement in dB (higher = more echo removed)
+    pub erle_db: AtomicF32,
+    /// Estimated far-end -> microphone delay in milliseconds
+    pub delay_ms: AtomicF32,
+    /// Set once a delay estimate has locked
+    pub converged: AtomicBool,
+}
+
+/// Gain applied by the `agc` stage
+#[derive(Debug, Default)]
+pub struct AgcStats {
+    /// Current AGC gain in dB (negative = attenuating)
+    pub gain_db: AtomicF32,
+}
+
+/// RMS history kept by `LevelMeter`, whatever the frame duration
+pub const LEVEL_HISTORY_MS: u32 = 1000;
+
+/// Floor reported for digital silence
+const MIN_DBFS: f32 = -100.0;
+
+/// Input levels of one capture source, written once per frame
+#[derive(Debug)]
+pub struct LevelMeter {
+    /// Peak of the latest frame (0.0 - 1.0)
+    peak: AtomicF32,
+    /// RMS of the latest frame (0.0 - 1.0)
+    rms: AtomicF32,
+    /// Samples at full scale since the meter was created
+    clip_count: AtomicU64,
+    /// RMS dBFS per frame, ring indexed by `frames`
+    history: Vec<AtomicF32>,
+    frames: AtomicUsize,
+}
+
+/// Point-in-time copy of a `LevelMeter`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelSnapshot {
+    pub peak: f32,
+    pub rms: f32,
+    pub dbfs: f32,
+    pub clip_count: u64,
+    /// RMS dBFS of recent frames, oldest first
+    pub history: Vec<f32>,
+}
+
+impl Default for LevelMeter {
+    /// Meter for the default 20ms frames
+    fn default() -> Self {
+        Self::new(crate::audio_config::FRAME_MS)
+    }
+}
+
+impl LevelMeter {
+    /// Meter keeping `LEVEL_HISTORY_MS` of history at `frame_ms` frames
+    pub fn new(frame_ms: u32) -> Self {
+        let history = (LEVEL_HISTORY_MS / frame_ms.max(1)).max(1) as usize;
+        Self {
+            peak: AtomicF32::new(0.0),
+            rms: AtomicF32::new(0.0),
+            clip_count: AtomicU64::new(0),
+            history: (0..history).map(|_| AtomicF32::new(MIN_DBFS)).collect(),
+            frames: AtomicUsize::new(0),
+        }
+    }
+
+    /// Measure one frame (DSP thread)
+    pub fn record(&self, samples: &[i16]) {
+        if samples.is_empty() {
+            return;
+        }
+
+        let mut peak = 0i32;
+        let mut energy = 0.0f64;
+        let mut clipped = 0u64;
+        for &s in samples {
+            peak = peak.max((s as i32).abs());
+            energy += (s as f64) * (s as f64);
+            if s == i16::MAX || s == i16::MIN {
+                clipped += 1;
+            }
+        }
+
+        let rms = ((energy / samples.len() as f64).sqrt() / 32768.0) as f32;
+        self.peak.store((peak as f32 / 32768.0).min(1.0));
+        self.rms.store(rms);
+        if clipped > 0 {
+            self.clip_count.fetch_add(clipped, Ordering::Relaxed);
+        }
+
+        let frame = self.frames.load(Ordering::Relaxed);
+        self.history[frame % self.history.len()].store(to_dbfs(rms));
+        self.frames.store(frame.wrapping_add(1), Ordering::Relaxed);
+    }
+
+    /// Read the latest values (any thread)
+    pub fn snapshot(&self) -> LevelSnapshot {
+        let frames = self.frames.load(Ordering::Relaxed);
+        let filled = frames.min(self.history.len());
+        let history = (frames - filled..frames)
+            .map(|i| self.history[i % self.history.len()].load())
+            .collect();
+        let rms = self.rms.load();
+
+        LevelSnapshot {
+            peak: self.peak.load(),
+            rms,
+            dbfs: to_dbfs(rms),
+            clip_count: self.clip_count.load(Ordering::Relaxed),
+            history,
+        }
+    }
+}
+
+fn to_dbfs(level: f32) -> f32 {
+    if level <= 0.0 {
+        MIN_DBFS
+    } else {
+        (20.0 * level.log10()).max(MIN_DBFS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_meter_tracks_peak_clips_and_history() {
+        let meter = LevelMeter::default();
+        assert!(meter.snapshot().history.is_empty());
+
+        meter.record(&[16384, -16384, 16384, -16384]);
+        meter.record(&[i16::MAX, i16::MIN, 0, 0]);
+
+        let levels = meter.snapshot();
+        assert_eq!(levels.clip_count, 2);
+        assert!((levels.peak - 1.0).abs() < 1e-3);
+        assert_eq!(levels.history.len(), 2);
+        assert!((levels.history[0] + 6.02).abs() < 0.05);
+
+        for _ in 0..100 {
+            meter.record(&[0; 4]);
+        }
+        let levels = meter.snapshot();
+        assert_eq!(levels.history.len(), 50);
+        assert_eq!(levels.dbfs, MIN_DBFS);
+    }
+
+    #[test]
+    fn test_level_history_covers_one_second_at_any_frame_duration() {
+        for (frame_ms, frames) in [(10, 100), (20, 50), (100, 10)] {
+            let meter = LevelMeter::new(frame_ms);
+            for _ in 0..200 {
+                meter.record(&[100; 4]);
+            }
+            assert_eq!(meter.snapshot().history.len(), frames);
+        }
+    }
+}
@@ -1,20 +1,53 @@
-// Microphone Capture - Lock-Free Real-Time Compliant
+// Microphone Capture - Real-Time Compliant
 // 
 // Architecture:
 // 1. CPAL callback: ONLY pushes to lock-free ring buffer
-// 2. No mutexes, allocations, or DSP in callback
-// 3. Background thread: drains buffer, resamples, emits to JS
+// 2. No allocations or DSP in callback; its mixer and producer sit behind a
+//    mutex that only a stream rebuild takes, and the callback never waits
+//    on it (try_lock, skip the block)
+// 3. Stream thread: owns the cpal stream (not Send) and rebuilds it at the
+//    device's new rate when cpal invalidates it
+// 4. Background thread: drains buffer, resamples, emits to JS
 
 use anyhow::Result;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{SampleFormat, Stream};
-use ringbuf::{traits::{Producer, Consumer, Split}, HeapRb, HeapProd, HeapCons};
-use std::sync::Arc;
+use cpal::{FromSample, SampleFormat, SizedSample, Stream};
+use ringbuf::{traits::{Observer, Split}, HeapRb, HeapProd, HeapCons};
+use std::sync::{mpsc, Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
 
 use crate::audio_config::RING_BUFFER_SAMPLES;
+use crate::backend::{BackendCapabilities, CaptureBackend, InputBackend};
+use crate::channel_mix::{ChannelMixer, ChannelPolicy};
+use crate::errors::{CaptureError, ErrorCode, ErrorReporter};
+use crate::pipeline::{fixed_rate, DeviceRate, RateHandle};
+use crate::virtual_input::{self, VirtualDevice};
 
-/// List available input devices
+/// Error returned when a requested input device id is no longer present
+#[derive(Debug)]
+pub struct DeviceNotFound(pub String);
+
+impl std::fmt::Display for DeviceNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Input device not found: {}", self.0)
+    }
+}
+
+impl std::error::Error for DeviceNotFound {}
+
+/// Human-readable device name
+fn device_name(device: &cpal::Device) -> String {
+    device.description()
+        .map(|d| d.name().to_string())
+        .unwrap_or_default()
+}
+
+/// List available input devices as (id, name)
+///
+/// Ids come from `cpal::DeviceId` ("<host>:<native id>", e.g. the CoreAudio
+/// UID or WASAPI endpoint id), so they survive renames and reboots. Virtual
+/// devices registered from JS follow as "virtual:<name>".
 pub fn list_input_devices() -> Result<Vec<(String, String)>> {
     let host = cpal::default_host();
     let mut list = Vec::new();
@@ -22,198 +55,483 @@ pub fn list_input_devices() -> Result<Vec<(String, String)>> {
     
     if let Ok(devices) = host.input_devices() {
         for device in devices {
-            if let Ok(name) = device.name() {
-                list.push((name.clone(), name));
+            if let Ok(id) = device.id() {
+                list.push((id.to_string(), device_name(&device)));
             }
         }
     }
+    for device in virtual_input::devices() {
+        list.push((device.id().to_string(), device.name().to_string()));
+    }
     Ok(list)
 }
 
-/// Lock-free microphone stream
+/// Id of the current system default input device
+pub fn default_input_device() -> Option<String> {
+    let device = cpal::default_host().default_input_device()?;
+    device.id().ok().map(|id| id.to_string())
+}
+
+/// Resolve a device id handed out by `list_input_devices`
+///
+/// Bare display names (the id format used by older builds) are still
+/// accepted so saved settings keep working.
+fn find_input_device(host: &cpal::Host, device_id: &str) -> Option<cpal::Device> {
+    if let Ok(id) = device_id.parse::<cpal::DeviceId>() {
+        if let Some(device) = host.device_by_id(&id) {
+            return Some(device);
+        }
+    }
+
+    host.input_devices().ok()?
+        .find(|device| device_name(device) == device_id)
+}
+
+/// Device selection and channel handling for `MicrophoneStream::new`
+#[derive(Debug, Clone, Default)]
+pub struct MicrophoneConfig {
+    /// Open the default device if the requested id no longer exists
+    pub fallback_to_default: bool,
+    /// How multi-channel devices are reduced
+    pub channel_policy: ChannelPolicy,
+}
+
+/// Requests for the thread owning a cpal stream
+enum StreamCommand {
+    Play(mpsc::Sender<Result<()>>),
+    Pause(mpsc::Sender<Result<()>>),
+    /// Sent by the error callback when cpal invalidates the stream (the
+    /// device's format or rate changed)
+    Rebuild,
+    Shutdown,
+}
+
+/// What the capture callback writes with; survives stream rebuilds
+struct CallbackState {
+    producer: HeapProd<f32>,
+    mixer: ChannelMixer,
+    /// Samples pushed so far, the position a rebuild's rate applies from
+    pushed: u64,
+}
+
+/// Result of opening the device on the stream thread
+struct Opened {
+    consumer: HeapCons<f32>,
+    sample_rate: RateHandle,
+    channels: usize,
+    device_id: Option<String>,
+}
+
+/// Handle to the thread owning a cpal stream
+struct StreamThread {
+    commands: mpsc::Sender<StreamCommand>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl StreamThread {
+    fn request(&self, command: fn(mpsc::Sender<Result<()>>) -> StreamCommand) -> Result<()> {
+        let (reply, result) = mpsc::channel();
+        self.commands.send(command(reply))
+            .map_err(|_| anyhow::anyhow!("Input stream thread has exited"))?;
+        result.recv()
+            .map_err(|_| anyhow::anyhow!("Input stream thread has exited"))?
+    }
+}
+
+impl Drop for StreamThread {
+    fn drop(&mut self) {
+        // The error callback holds a sender too, so closing ours is not enough
+        let _ = self.commands.send(StreamCommand::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Microphone stream
 /// 
 /// Callback pushes raw f32 samples to ring buffer.
 /// Consumer is polled by DSP thread.
 pub struct MicrophoneStream {
-    stream: Option<Stream>,
+    stream: Option<StreamThread>,
+    /// Set instead of `stream` for a virtual device
+    virtual_device: Option<Arc<VirtualDevice>>,
     consumer: Option<HeapCons<f32>>,
-    sample_rate: u32,
+    device_id: Option<String>,
+    sample_rate: RateHandle,
+    channels: usize,
     is_running: Arc<AtomicBool>,
 }
 
 impl MicrophoneStream {
-    pub fn new(_device_id: Option<String>) -> Result<Self> {
-        let host = cpal::default_host();
-        let device = host.default_input_device()
-            .ok_or_else(|| anyhow::anyhow!("No input device found"))?;
-        
-        let config = device.default_input_config()
-            .map_err(|e| anyhow::anyhow!("Failed to get config: {}", e))?;
-        
-        let sample_rate = config.sample_rate().0;
-        let channels = config.channels() as usize;
-        
-        println!(
-            "[Microphone] Device: {}, Rate: {}Hz, Channels: {}, Format: {:?}", 
-            device.name().unwrap_or_default(), 
-            sample_rate, 
-            channels,
-            config.sample_format()
-        );
-        
-        // Create lock-free SPSC ring buffer
-        let rb = HeapRb::<f32>::new(RING_BUFFER_SAMPLES);
-        let (producer, consumer) = rb.split();
-        
+    /// Open the input device with the given id (`None` or "default" opens
+    /// the system default). If the id no longer exists, fails with
+    /// `DeviceNotFound` unless `config.fallback_to_default` is set.
+    /// Stream failures and ring buffer overflows go to `errors`.
+    pub fn new(
+        device_id: Option<String>,
+        config: MicrophoneConfig,
+        errors: Arc<ErrorReporter>,
+    ) -> Result<Self> {
+        let device_id = device_id.filter(|id| !id.is_empty() && id != "default");
+        if let Some(device) = device_id.as_deref().and_then(virtual_input::find) {
+            return Self::open_virtual(device, config, errors);
+        }
+        Self::open_cpal(device_id, config, errors)
+    }
+
+    /// Open a real device, ignoring virtual ones. The stream lives on its
+    /// own thread, which rebuilds it when cpal invalidates it.
+    fn open_cpal(device_id: Option<String>, config: MicrophoneConfig, errors: Arc<ErrorReporter>) -> Result<Self> {
         let is_running = Arc::new(AtomicBool::new(false));
-        let is_running_clone = is_running.clone();
-        
-        // Build the stream with minimal callback
-        let stream = build_input_stream(
-            &device, 
-            &config, 
-            producer, 
-            channels, 
-            is_running_clone
-        )?;
-        
+        let (commands, command_rx) = mpsc::channel();
+        let (init_tx, init_rx) = mpsc::channel();
+
+        let thread = {
+            let is_running = is_running.clone();
+            let commands = commands.clone();
+            thread::spawn(move || {
+                run_stream_thread(device_id, config, is_running, errors, commands, command_rx, init_tx)
+            })
+        };
+        // Dropping this on a failed open joins the thread
+        let stream = StreamThread { commands, thread: Some(thread) };
+
+        let opened = init_rx.recv()
+            .map_err(|_| anyhow::anyhow!("Input stream thread exited during open"))??;
+
         Ok(Self {
             stream: Some(stream),
+            virtual_device: None,
+            consumer: Some(opened.consumer),
+            device_id: opened.device_id,
+            sample_rate: opened.sample_rate,
+            channels: opened.channels,
+            is_running,
+        })
+    }
+
+    /// A virtual device pushes straight into the ring buffer; there is no
+    /// cpal stream. Unregistering it reports `DeviceLost` to `errors`.
+    fn open_virtual(device: Arc<VirtualDevice>, config: MicrophoneConfig, errors: Arc<ErrorReporter>) -> Result<Self> {
+        let mixer = ChannelMixer::new(config.channel_policy, device.channels())?;
+        let output_channels = mixer.output_channels();
+        let (producer, consumer) = HeapRb::<f32>::new(RING_BUFFER_SAMPLES).split();
+        let is_running = Arc::new(AtomicBool::new(false));
+        device.attach(producer, mixer, is_running.clone(), errors)?;
+
+        log::info!(
+            "[Microphone] Virtual device: {}, Rate: {}Hz, Channels: {}, Format: {}, Policy: {:?}",
+            device.id(), device.sample_rate(), device.channels(), device.format().name(), config.channel_policy
+        );
+        Ok(Self {
+            stream: None,
+            device_id: Some(device.id().to_string()),
+            sample_rate: fixed_rate(device.sample_rate()),
+            virtual_device: Some(device),
             consumer: Some(consumer),
-            sample_rate,
+            channels: output_channels,
             is_running,
         })
     }
 
-    /// Start capturing audio
-    pub fn play(&self) -> Result<()> {
+    /// Check if stream is running
+    pub fn is_running(&self) -> bool {
+        self.is_running.load(Ordering::SeqCst)
+    }
+}
+
+impl CaptureBackend for MicrophoneStream {
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            name: if self.virtual_device.is_some() { "virtual" } else { "cpal" },
+            pausable: true,
+            variable_rate: self.virtual_device.is_none(),
+            selects_device: true,
+        }
+    }
+
+    fn start(&mut self) -> Result<()> {
         if let Some(ref stream) = self.stream {
-            stream.play().map_err(|e| anyhow::anyhow!("Failed to start stream: {}", e))?;
-            self.is_running.store(true, Ordering::SeqCst);
-            println!("[Microphone] Stream started");
+            stream.request(StreamCommand::Play)?;
         }
+        self.is_running.store(true, Ordering::SeqCst);
+        log::info!("[Microphone] Stream started");
         Ok(())
     }
 
-    /// Pause capturing
-    pub fn pause(&self) -> Result<()> {
+    fn stop(&mut self) -> Result<()> {
         if let Some(ref stream) = self.stream {
-            stream.pause().map_err(|e| anyhow::anyhow!("Failed to pause stream: {}", e))?;
-            self.is_running.store(false, Ordering::SeqCst);
-            println!("[Microphone] Stream paused");
+            stream.request(StreamCommand::Pause)?;
         }
+        self.is_running.store(false, Ordering::SeqCst);
+        log::info!("[Microphone] Stream paused");
         Ok(())
     }
 
-    /// Get the input sample rate
-    pub fn sample_rate(&self) -> u32 {
-        self.sample_rate
+    /// Set again, from the first sample at the new rate, when the stream
+    /// thread rebuilds an invalidated cpal stream; virtual devices are fixed
+    fn sample_rate(&self) -> RateHandle {
+        self.sample_rate.clone()
+    }
+
+    /// 1 unless the channel policy keeps all channels
+    fn channels(&self) -> usize {
+        self.channels
     }
 
-    /// Take ownership of the consumer for the DSP thread
-    pub fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+    fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
         self.consumer.take()
     }
-    
-    /// Check if stream is running
-    pub fn is_running(&self) -> bool {
-        self.is_running.load(Ordering::SeqCst)
+
+    fn device_id(&self) -> Option<String> {
+        self.device_id.clone()
     }
 }
 
-/// Build input stream with lock-free callback
-/// 
-/// The callback ONLY pushes to the ring buffer.
-/// No mutexes, allocations, or DSP.
-fn build_input_stream(
-    device: &cpal::Device,
-    config: &cpal::SupportedStreamConfig,
-    mut producer: HeapProd<f32>,
-    channels: usize,
+/// Open a microphone through `backend`. `Auto` also resolves virtual device
+/// ids; `Cpal` only opens real devices. A missing device fails with
+/// `DeviceNotFound` (see `MicrophoneStream::new`).
+pub fn open(
+    backend: InputBackend,
+    device_id: Option<String>,
+    config: MicrophoneConfig,
+    errors: Arc<ErrorReporter>,
+) -> Result<Box<dyn CaptureBackend>> {
+    let stream = match backend {
+        InputBackend::Auto => MicrophoneStream::new(device_id, config, errors)?,
+        InputBackend::Cpal => MicrophoneStream::open_cpal(device_id, config, errors)?,
+    };
+    Ok(Box::new(stream))
+}
+
+/// Body of the thread owning a cpal stream: open the device, report the
+/// result through `init`, then serve commands until shutdown
+fn run_stream_thread(
+    device_id: Option<String>,
+    config: MicrophoneConfig,
     is_running: Arc<AtomicBool>,
-) -> Result<Stream> {
-    let err_fn = |err| eprintln!("[Microphone] Stream error: {}", err);
-    
-    let stream = match config.sample_format() {
-        SampleFormat::F32 => {
-            device.build_input_stream(
-                &config.clone().into(),
-                move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    if !is_running.load(Ordering::Relaxed) {
-                        return;
-                    }
-                    // REAL-TIME SAFE: Only lock-free push
-                    // Convert stereo to mono if needed, then push
-                    if channels > 1 {
-                        // Take first channel only (interleaved)
-                        for chunk in data.chunks(channels) {
-                            let _ = producer.try_push(chunk[0]);
-                        }
-                    } else {
-                        let _ = producer.push_slice(data);
-                    }
-                },
-                err_fn,
-                None,
-            )?
+    errors: Arc<ErrorReporter>,
+    commands: mpsc::Sender<StreamCommand>,
+    command_rx: mpsc::Receiver<StreamCommand>,
+    init: mpsc::Sender<Result<Opened>>,
+) {
+    let host = cpal::default_host();
+    let device = match resolve_device(&host, device_id, &config) {
+        Ok(device) => device,
+        Err(e) => {
+            let _ = init.send(Err(e));
+            return;
         }
-        SampleFormat::I16 => {
-            device.build_input_stream(
-                &config.clone().into(),
-                move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                    if !is_running.load(Ordering::Relaxed) {
-                        return;
-                    }
-                    // REAL-TIME SAFE: Convert and push
-                    if channels > 1 {
-                        for chunk in data.chunks(channels) {
-                            let sample = chunk[0] as f32 / 32768.0;
-                            let _ = producer.try_push(sample);
-                        }
-                    } else {
-                        for &sample in data {
-                            let _ = producer.try_push(sample as f32 / 32768.0);
-                        }
-                    }
-                },
-                err_fn,
-                None,
-            )?
+    };
+    let stream_config = match device.default_input_config() {
+        Ok(stream_config) => stream_config,
+        Err(e) => {
+            let _ = init.send(Err(anyhow::anyhow!("Failed to get config: {}", e)));
+            return;
         }
-        SampleFormat::I32 => {
-            device.build_input_stream(
-                &config.clone().into(),
-                move |data: &[i32], _: &cpal::InputCallbackInfo| {
-                    if !is_running.load(Ordering::Relaxed) {
-                        return;
-                    }
-                    // REAL-TIME SAFE: Convert and push
-                    if channels > 1 {
-                        for chunk in data.chunks(channels) {
-                            let sample = chunk[0] as f32 / 2147483648.0;
-                            let _ = producer.try_push(sample);
+    };
+    let mixer = match ChannelMixer::new(config.channel_policy, stream_config.channels() as usize) {
+        Ok(mixer) => mixer,
+        Err(e) => {
+            let _ = init.send(Err(e));
+            return;
+        }
+    };
+
+    log::info!(
+        "[Microphone] Device: {}, Rate: {}Hz, Channels: {}, Format: {:?}, Policy: {:?}",
+        device_name(&device),
+        stream_config.sample_rate(),
+        stream_config.channels(),
+        stream_config.sample_format(),
+        config.channel_policy
+    );
+
+    // Create lock-free SPSC ring buffer
+    let (producer, consumer) = HeapRb::<f32>::new(RING_BUFFER_SAMPLES).split();
+    let output_channels = mixer.output_channels();
+    let sample_rate = Arc::new(DeviceRate::new(stream_config.sample_rate()));
+    let state = Arc::new(Mutex::new(CallbackState { producer, mixer, pushed: 0 }));
+
+    let build = |stream_config: &cpal::SupportedStreamConfig| {
+        build_input_stream(&device, stream_config, state.clone(), is_running.clone(), errors.clone(), commands.clone())
+    };
+    let mut stream = match build(&stream_config) {
+        Ok(stream) => Some(stream),
+        Err(e) => {
+            let _ = init.send(Err(e));
+            return;
+        }
+    };
+    let _ = init.send(Ok(Opened {
+        consumer,
+        sample_rate: sample_rate.clone(),
+        channels: output_channels,
+        device_id: device.id().ok().map(|id| id.to_string()),
+    }));
+
+    while let Ok(command) = command_rx.recv() {
+        match command {
+            StreamCommand::Play(reply) => {
+                let _ = reply.send(match stream {
+                    Some(ref stream) => stream.play().map_err(|e| anyhow::anyhow!("Failed to start stream: {}", e)),
+                    None => Err(anyhow::anyhow!("Input stream was lost")),
+                });
+            }
+            StreamCommand::Pause(reply) => {
+                let _ = reply.send(match stream {
+                    Some(ref stream) => stream.pause().map_err(|e| anyhow::anyhow!("Failed to pause stream: {}", e)),
+                    None => Ok(()),
+                });
+            }
+            StreamCommand::Rebuild => {
+                // Several invalidations can queue up behind one rebuild
+                if stream.is_none() {
+                    continue;
+                }
+                stream = None;
+                let rebuilt = device.default_input_config()
+                    .map_err(|e| anyhow::anyhow!("Failed to get config: {}", e))
+                    .and_then(|stream_config| {
+                        let mixer = ChannelMixer::new(config.channel_policy, stream_config.channels() as usize)?;
+                        if mixer.output_channels() != output_channels {
+                            return Err(anyhow::anyhow!(
+                                "Device now has {} channels, the capture was opened with {}",
+                                mixer.output_channels(), output_channels
+                            ));
                         }
-                    } else {
-                        for &sample in data {
-                            let _ = producer.try_push(sample as f32 / 2147483648.0);
+                        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                        state.mixer = mixer;
+                        sample_rate.set(stream_config.sample_rate(), state.pushed);
+                        drop(state);
+
+                        let rebuilt = build(&stream_config)?;
+                        if is_running.load(Ordering::SeqCst) {
+                            rebuilt.play().map_err(|e| anyhow::anyhow!("Failed to start stream: {}", e))?;
                         }
-                    }
-                },
-                err_fn,
-                None,
-            )?
-        }
-        format => {
-            return Err(anyhow::anyhow!("Unsupported sample format: {:?}", format));
+                        log::info!("[Microphone] Stream rebuilt at {}Hz", stream_config.sample_rate());
+                        Ok(rebuilt)
+                    });
+                match rebuilt {
+                    Ok(rebuilt) => stream = Some(rebuilt),
+                    Err(e) => errors.report(CaptureError::new(
+                        ErrorCode::DeviceLost,
+                        format!("Failed to reopen input stream: {}", e),
+                        true,
+                    )),
+                }
+            }
+            StreamCommand::Shutdown => break,
         }
+    }
+}
+
+/// Find the device for `device_id` (`None` is the system default)
+fn resolve_device(host: &cpal::Host, device_id: Option<String>, config: &MicrophoneConfig) -> Result<cpal::Device> {
+    match device_id.filter(|id| !id.is_empty() && id != "default") {
+        Some(id) => match find_input_device(host, &id) {
+            Some(device) => Ok(device),
+            None if config.fallback_to_default => {
+                log::warn!("[Microphone] Device {} not found, falling back to default", id);
+                host.default_input_device()
+                    .ok_or_else(|| anyhow::anyhow!("No input device found"))
+            }
+            None => Err(DeviceNotFound(id).into()),
+        },
+        None => host.default_input_device()
+            .ok_or_else(|| anyhow::anyhow!("No input device found")),
+    }
+}
+
+/// Build input stream with real-time safe callback
+/// 
+/// The callback ONLY converts, mixes and pushes to the ring buffer.
+/// No allocations or DSP, and no waiting on the state lock.
+fn build_input_stream(
+    device: &cpal::Device,
+    config: &cpal::SupportedStreamConfig,
+    state: Arc<Mutex<CallbackState>>,
+    is_running: Arc<AtomicBool>,
+    errors: Arc<ErrorReporter>,
+    commands: mpsc::Sender<StreamCommand>,
+) -> Result<Stream> {
+    let (s, r, e, c) = (state, is_running, errors, commands);
+    match config.sample_format() {
+        SampleFormat::F32 => build_typed_stream::<f32>(device, config, s, r, e, c),
+        SampleFormat::F64 => build_typed_stream::<f64>(device, config, s, r, e, c),
+        SampleFormat::I16 => build_typed_stream::<i16>(device, config, s, r, e, c),
+        SampleFormat::I32 => build_typed_stream::<i32>(device, config, s, r, e, c),
+        SampleFormat::U8 => build_typed_stream::<u8>(device, config, s, r, e, c),
+        SampleFormat::U16 => build_typed_stream::<u16>(device, config, s, r, e, c),
+        format => Err(anyhow::anyhow!("Unsupported sample format: {:?}", format)),
+    }
+}
+
+fn build_typed_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::SupportedStreamConfig,
+    state: Arc<Mutex<CallbackState>>,
+    is_running: Arc<AtomicBool>,
+    errors: Arc<ErrorReporter>,
+    commands: mpsc::Sender<StreamCommand>,
+) -> Result<Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let input_channels = config.channels().max(1) as usize;
+
+    // cpal calls this on its own (non-real-time) thread
+    let stream_errors = errors.clone();
+    let err_fn = move |err: cpal::StreamError| {
+        let (code, fatal) = match err {
+            cpal::StreamError::DeviceNotAvailable => (ErrorCode::DeviceLost, true),
+            // The stream thread rebuilds it; if that thread is gone the
+            // device can't be read any more
+            cpal::StreamError::StreamInvalidated => {
+                log::warn!("[Microphone] Stream invalidated, rebuilding");
+                if commands.send(StreamCommand::Rebuild).is_ok() {
+                    return;
+                }
+                (ErrorCode::DeviceLost, true)
+            }
+            // An xrun is a glitch in the device stream, not our ring buffer
+            cpal::StreamError::BufferUnderrun => (ErrorCode::StreamError, false),
+            _ => (ErrorCode::StreamError, false),
+        };
+        stream_errors.report(CaptureError::new(code, err.to_string(), fatal));
     };
-    
+
+    let stream = device.build_input_stream(
+        &config.clone().into(),
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            if !is_running.load(Ordering::Relaxed) {
+                return;
+            }
+            // Only contended while the stream thread swaps in a rebuilt mixer
+            let Ok(mut state) = state.try_lock() else { return };
+            let CallbackState { producer, mixer, pushed } = &mut *state;
+            // REAL-TIME SAFE: Convert, mix and lock-free push
+            if producer.vacant_len() < data.len() / input_channels * mixer.output_channels() {
+                errors.flag(ErrorCode::Overflow, false);
+            }
+            *pushed += mixer.push(data, producer) as u64;
+        },
+        err_fn,
+        None,
+    )?;
+
     Ok(stream)
 }
 
 impl Drop for MicrophoneStream {
     fn drop(&mut self) {
         self.is_running.store(false, Ordering::SeqCst);
-        // Stream will be dropped and stopped automatically
+        if let Some(device) = self.virtual_device.take() {
+            device.detach();
+        }
+        // Joining the stream thread drops and stops the stream
+        self.stream = None;
     }
 }
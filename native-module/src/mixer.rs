@@ -0,0 +1,366 @@
+// Stereo Mixer - microphone on the left, system audio on the right
+//
+// Architecture:
+// 1. Each capture pipeline gets a `MixerLane` tap; its DSP thread hands the
+//    lane every resampled frame (before any stage can drop it) together
+//    with the frame's capture timestamp
+// 2. A mixer thread places the frames on one timeline (output samples since
+//    the mixer started), padding gaps with silence and trimming overlap, so
+//    the two channels stay aligned even when a device stalls or drifts
+// 3. Aligned interleaved chunks go to a recorder and/or a sink (the JS
+//    callback in lib.rs)
+//
+// A lane that stops delivering (device paused, nothing playing) is padded
+// with silence once it falls MAX_LAG_MS behind, so one side never holds up
+// the other.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::pipeline::{Frame, PipelineTap};
+use crate::recorder::{Recorder, RecorderConfig};
+
+/// Output rate of the mixed stream
+pub const MIXER_SAMPLE_RATE: u32 = 16000;
+
+/// Left = microphone, right = system audio
+pub const MIXER_CHANNELS: usize = 2;
+
+/// Frames within this distance of where the lane expects them are treated
+/// as contiguous (timestamps jitter with the DSP thread's polling); this is
+/// also the alignment accuracy
+const JITTER_MS: i64 = 30;
+
+/// How far a silent lane may fall behind before it is padded
+const MAX_LAG_MS: i64 = 200;
+
+/// Frames queued per lane before the oldest are dropped (~2s)
+const LANE_CAPACITY: usize = 100;
+
+const TICK: Duration = Duration::from_millis(10);
+
+/// Receives interleaved stereo chunks from the mixer thread, placed on the
+/// mix timeline (`sample_offset`, `captured_at` of the first sample)
+pub type MixSink = Box<dyn FnMut(Frame) + Send>;
+
+// ============================================================================
+// LANE (DSP thread side)
+// ============================================================================
+
+struct TimedFrame {
+    captured_at: Instant,
+    samples: Vec<i16>,
+}
+
+/// Tap registered on one capture pipeline
+#[derive(Default)]
+pub struct MixerLane {
+    queue: Mutex<VecDeque<TimedFrame>>,
+    closed: AtomicBool,
+}
+
+impl MixerLane {
+    fn drain(&self) -> Vec<TimedFrame> {
+        self.queue.lock().map(|mut q| q.drain(..).collect()).unwrap_or_default()
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+}
+
+impl PipelineTap for MixerLane {
+    fn frame(&self, frame: &Frame) {
+        if self.is_closed() {
+            return;
+        }
+        // Multi-channel microphones are averaged down to their lane
+        let samples = if frame.channels > 1 {
+            frame.samples.chunks(frame.channels)
+                .map(|c| (c.iter().map(|&s| s as i32).sum::<i32>() / c.len() as i32) as i16)
+                .collect()
+        } else {
+            frame.samples.clone()
+        };
+
+        if let Ok(mut queue) = self.queue.lock() {
+            if queue.len() >= LANE_CAPACITY {
+                queue.pop_front();
+            }
+            queue.push_back(TimedFrame { captured_at: frame.captured_at, samples });
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+}
+
+// ============================================================================
+// TIMELINE
+// ============================================================================
+
+/// Samples of one lane not yet emitted; `pending[0]` sits at the cursor
+#[derive(Default)]
+struct LaneBuffer {
+    pending: VecDeque<i16>,
+}
+
+impl LaneBuffer {
+    /// Put `samples` at timeline position `pos` given the shared cursor
+    fn place(&mut self, pos: i64, samples: &[i16], cursor: i64, jitter: i64) {
+        let end = cursor + self.pending.len() as i64;
+        let skip = if pos > end + jitter {
+            // Gap (dropped input, device stall): pad with silence
+            self.pending.extend(std::iter::repeat_n(0, (pos - end) as usize));
+            0
+        } else if pos + jitter < end {
+            // Overlap (clock drift, late after padding): drop what's covered
+            ((end - pos) as usize).min(samples.len())
+        } else {
+            0
+        };
+        self.pending.extend(&samples[skip..]);
+    }
+}
+
+/// Aligns the two lanes on a common sample timeline
+struct Timeline {
+    origin: Instant,
+    sample_rate: u32,
+    /// Timeline position of the next output sample
+    cursor: i64,
+    lanes: [LaneBuffer; MIXER_CHANNELS],
+}
+
+impl Timeline {
+    fn new(origin: Instant, sample_rate: u32) -> Self {
+        Self { origin, sample_rate, cursor: 0, lanes: Default::default() }
+    }
+
+    fn position(&self, at: Instant) -> i64 {
+        let seconds = match at.checked_duration_since(self.origin) {
+            Some(after) => after.as_secs_f64(),
+            None => -self.origin.duration_since(at).as_secs_f64(),
+        };
+        (seconds * self.sample_rate as f64).round() as i64
+    }
+
+    /// When timeline position `pos` (>= 0) was captured
+    fn instant(&self, pos: i64) -> Instant {
+        self.origin + Duration::from_secs_f64(pos.max(0) as f64 / self.sample_rate as f64)
+    }
+
+    fn ms_to_samples(&self, ms: i64) -> i64 {
+        ms * self.sample_rate as i64 / 1000
+    }
+
+    fn place(&mut self, lane: usize, frame: &TimedFrame) {
+        let pos = self.position(frame.captured_at);
+        let jitter = self.ms_to_samples(JITTER_MS);
+        self.lanes[lane].place(pos, &frame.samples, self.cursor, jitter);
+    }
+
+    /// Emit every sample both lanes have, plus silence for a lane that has
+    /// fallen more than MAX_LAG_MS behind `now`
+    fn emit(&mut self, now: Instant) -> Vec<i16> {
+        let ready = self.lanes.iter().map(|l| l.pending.len() as i64).min().unwrap_or(0);
+        let overdue = self.position(now) - self.ms_to_samples(MAX_LAG_MS) - self.cursor;
+        self.take(ready.max(overdue))
+    }
+
+    /// Emit everything buffered, padding the shorter lane (used when stopping)
+    fn flush(&mut self) -> Vec<i16> {
+        let buffered = self.lanes.iter().map(|l| l.pending.len() as i64).max().unwrap_or(0);
+        self.take(buffered)
+    }
+
+    fn take(&mut self, count: i64) -> Vec<i16> {
+        let count = count.max(0) as usize;
+        let mut out = Vec::with_capacity(count * MIXER_CHANNELS);
+        for _ in 0..count {
+            for lane in self.lanes.iter_mut() {
+                out.push(lane.pending.pop_front().unwrap_or(0));
+            }
+        }
+        self.cursor += count as i64;
+        out
+    }
+}
+
+// ============================================================================
+// MIXER (mixer thread)
+// ============================================================================
+
+/// A running mix; dropping it without `stop` still finalizes the file
+pub struct Mixer {
+    lanes: [Arc<MixerLane>; MIXER_CHANNELS],
+    stop_signal: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<Result<Vec<PathBuf>>>>,
+}
+
+impl Mixer {
+    /// Start mixing into `recording` and/or `sink`. Register `lanes()` on the
+    /// microphone and system audio pipelines afterwards.
+    pub fn start(recording: Option<RecorderConfig>, sink: Option<MixSink>) -> Result<Self> {
+        let recorder = recording
+            .map(|config| Recorder::start(config, MIXER_SAMPLE_RATE, MIXER_CHANNELS, None))
+            .transpose()?;
+
+        let lanes: [Arc<MixerLane>; MIXER_CHANNELS] = Default::default();
+        let stop_signal = Arc::new(AtomicBool::new(false));
+
+        let thread_lanes = lanes.clone();
+        let thread_stop = stop_signal.clone();
+        let thread = thread::spawn(move || {
+            run(thread_lanes, thread_stop, recorder, sink)
+        });
+
+        log::info!("[Mixer] Mixing microphone (L) and system audio (R) at {}Hz", MIXER_SAMPLE_RATE);
+        Ok(Self { lanes, stop_signal, thread: Some(thread) })
+    }
+
+    /// Taps for the microphone (left) and system audio (right) pipelines
+    pub fn lanes(&self) -> [Arc<dyn PipelineTap>; MIXER_CHANNELS] {
+        [self.lanes[0].clone(), self.lanes[1].clone()]
+    }
+
+    /// Close the lanes, emit what is buffered and finalize the recording
+    pub fn stop(mut self) -> Result<Vec<PathBuf>> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<Vec<PathBuf>> {
+        self.lanes.iter().for_each(|lane| lane.close());
+        self.stop_signal.store(true, Ordering::SeqCst);
+        match self.thread.take() {
+            Some(thread) => thread.join().map_err(|_| anyhow::anyhow!("Mixer thread panicked"))?,
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+impl Drop for Mixer {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            log::error!("[Mixer] Failed to finalize recording: {}", e);
+        }
+    }
+}
+
+fn run(
+    lanes: [Arc<MixerLane>; MIXER_CHANNELS],
+    stop_signal: Arc<AtomicBool>,
+    recorder: Option<Recorder>,
+    mut sink: Option<MixSink>,
+) -> Result<Vec<PathBuf>> {
+    let mut timeline = Timeline::new(Instant::now(), MIXER_SAMPLE_RATE);
+    let mut deliver = |samples: Vec<i16>, sample_offset: i64, captured_at: Instant| {
+        if samples.is_empty() {
+            return;
+        }
+        if let Some(recorder) = recorder.as_ref() {
+            recorder.write(samples.clone());
+        }
+        if let Some(sink) = sink.as_mut() {
+            sink(Frame {
+                samples,
+                channels: MIXER_CHANNELS,
+                sample_offset: sample_offset as u64,
+                captured_at,
+                payload: None,
+            });
+        }
+    };
+
+    loop {
+        let stopping = stop_signal.load(Ordering::SeqCst);
+        for (index, lane) in lanes.iter().enumerate() {
+            for frame in lane.drain() {
+                timeline.place(index, &frame);
+            }
+        }
+
+        let offset = timeline.cursor;
+        let samples = if stopping { timeline.flush() } else { timeline.emit(Instant::now()) };
+        deliver(samples, offset, timeline.instant(offset));
+        if stopping {
+            break;
+        }
+        thread::sleep(TICK);
+    }
+
+    match recorder {
+        Some(recorder) => recorder.stop(),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(origin: Instant, at_ms: u64, value: i16) -> TimedFrame {
+        TimedFrame { captured_at: origin + Duration::from_millis(at_ms), samples: vec![value; 320] }
+    }
+
+    #[test]
+    fn test_timeline_aligns_lanes_and_pads_gaps() {
+        let origin = Instant::now();
+        let mut timeline = Timeline::new(origin, 16000);
+
+        // Mic is steady; system audio starts 100ms late, arrives with 5ms of
+        // jitter and drops two frames
+        for i in 0..20 {
+            timeline.place(0, &frame(origin, i * 20, 1000));
+        }
+        for i in 5..20 {
+            if i == 10 || i == 11 {
+                continue;
+            }
+            let jitter = if i % 2 == 0 { 5 } else { 0 };
+            timeline.place(1, &frame(origin, i * 20 + jitter, 2000));
+        }
+
+        let out = timeline.emit(origin + Duration::from_millis(400));
+        assert_eq!(out.len(), 400 * 16 * 2);
+
+        let right_at = |ms: usize| out[ms * 16 * 2 + 1];
+        assert!(out.chunks(2).all(|s| s[0] == 1000));
+        assert_eq!(right_at(50), 0);
+        assert_eq!(right_at(150), 2000);
+        assert_eq!(right_at(210), 0);
+        assert_eq!(right_at(230), 0);
+        assert_eq!(right_at(250), 2000);
+    }
+
+    #[test]
+    fn test_silent_lane_is_padded_after_max_lag() {
+        let origin = Instant::now();
+        let mut timeline = Timeline::new(origin, 16000);
+        for i in 0..50 {
+            timeline.place(0, &frame(origin, i * 20, 1000));
+        }
+
+        // Nothing on the right yet: hold back until MAX_LAG_MS has passed
+        assert!(timeline.emit(origin + Duration::from_millis(100)).is_empty());
+
+        let out = timeline.emit(origin + Duration::from_millis(1000));
+        assert_eq!(out.len(), 800 * 16 * 2);
+        assert!(out.chunks(2).all(|s| s == [1000, 0]));
+
+        // A late right frame covering emitted time is trimmed, not shifted
+        timeline.place(1, &frame(origin, 700, 2000));
+        timeline.place(1, &frame(origin, 800, 2000));
+        let out = timeline.flush();
+        assert_eq!(out.len(), 200 * 16 * 2);
+        assert!(out[..320 * 2].chunks(2).all(|s| s == [1000, 2000]));
+        assert!(out[320 * 2..].chunks(2).all(|s| s == [1000, 0]));
+    }
+}
@@ -0,0 +1,300 @@
+// Noise Suppression - spectral subtraction with a learned noise floor
+//
+// Steady background noise (laptop fans, HVAC, keyboard bed) keeps the RMS
+// gate in SilenceSuppressor open, so it runs BEFORE suppression.
+//
+// Algorithm (per 10ms hop, 20ms sqrt-Hann window, 50% overlap-add):
+// 1. Track the noise floor per frequency bin: average bins that look like
+//    noise, let bins that look like speech raise it only slowly (~3dB/s)
+// 2. Spectral subtraction gain per bin, floored at the maximum attenuation
+// 3. Smooth gains over time to avoid musical noise
+//
+// LATENCY: one hop (10ms at 16kHz) from the overlap-add.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use realfft::num_complex::Complex;
+
+use crate::pipeline::{Frame, FrameProcessor, StageOutput};
+
+/// Noise floor rise per hop (~3dB/s at 100 hops/s)
+const NOISE_RISE: f32 = 1.007;
+
+/// Bins below this multiple of the floor are treated as noise
+const NOISE_DECISION: f32 = 4.0;
+
+/// Smoothing when averaging noise-like bins into the floor
+const NOISE_SMOOTHING: f32 = 0.95;
+
+/// Smoothing for the per-bin noisy power estimate
+const POWER_SMOOTHING: f32 = 0.6;
+
+/// Smoothing for per-bin gains (higher = less musical noise, slower onsets)
+const GAIN_SMOOTHING: f32 = 0.5;
+
+/// Hops used to seed the noise floor before subtraction starts
+const LEARNING_HOPS: usize = 10;
+
+/// Maximum attenuation at strength 1.0, in dB
+const MAX_ATTENUATION_DB: f32 = 30.0;
+
+pub struct NoiseSuppressor {
+    hop: usize,
+    window: Vec<f32>,
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+    /// Over-subtraction factor derived from strength
+    over_subtraction: f32,
+    /// Minimum gain derived from strength
+    gain_floor: f32,
+    noise: Vec<f32>,
+    power: Vec<f32>,
+    gains: Vec<f32>,
+    hops_seen: usize,
+    /// Last `window.len()` input samples
+    analysis: Vec<f32>,
+    /// Overlap-add accumulator for the second half of the previous window
+    overlap: Vec<f32>,
+    input: VecDeque<f32>,
+    output: VecDeque<f32>,
+    time_buf: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+}
+
+impl NoiseSuppressor {
+    /// `strength` 0.0 (bypass) .. 1.0 (maximum attenuation)
+    pub fn new(sample_rate: u32, strength: f32) -> Self {
+        let hop = (sample_rate / 100) as usize;
+        let window_len = hop * 2;
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(window_len);
+        let ifft = planner.plan_fft_inverse(window_len);
+        let bins = window_len / 2 + 1;
+
+        // sqrt-Hann on both analysis and synthesis sums to 1 at 50% overlap
+        let window: Vec<f32> = (0..window_len)
+            .map(|n| {
+                let phase = std::f32::consts::PI * n as f32 / window_len as f32;
+                phase.sin()
+            })
+            .collect();
+
+        let strength = strength.clamp(0.0, 1.0);
+        let spectrum = fft.make_output_vec();
+
+        Self {
+            hop,
+            window,
+            over_subtraction: 1.0 + 2.0 * strength,
+            gain_floor: 10f32.powf(-MAX_ATTENUATION_DB * strength / 20.0),
+            noise: vec![0.0; bins],
+            power: vec![0.0; bins],
+            gains: vec![1.0; bins],
+            hops_seen: 0,
+            analysis: vec![0.0; window_len],
+            overlap: vec![0.0; hop],
+            input: VecDeque::new(),
+            output: VecDeque::new(),
+            time_buf: vec![0.0; window_len],
+            spectrum,
+            fft,
+            ifft,
+        }
+    }
+
+    /// Algorithmic latency in samples
+    pub fn latency(&self) -> usize {
+        self.hop
+    }
+
+    /// Denoise `samples` in place. Output is delayed by one hop; lengths that
+    /// are not a multiple of the hop add up to one more hop of delay.
+    pub fn process(&mut self, samples: &mut [i16]) {
+        self.input.extend(samples.iter().map(|&s| s as f32 / 32768.0));
+
+        while self.input.len() >= self.hop {
+            self.analysis.drain(0..self.hop);
+            self.analysis.extend(self.input.drain(0..self.hop));
+            self.process_hop();
+        }
+
+        // Output runs short only until the delay settles
+        let missing = samples.len().saturating_sub(self.output.len());
+        for _ in 0..missing {
+            self.output.push_front(0.0);
+        }
+        for sample in samples.iter_mut() {
+            let value = self.output.pop_front().unwrap_or(0.0);
+            *sample = (value * 32768.0).clamp(-32768.0, 32767.0) as i16;
+        }
+    }
+
+    fn process_hop(&mut self) {
+        for ((dst, &src), &w) in self.time_buf.iter_mut().zip(&self.analysis).zip(&self.window) {
+            *dst = src * w;
+        }
+        if self.fft.process(&mut self.time_buf, &mut self.spectrum).is_err() {
+            return;
+        }
+
+        let learning = self.hops_seen < LEARNING_HOPS;
+        for (k, bin) in self.spectrum.iter_mut().enumerate() {
+            let p = bin.norm_sqr();
+            self.power[k] = POWER_SMOOTHING * self.power[k] + (1.0 - POWER_SMOOTHING) * p;
+
+            if learning {
+                // Running mean while seeding
+                let n = self.hops_seen as f32;
+                self.noise[k] = (self.noise[k] * n + p) / (n + 1.0);
+            } else if self.power[k] < NOISE_DECISION * self.noise[k] {
+                self.noise[k] = NOISE_SMOOTHING * self.noise[k] + (1.0 - NOISE_SMOOTHING) * self.power[k];
+            } else {
+                self.noise[k] = (self.noise[k] * NOISE_RISE).min(self.power[k]);
+            }
+
+            let target = if learning || self.power[k] <= 0.0 {
+                1.0
+            } else {
+                (1.0 - self.over_subtraction * self.noise[k] / self.power[k]).max(self.gain_floor)
+            };
+            // Open fast, close slowly
+            let gain = if target > self.gains[k] {
+                target
+            } else {
+                GAIN_SMOOTHING * self.gains[k] + (1.0 - GAIN_SMOOTHING) * target
+            };
+            self.gains[k] = gain;
+            *bin *= gain;
+        }
+        self.hops_seen += 1;
+
+        // Inverse real FFT requires purely real DC/Nyquist bins
+        if let Some(first) = self.spectrum.first_mut() {
+            first.im = 0.0;
+        }
+        if let Some(last) = self.spectrum.last_mut() {
+            last.im = 0.0;
+        }
+        if self.ifft.process(&mut self.spectrum, &mut self.time_buf).is_err() {
+            return;
+        }
+
+        let scale = 1.0 / self.time_buf.len() as f32;
+        let hop = self.hop;
+        for (i, (sample, &w)) in self.time_buf.iter().zip(&self.window).enumerate() {
+            let value = sample * scale * w;
+            if i < hop {
+                self.output.push_back(self.overlap[i] + value);
+            } else {
+                self.overlap[i - hop] = value;
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.noise.iter_mut().for_each(|n| *n = 0.0);
+        self.power.iter_mut().for_each(|p| *p = 0.0);
+        self.gains.iter_mut().for_each(|g| *g = 1.0);
+        self.hops_seen = 0;
+        self.analysis.iter_mut().for_each(|s| *s = 0.0);
+        self.overlap.iter_mut().for_each(|s| *s = 0.0);
+        self.input.clear();
+        self.output.clear();
+    }
+}
+
+impl FrameProcessor for NoiseSuppressor {
+    fn name(&self) -> &'static str {
+        "noiseSuppression"
+    }
+
+    fn process(&mut self, frame: &mut Frame) -> StageOutput {
+        // Noise statistics are per stream; multi-channel frames pass through
+        if frame.channels == 1 {
+            NoiseSuppressor::process(self, &mut frame.samples);
+        }
+        StageOutput::Forward
+    }
+
+    fn reset(&mut self) {
+        NoiseSuppressor::reset(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    const RATE: usize = 16_000;
+
+    fn power(samples: &[i16]) -> f32 {
+        samples.iter().map(|&s| (s as f32).powi(2)).sum::<f32>() / samples.len() as f32
+    }
+
+    /// 2s of fan-like noise, then 1s of a voiced tone over the same noise
+    fn noisy_signal() -> (Vec<i16>, Vec<i16>) {
+        let mut rng = StdRng::seed_from_u64(7);
+        let noise: Vec<f32> = (0..3 * RATE).map(|_| rng.gen_range(-0.02..0.02)).collect();
+        let voice: Vec<f32> = (0..3 * RATE)
+            .map(|n| if n < 2 * RATE {
+                0.0
+            } else {
+                let t = n as f32 / RATE as f32;
+                0.2 * (t * 220.0 * std::f32::consts::TAU).sin() + 0.1 * (t * 660.0 * std::f32::consts::TAU).sin()
+            })
+            .collect();
+
+        let mix = noise.iter().zip(&voice).map(|(n, v)| ((n + v) * 32767.0) as i16).collect();
+        let clean = voice.iter().map(|v| (v * 32767.0) as i16).collect();
+        (mix, clean)
+    }
+
+    fn run(strength: f32, input: &[i16]) -> Vec<i16> {
+        let mut suppressor = NoiseSuppressor::new(RATE as u32, strength);
+        let mut output = Vec::with_capacity(input.len());
+        for frame in input.chunks(320) {
+            let mut frame = frame.to_vec();
+            NoiseSuppressor::process(&mut suppressor, &mut frame);
+            output.extend(frame);
+        }
+        output
+    }
+
+    #[test]
+    fn test_noise_floor_is_attenuated() {
+        let (input, _) = noisy_signal();
+        let output = run(1.0, &input);
+
+        let noise_only = RATE..2 * RATE;
+        let reduction_db = 10.0 * (power(&input[noise_only.clone()]) / power(&output[noise_only])).log10();
+        assert!(reduction_db > 15.0, "noise reduced by {:.1}dB", reduction_db);
+    }
+
+    #[test]
+    fn test_voice_is_preserved() {
+        let (input, clean) = noisy_signal();
+        let output = run(1.0, &input);
+
+        let voice = 2 * RATE + RATE / 4..3 * RATE;
+        let change_db = 10.0 * (power(&output[voice.clone()]) / power(&clean[voice])).log10();
+        assert!(change_db.abs() < 2.0, "voice level changed by {:.1}dB", change_db);
+    }
+
+    #[test]
+    fn test_zero_strength_is_transparent() {
+        let (input, _) = noisy_signal();
+        let output = run(0.0, &input);
+        let latency = RATE / 100;
+
+        let max_error = input[..input.len() - latency].iter()
+            .zip(&output[latency..])
+            .map(|(a, b)| (*a as i32 - *b as i32).abs())
+            .max()
+            .unwrap();
+        assert!(max_error <= 2, "max reconstruction error {}", max_error);
+    }
+}
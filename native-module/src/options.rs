@@ -0,0 +1,97 @@
+// Constructor options for the capture classes
+//
+// Plain JS objects on the TypeScript side; every field is optional so
+// callers only spell out what they want to change.
+
+use crate::stages::StageOptions;
+
+/// Options accepted by the `MicrophoneCapture` and `SystemAudioCapture`
+/// constructors. Device and channel options only apply to microphones.
+#[napi(object)]
+#[derive(Default)]
+pub struct CaptureOptions {
+    /// Open the default device instead of failing when `deviceId` no longer
+    /// exists (e.g. a USB headset that was unplugged). Defaults to false.
+    pub fallback_to_default: Option<bool>,
+    /// How multi-channel microphones are reduced:
+    /// "downmix" (average, default) | "channel" (see `channel`) |
+    /// "maxEnergy" (follow the loudest channel) | "all" (keep interleaved)
+    pub channel_policy: Option<String>,
+    /// 0-based channel index used with `channelPolicy: "channel"`
+    pub channel: Option<u32>,
+    /// Ordered processing stages run on every 20ms frame.
+    /// Defaults to `[{ kind: "suppression" }]`.
+    pub stages: Option<Vec<StageOptions>>,
+    /// Detector behind the speechStart/speechEnd events:
+    /// "rms" (fixed threshold, default) | "features"
+    pub vad_detector: Option<String>,
+    /// Output rate of delivered frames: 8000 | 16000 (default) | 24000 | 48000
+    pub sample_rate: Option<u32>,
+    /// Frame duration in ms: 10 | 20 (default) | 40 | 100
+    pub frame_ms: Option<u32>,
+    /// Sample type of delivered PCM: "i16" (default) | "f32"
+    pub sample_format: Option<String>,
+    /// JS type of delivered frames: "buffer" (little-endian bytes, default) |
+    /// "typedArray" (Int16Array or Float32Array per `sampleFormat`). Opus
+    /// frames are always Buffers.
+    pub frame_type: Option<String>,
+    /// Deliver contiguous frames together, at most every `batchMs` (rounded
+    /// up to whole frames, max 1000). 0 (default) delivers every frame.
+    pub batch_ms: Option<u32>,
+    /// Rate conversion: "fast" (linear interpolation, no lookahead,
+    /// default) | "high" (anti-aliased windowed sinc, ~1ms lookahead)
+    pub resampler_quality: Option<String>,
+    /// Move to the new system default device whenever it changes (headset
+    /// plugged in, Bluetooth disconnected) without interrupting the stream.
+    /// Only valid without `deviceId`. Defaults to false.
+    pub follow_default_device: Option<bool>,
+    /// Capture backend. MicrophoneCapture: "auto" (default) | "cpal".
+    /// SystemAudioCapture: "auto" (default) | "coreAudioTap" |
+    /// "screenCaptureKit" (macOS) | "wasapi" (Windows) | "pulseAudio" (Linux).
+    pub backend: Option<String>,
+}
+
+/// Options accepted by `startRecording`
+#[napi(object)]
+#[derive(Default)]
+pub struct RecordingOptions {
+    /// "wav" | "flac" | "opus" (Ogg Opus, needs the processed source);
+    /// defaults from the file extension (.flac, .opus/.ogg), else "wav"
+    pub format: Option<String>,
+    /// "processed" (16kHz frames, default) | "raw" (device rate)
+    pub source: Option<String>,
+    /// Start a new numbered file (`name-000.wav`, ...) every N seconds
+    pub rotate_seconds: Option<u32>,
+    /// Keep only the newest N rotated files
+    pub max_files: Option<u32>,
+    /// opus: bitrate in bits per second (default 24000)
+    pub bitrate: Option<u32>,
+}
+
+/// Playback options of the `FileAudioCapture` constructor (pipeline options
+/// go in its `CaptureOptions`)
+#[napi(object)]
+#[derive(Default)]
+pub struct FilePlaybackOptions {
+    /// Multiple of real time (default 1.0); 0 plays as fast as the pipeline
+    /// consumes
+    pub speed: Option<f64>,
+    /// Start over at the end of the file instead of finishing. Defaults to
+    /// false.
+    pub repeat: Option<bool>,
+    /// Capture the file stands in for, selecting its suppression defaults:
+    /// "microphone" (default) | "systemAudio"
+    pub source: Option<String>,
+}
+
+/// Format of the PCM a `VirtualAudioInput` accepts
+#[napi(object)]
+#[derive(Default)]
+pub struct VirtualInputOptions {
+    /// Default 48000
+    pub sample_rate: Option<u32>,
+    /// Interleaved channels per frame. Default 1.
+    pub channels: Option<u32>,
+    /// "i16" (default) | "i32" | "f32" | "u8", little-endian
+    pub sample_format: Option<String>,
+}
@@ -0,0 +1,366 @@
+// Opus Encoder - compresses 16-bit PCM frames for streaming and recording
+//
+// Packets are delivered either raw (one packet per frame) or wrapped in an
+// Ogg Opus stream (RFC 7845, `audio/ogg; codecs=opus`), which most STT
+// providers accept as-is. The codec is libopus translated to Rust, so no C
+// toolchain is needed to build it.
+//
+// Ogg granule positions are derived from the frames' sample offsets, so
+// frames dropped by silence suppression show up as timestamp gaps instead
+// of shifting the audio after them.
+
+use std::ptr::NonNull;
+
+use anyhow::Result;
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+use unsafe_libopus::{
+    opus_encode, opus_encoder_create, opus_encoder_ctl, opus_encoder_destroy, opus_strerror,
+    OPUS_APPLICATION_VOIP, OPUS_GET_LOOKAHEAD_REQUEST, OPUS_OK, OPUS_SET_BITRATE_REQUEST,
+    OPUS_SET_COMPLEXITY_REQUEST, OPUS_SET_SIGNAL_REQUEST, OPUS_SIGNAL_VOICE,
+};
+
+use crate::pipeline::{Frame, FrameProcessor, StageOutput};
+
+/// Speech at 24 kbps is transparent for STT and ~10x smaller than 16kHz PCM
+pub const DEFAULT_BITRATE: u32 = 24_000;
+pub const DEFAULT_COMPLEXITY: u32 = 5;
+
+/// Largest packet libopus produces (RFC 6716 recommends 4000 bytes)
+const MAX_PACKET_BYTES: usize = 4000;
+
+/// Ogg granule positions always count 48kHz samples
+const GRANULE_RATE: u64 = 48_000;
+
+const OGG_SERIAL: u32 = 0x4e41_5456;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpusContainer {
+    /// Bare Opus packets, one per frame
+    Raw,
+    /// Ogg Opus stream; the first output carries the stream headers
+    Ogg,
+}
+
+impl OpusContainer {
+    pub fn from_name(name: Option<&str>) -> Result<Self> {
+        match name {
+            None | Some("ogg") => Ok(Self::Ogg),
+            Some("raw") => Ok(Self::Raw),
+            Some(other) => Err(anyhow::anyhow!("Unknown Opus container: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OpusConfig {
+    /// 8000, 12000, 16000, 24000 or 48000
+    pub sample_rate: u32,
+    /// 1 or 2
+    pub channels: usize,
+    pub bitrate: u32,
+    /// 0 (fastest) - 10 (best)
+    pub complexity: u32,
+    pub container: OpusContainer,
+}
+
+impl OpusConfig {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            bitrate: DEFAULT_BITRATE,
+            complexity: DEFAULT_COMPLEXITY,
+            container: OpusContainer::Ogg,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if ![8000, 12000, 16000, 24000, 48000].contains(&self.sample_rate) {
+            return Err(anyhow::anyhow!("Opus does not support {}Hz", self.sample_rate));
+        }
+        if !(1..=2).contains(&self.channels) {
+            return Err(anyhow::anyhow!("Opus supports 1 or 2 channels, not {}", self.channels));
+        }
+        if !(6_000..=510_000).contains(&self.bitrate) {
+            return Err(anyhow::anyhow!("Opus bitrate must be 6000-510000, got {}", self.bitrate));
+        }
+        if self.complexity > 10 {
+            return Err(anyhow::anyhow!("Opus complexity must be 0-10, got {}", self.complexity));
+        }
+        Ok(())
+    }
+}
+
+fn opus_error(code: i32) -> anyhow::Error {
+    anyhow::anyhow!("Opus error: {}", opus_strerror(code))
+}
+
+pub struct OpusEncoder {
+    encoder: NonNull<unsafe_libopus::OpusEncoder>,
+    config: OpusConfig,
+    /// Encoder delay in input samples, skipped by Ogg decoders
+    pre_skip: u64,
+    ogg: Option<PacketWriter<'static, Vec<u8>>>,
+    headers_written: bool,
+    packet: Vec<u8>,
+}
+
+// The encoder state is only touched through `&mut self`
+unsafe impl Send for OpusEncoder {}
+
+impl OpusEncoder {
+    pub fn new(config: OpusConfig) -> Result<Self> {
+        config.validate()?;
+
+        let mut error = 0;
+        let encoder = unsafe {
+            opus_encoder_create(config.sample_rate as i32, config.channels as i32, OPUS_APPLICATION_VOIP, &mut error)
+        };
+        let encoder = NonNull::new(encoder).filter(|_| error == OPUS_OK)
+            .ok_or_else(|| opus_error(error))?;
+
+        let mut lookahead = 0i32;
+        let status = unsafe {
+            let st = encoder.as_ptr();
+            [
+                opus_encoder_ctl!(st, OPUS_SET_BITRATE_REQUEST, config.bitrate as i32),
+                opus_encoder_ctl!(st, OPUS_SET_COMPLEXITY_REQUEST, config.complexity as i32),
+                opus_encoder_ctl!(st, OPUS_SET_SIGNAL_REQUEST, OPUS_SIGNAL_VOICE),
+                opus_encoder_ctl!(st, OPUS_GET_LOOKAHEAD_REQUEST, &mut lookahead),
+            ]
+        };
+
+        // Owned from here so a failed ctl still frees the encoder
+        let mut opus = Self {
+            encoder,
+            pre_skip: lookahead.max(0) as u64 * GRANULE_RATE / config.sample_rate as u64,
+            ogg: (config.container == OpusContainer::Ogg).then(|| PacketWriter::new(Vec::new())),
+            config,
+            headers_written: false,
+            packet: vec![0; MAX_PACKET_BYTES],
+        };
+        if let Some(&code) = status.iter().find(|&&code| code != OPUS_OK) {
+            return Err(opus_error(code));
+        }
+        opus.headers_written = opus.ogg.is_none();
+        Ok(opus)
+    }
+
+    pub fn config(&self) -> &OpusConfig {
+        &self.config
+    }
+
+    /// Samples per channel in one 20ms Opus frame
+    pub fn frame_samples(&self) -> usize {
+        self.config.sample_rate as usize / 50
+    }
+
+    /// Encode one interleaved frame (2.5-60ms) starting at `sample_offset`
+    /// (per channel). With Ogg, a page is closed when `end_page` is set.
+    pub fn encode(&mut self, pcm: &[i16], sample_offset: u64, end_page: bool) -> Result<Vec<u8>> {
+        let frame_samples = pcm.len() / self.config.channels;
+        let len = unsafe {
+            opus_encode(
+                self.encoder.as_ptr(),
+                pcm.as_ptr(),
+                frame_samples as i32,
+                self.packet.as_mut_ptr(),
+                self.packet.len() as i32,
+            )
+        };
+        if len < 0 {
+            return Err(opus_error(len));
+        }
+        let packet = self.packet[..len as usize].to_vec();
+
+        let granule = self.granule(sample_offset + frame_samples as u64);
+        let end = if end_page { PacketWriteEndInfo::EndPage } else { PacketWriteEndInfo::NormalPacket };
+        self.write_ogg(packet, end, granule)
+    }
+
+    /// End the Ogg stream (no-op for raw packets); `sample_offset` is where
+    /// the stream stopped
+    pub fn finish(&mut self, sample_offset: u64) -> Result<Vec<u8>> {
+        if self.ogg.is_none() {
+            return Ok(Vec::new());
+        }
+        // An empty packet cannot end a stream, so close it with a 2.5ms DTX frame
+        let silence = vec![0i16; self.config.sample_rate as usize / 400 * self.config.channels];
+        let len = unsafe {
+            opus_encode(
+                self.encoder.as_ptr(),
+                silence.as_ptr(),
+                (silence.len() / self.config.channels) as i32,
+                self.packet.as_mut_ptr(),
+                self.packet.len() as i32,
+            )
+        };
+        if len < 0 {
+            return Err(opus_error(len));
+        }
+        let packet = self.packet[..len as usize].to_vec();
+        let granule = self.granule(sample_offset);
+        self.write_ogg(packet, PacketWriteEndInfo::EndStream, granule)
+    }
+
+    fn granule(&self, samples: u64) -> u64 {
+        self.pre_skip + samples * GRANULE_RATE / self.config.sample_rate as u64
+    }
+
+    /// Raw packets pass straight through; Ogg returns whatever pages closed
+    fn write_ogg(&mut self, packet: Vec<u8>, end: PacketWriteEndInfo, granule: u64) -> Result<Vec<u8>> {
+        if self.ogg.is_none() {
+            return Ok(packet);
+        }
+        if !self.headers_written {
+            self.write_headers()?;
+        }
+        let Some(ogg) = self.ogg.as_mut() else { return Ok(packet) };
+        ogg.write_packet(packet, OGG_SERIAL, end, granule)?;
+        Ok(std::mem::take(ogg.inner_mut()))
+    }
+
+    /// OpusHead and OpusTags, each on its own page (RFC 7845 section 5)
+    fn write_headers(&mut self) -> Result<()> {
+        let Some(ogg) = self.ogg.as_mut() else { return Ok(()) };
+
+        let mut head = Vec::with_capacity(19);
+        head.extend_from_slice(b"OpusHead");
+        head.push(1);
+        head.push(self.config.channels as u8);
+        head.extend_from_slice(&(self.pre_skip as u16).to_le_bytes());
+        head.extend_from_slice(&self.config.sample_rate.to_le_bytes());
+        head.extend_from_slice(&0i16.to_le_bytes());
+        head.push(0);
+        ogg.write_packet(head, OGG_SERIAL, PacketWriteEndInfo::EndPage, 0)?;
+
+        let vendor = b"natively-audio";
+        let mut tags = Vec::with_capacity(16 + vendor.len());
+        tags.extend_from_slice(b"OpusTags");
+        tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        tags.extend_from_slice(vendor);
+        tags.extend_from_slice(&0u32.to_le_bytes());
+        ogg.write_packet(tags, OGG_SERIAL, PacketWriteEndInfo::EndPage, 0)?;
+
+        self.headers_written = true;
+        Ok(())
+    }
+}
+
+impl Drop for OpusEncoder {
+    fn drop(&mut self) {
+        unsafe { opus_encoder_destroy(self.encoder.as_ptr()) };
+    }
+}
+
+/// Encodes every frame that reaches it; frames leave with `payload` set.
+/// With Ogg, every frame closes a page so nothing waits for the next one
+/// (the first payload also carries the stream headers).
+pub struct OpusStage {
+    encoder: OpusEncoder,
+}
+
+impl OpusStage {
+    pub fn new(config: OpusConfig) -> Result<Self> {
+        Ok(Self { encoder: OpusEncoder::new(config)? })
+    }
+}
+
+impl FrameProcessor for OpusStage {
+    fn name(&self) -> &'static str {
+        "opus"
+    }
+
+    fn process(&mut self, frame: &mut Frame) -> StageOutput {
+        match self.encoder.encode(&frame.samples, frame.sample_offset, true) {
+            Ok(bytes) => {
+                frame.payload = Some(bytes);
+                StageOutput::Forward
+            }
+            Err(e) => {
+                log::warn!("[OpusStage] Dropping frame: {}", e);
+                StageOutput::Drop
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use unsafe_libopus::{opus_decode, opus_decoder_create, opus_decoder_destroy};
+
+    pub(crate) fn speechlike(len: usize) -> Vec<i16> {
+        (0..len)
+            .map(|n| {
+                let t = n as f32 / 16000.0;
+                let envelope = 0.6 + 0.4 * (t * 2.0 * std::f32::consts::PI * 3.0).sin();
+                let voice = (t * 2.0 * std::f32::consts::PI * 180.0).sin()
+                    + 0.5 * (t * 2.0 * std::f32::consts::PI * 360.0).sin()
+                    + 0.25 * (t * 2.0 * std::f32::consts::PI * 900.0).sin();
+                (voice * envelope * 6000.0) as i16
+            })
+            .collect()
+    }
+
+    /// Decode an Ogg Opus stream; returns (OpusHead, PCM, last granule)
+    pub(crate) fn decode_ogg(bytes: &[u8]) -> (Vec<u8>, Vec<i16>, u64) {
+        let mut reader = ogg::PacketReader::new(Cursor::new(bytes));
+        let head = reader.read_packet_expected().unwrap().data;
+        let tags = reader.read_packet_expected().unwrap().data;
+        assert!(tags.starts_with(b"OpusTags"));
+
+        let mut error = 0;
+        let decoder = unsafe { opus_decoder_create(16000, 1, &mut error) };
+        assert_eq!(error, OPUS_OK);
+        let mut pcm = Vec::new();
+        let mut granule = 0;
+        let mut buf = vec![0i16; 5760];
+        while let Some(packet) = reader.read_packet().unwrap() {
+            let n = unsafe {
+                opus_decode(decoder, packet.data.as_ptr(), packet.data.len() as i32, buf.as_mut_ptr(), buf.len() as i32, 0)
+            };
+            assert!(n > 0);
+            pcm.extend_from_slice(&buf[..n as usize]);
+            granule = packet.absgp_page();
+        }
+        unsafe { opus_decoder_destroy(decoder) };
+        (head, pcm, granule)
+    }
+
+    #[test]
+    fn test_ogg_stream_decodes_at_a_tenth_of_the_size() {
+        let input = speechlike(16000 * 2);
+        let mut encoder = OpusEncoder::new(OpusConfig::new(16000, 1)).unwrap();
+
+        // One page per second, as recordings are written
+        let mut bytes = Vec::new();
+        for (i, frame) in input.chunks(320).enumerate() {
+            bytes.extend(encoder.encode(frame, i as u64 * 320, (i + 1) % 50 == 0).unwrap());
+        }
+        bytes.extend(encoder.finish(input.len() as u64).unwrap());
+
+        let pcm_bytes = input.len() * 2;
+        assert!(bytes.len() * 9 < pcm_bytes, "{} bytes for {} bytes of PCM", bytes.len(), pcm_bytes);
+
+        let (head, decoded, granule) = decode_ogg(&bytes);
+        assert!(head.starts_with(b"OpusHead"));
+        let pre_skip = u16::from_le_bytes([head[10], head[11]]) as usize;
+        assert_eq!(granule, pre_skip as u64 + 2 * 48000);
+
+        // Opus is perceptual rather than waveform-exact at 24 kbps, so
+        // compare the level envelope frame by frame after the encoder delay
+        // (pre-skip is in 48kHz samples)
+        let delay = pre_skip / 3;
+        let rms_db = |s: &[i16]| {
+            let mean = s.iter().map(|&x| (x as f64).powi(2)).sum::<f64>() / s.len() as f64;
+            10.0 * mean.max(1.0).log10()
+        };
+        let frames = input.chunks(320).zip(decoded[delay..].chunks(320)).skip(5).take(90);
+        for (i, (original, coded)) in frames.enumerate() {
+            let diff = (rms_db(original) - rms_db(coded)).abs();
+            assert!(diff < 2.0, "frame {}: level off by {:.1} dB", i + 5, diff);
+        }
+    }
+}
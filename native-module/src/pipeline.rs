@@ -0,0 +1,791 @@
+// DSP Pipeline - shared by every capture class
+//
+// Architecture:
+// 1. Drain the backend's lock-free ring buffer
+// 2. Resample to the output rate (f32 -> i16)
+// 3. Cut fixed-size frames (FRAME_SAMPLES per channel)
+// 4. Run the frame through an ordered list of stages
+// 5. Hand surviving frames to the sink (the JS callback)
+//
+// Stages implement `FrameProcessor` and may modify the frame in place or
+// drop it. Everything runs on one DSP thread per capture source; nothing
+// here touches the real-time audio callback.
+//
+// Backends publish their device rate through a `RateHandle`, tagged with
+// the ring buffer position it applies from. When it changes mid-stream
+// (e.g. output switched to AirPods) the DSP thread drains the samples
+// still buffered at the old rate, then rebuilds the resampler, so pitch
+// and timing stay correct across the switch. A new device's consumer can
+// be handed to a running DSP thread through its `PipelineInput` (hot-plug).
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use ringbuf::traits::Consumer;
+
+use crate::audio_config::{DSP_POLL_MS, FRAME_MS, FRAME_SAMPLES, SAMPLE_RATE};
+use crate::errors::ErrorReporter;
+use crate::streaming_resampler::{InterleavedResampler, ResamplerQuality};
+
+/// Input samples drained from the ring buffer per iteration (per channel)
+const DRAIN_BATCH: usize = 480;
+
+/// One frame of output audio
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Interleaved i16 samples at the output rate
+    pub samples: Vec<i16>,
+    /// Channels per sample frame
+    pub channels: usize,
+    /// Output-rate sample index (per channel) of the first sample since the
+    /// stream started; dropped frames still advance it
+    pub sample_offset: u64,
+    /// When the first sample of the frame was captured (estimated from the
+    /// time its input block was drained)
+    pub captured_at: Instant,
+    /// Encoded bytes set by an encoder stage, delivered instead of the PCM
+    pub payload: Option<Vec<u8>>,
+}
+
+/// Receives the frames that survive the stages, on the DSP thread
+pub trait FrameSink: Send {
+    fn frame(&mut self, frame: Frame);
+
+    /// Called once per DSP iteration, whether or not frames were delivered
+    fn idle(&mut self, _now: Instant) {}
+}
+
+impl<T: FrameSink> FrameSink for Option<T> {
+    fn frame(&mut self, frame: Frame) {
+        if let Some(sink) = self.as_mut() {
+            sink.frame(frame);
+        }
+    }
+
+    fn idle(&mut self, now: Instant) {
+        if let Some(sink) = self.as_mut() {
+            sink.idle(now);
+        }
+    }
+}
+
+/// Bits of `DeviceRate::current` holding the rate; the rest is the position
+const RATE_BITS: u32 = 20;
+const RATE_MASK: u64 = (1 << RATE_BITS) - 1;
+
+/// Device rate of a capture backend, and the ring buffer position it
+/// applies from. Positions count interleaved samples pushed since the ring
+/// buffer was created; the DSP thread counts the samples it pops the same
+/// way, so it switches the resampler exactly at the first sample at the new
+/// rate.
+#[derive(Debug)]
+pub struct DeviceRate {
+    /// Latest rate (low `RATE_BITS`) and the position it applies from,
+    /// packed so both are read together
+    current: AtomicU64,
+    /// Rate of the samples before that position
+    previous: AtomicU32,
+}
+
+impl DeviceRate {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            current: AtomicU64::new(sample_rate as u64 & RATE_MASK),
+            previous: AtomicU32::new(sample_rate),
+        }
+    }
+
+    /// Real-time safe: samples pushed from `position` on are at
+    /// `sample_rate`. Call before pushing them; a repeated rate is ignored.
+    pub fn set(&self, sample_rate: u32, position: u64) {
+        let rate = self.rate();
+        if sample_rate == rate {
+            return;
+        }
+        self.previous.store(rate, Ordering::Relaxed);
+        self.current.store((position << RATE_BITS) | (sample_rate as u64 & RATE_MASK), Ordering::Release);
+    }
+
+    /// Latest rate
+    pub fn rate(&self) -> u32 {
+        self.change().0
+    }
+
+    /// Latest rate and the position it applies from
+    pub fn change(&self) -> (u32, u64) {
+        let current = self.current.load(Ordering::Acquire);
+        ((current & RATE_MASK) as u32, current >> RATE_BITS)
+    }
+
+    /// Rate of the sample at `position`
+    pub fn rate_at(&self, position: u64) -> u32 {
+        match self.change() {
+            (rate, from) if position >= from => rate,
+            _ => self.previous.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Shared by a backend and the DSP thread draining it; backends whose rate
+/// can change mid-stream update it from their IO callback
+pub type RateHandle = Arc<DeviceRate>;
+
+/// Fixed-rate backends hand out a handle that never changes
+pub fn fixed_rate(sample_rate: u32) -> RateHandle {
+    Arc::new(DeviceRate::new(sample_rate))
+}
+
+/// The backend a DSP thread reads from. Another thread (the device monitor)
+/// can hand over a new device's consumer and rate while the pipeline runs.
+pub struct PipelineInput<C> {
+    state: Mutex<InputState<C>>,
+    /// Output samples (per channel) framed so far
+    position: AtomicU64,
+    /// Interleaved samples popped from the current consumer, kept while
+    /// the consumer is parked between DSP threads
+    consumed: AtomicU64,
+}
+
+struct InputState<C> {
+    sample_rate: RateHandle,
+    pending: Option<C>,
+}
+
+impl<C> PipelineInput<C> {
+    pub fn new(sample_rate: RateHandle) -> Self {
+        Self {
+            state: Mutex::new(InputState { sample_rate, pending: None }),
+            position: AtomicU64::new(0),
+            consumed: AtomicU64::new(0),
+        }
+    }
+
+    /// Rate of the next sample the consumer will pop
+    pub fn sample_rate(&self) -> u32 {
+        self.rate_handle().rate_at(self.consumed.load(Ordering::Relaxed))
+    }
+
+    pub fn rate_handle(&self) -> RateHandle {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).sample_rate.clone()
+    }
+
+    /// Switch to a new device; the DSP thread picks it up before its next block
+    pub fn replace(&self, consumer: C, sample_rate: RateHandle) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.sample_rate = sample_rate;
+        state.pending = Some(consumer);
+    }
+
+    /// The replacement consumer and its rate, if one is waiting
+    pub fn take_pending(&self) -> Option<(C, RateHandle)> {
+        let mut state = self.state.lock().ok()?;
+        let consumer = state.pending.take()?;
+        self.consumed.store(0, Ordering::Relaxed);
+        Some((consumer, state.sample_rate.clone()))
+    }
+
+    /// Stream position for events raised off the DSP thread
+    pub fn position(&self) -> u64 {
+        self.position.load(Ordering::Relaxed)
+    }
+}
+
+/// The device rate changed and the resampler was rebuilt
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FormatChange {
+    pub previous_rate: f64,
+    pub sample_rate: f64,
+    /// Output-rate sample index of the first frame resampled at the new rate
+    pub sample_offset: u64,
+}
+
+/// Notified on the DSP thread when the input format changes
+pub type FormatListener = Arc<dyn Fn(FormatChange) + Send + Sync>;
+
+/// What a stage wants done with the frame it was given
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StageOutput {
+    /// Pass the (possibly modified) frame to the next stage
+    Forward,
+    /// Drop the frame; later stages and the sink never see it
+    Drop,
+}
+
+/// A processing stage operating on fixed-size frames
+pub trait FrameProcessor: Send {
+    /// Short name used in logs
+    fn name(&self) -> &'static str;
+
+    /// Process one frame in place
+    fn process(&mut self, frame: &mut Frame) -> StageOutput;
+
+    /// Clear internal state (e.g. when the stream restarts)
+    fn reset(&mut self) {}
+}
+
+/// Observes a pipeline's audio without affecting it (recorders, mixers)
+pub trait PipelineTap: Send + Sync {
+    /// Raw interleaved input at the device rate
+    fn input(&self, _samples: &[f32]) {}
+
+    /// Every resampled frame, before any stage can drop it
+    fn frame(&self, _frame: &Frame) {}
+
+    /// Closed taps are dropped from the registry
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+/// Taps attached to one source; shared by the capture class and its DSP
+/// thread so taps can come and go without restarting the pipeline
+#[derive(Default)]
+pub struct TapRegistry {
+    taps: Mutex<Vec<Arc<dyn PipelineTap>>>,
+}
+
+impl TapRegistry {
+    pub fn add(&self, tap: Arc<dyn PipelineTap>) {
+        if let Ok(mut taps) = self.taps.lock() {
+            taps.push(tap);
+        }
+    }
+
+    pub fn remove(&self, tap: &Arc<dyn PipelineTap>) {
+        if let Ok(mut taps) = self.taps.lock() {
+            taps.retain(|t| !Arc::ptr_eq(t, tap));
+        }
+    }
+
+    /// True when no open tap is attached (closed taps are pruned)
+    pub fn is_empty(&self) -> bool {
+        match self.taps.lock() {
+            Ok(mut taps) => {
+                taps.retain(|t| !t.is_closed());
+                taps.is_empty()
+            }
+            Err(_) => true,
+        }
+    }
+
+    fn input(&self, samples: &[f32]) {
+        if let Ok(taps) = self.taps.lock() {
+            taps.iter().for_each(|t| t.input(samples));
+        }
+    }
+
+    fn frame(&self, frame: &Frame) {
+        if let Ok(taps) = self.taps.lock() {
+            taps.iter().for_each(|t| t.frame(frame));
+        }
+    }
+}
+
+/// Sample type of the PCM handed to JS
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleFormat {
+    /// Little-endian 16-bit integers
+    I16,
+    /// Little-endian 32-bit floats in -1.0..1.0
+    F32,
+}
+
+/// Output rate, frame duration and sample type of a capture source
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputFormat {
+    pub sample_rate: u32,
+    pub frame_ms: u32,
+    pub sample_format: SampleFormat,
+    /// How the device rate is converted to `sample_rate`
+    pub resampler: ResamplerQuality,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        Self {
+            sample_rate: SAMPLE_RATE,
+            frame_ms: FRAME_MS,
+            sample_format: SampleFormat::I16,
+            resampler: ResamplerQuality::Linear,
+        }
+    }
+}
+
+/// Rates and frame durations accepted from JS
+pub const OUTPUT_SAMPLE_RATES: &[u32] = &[8000, 16000, 24000, 48000];
+pub const OUTPUT_FRAME_MS: &[u32] = &[10, 20, 40, 100];
+
+impl OutputFormat {
+    /// Parse the JS-facing options; anything left out keeps the default
+    pub fn from_options(sample_rate: Option<u32>, frame_ms: Option<u32>, sample_format: Option<&str>) -> Result<Self> {
+        let default = Self::default();
+        let sample_rate = sample_rate.unwrap_or(default.sample_rate);
+        if !OUTPUT_SAMPLE_RATES.contains(&sample_rate) {
+            return Err(anyhow::anyhow!("Unsupported sample rate: {} (use one of {:?})", sample_rate, OUTPUT_SAMPLE_RATES));
+        }
+        let frame_ms = frame_ms.unwrap_or(default.frame_ms);
+        if !OUTPUT_FRAME_MS.contains(&frame_ms) {
+            return Err(anyhow::anyhow!("Unsupported frame duration: {}ms (use one of {:?})", frame_ms, OUTPUT_FRAME_MS));
+        }
+        let sample_format = match sample_format {
+            None | Some("i16") => SampleFormat::I16,
+            Some("f32") => SampleFormat::F32,
+            Some(other) => return Err(anyhow::anyhow!("Unknown sample format: {}", other)),
+        };
+        Ok(Self { sample_rate, frame_ms, sample_format, ..default })
+    }
+
+    /// Samples per channel in one frame
+    pub fn frame_samples(&self) -> usize {
+        (self.sample_rate * self.frame_ms / 1000) as usize
+    }
+
+    /// Little-endian bytes of a frame in this format
+    pub fn encode(&self, samples: &[i16]) -> Vec<u8> {
+        match self.sample_format {
+            SampleFormat::I16 => samples.iter().flat_map(|s| s.to_le_bytes()).collect(),
+            SampleFormat::F32 => samples.iter().flat_map(|&s| (s as f32 / 32768.0).to_le_bytes()).collect(),
+        }
+    }
+}
+
+/// Static pipeline parameters
+#[derive(Debug, Clone)]
+pub struct PipelineConfig {
+    /// Log prefix, e.g. "MicrophoneCapture"
+    pub label: &'static str,
+    pub input_sample_rate: f64,
+    pub output_sample_rate: f64,
+    pub channels: usize,
+    /// Samples per channel in each output frame
+    pub frame_samples: usize,
+    pub resampler_quality: ResamplerQuality,
+}
+
+impl PipelineConfig {
+    /// Default output format (16kHz, 20ms frames) for the given input
+    pub fn new(label: &'static str, input_sample_rate: f64, channels: usize) -> Self {
+        Self {
+            label,
+            input_sample_rate,
+            output_sample_rate: SAMPLE_RATE as f64,
+            channels: channels.max(1),
+            frame_samples: FRAME_SAMPLES,
+            resampler_quality: ResamplerQuality::Linear,
+        }
+    }
+
+    /// Resample and frame to `output` instead of the defaults
+    pub fn with_output(mut self, output: &OutputFormat) -> Self {
+        self.output_sample_rate = output.sample_rate as f64;
+        self.frame_samples = output.frame_samples();
+        self.resampler_quality = output.resampler;
+        self
+    }
+}
+
+/// Resampler + framer + ordered stages
+pub struct Pipeline {
+    config: PipelineConfig,
+    resampler: InterleavedResampler,
+    stages: Vec<Box<dyn FrameProcessor>>,
+    frame_buffer: Vec<i16>,
+    /// Samples per channel framed so far
+    sample_offset: u64,
+    taps: Option<Arc<TapRegistry>>,
+    input_rate: Option<RateHandle>,
+    format_listener: Option<FormatListener>,
+    errors: Option<Arc<ErrorReporter>>,
+}
+
+impl Pipeline {
+    pub fn new(config: PipelineConfig) -> Self {
+        let resampler = InterleavedResampler::with_quality(
+            config.input_sample_rate,
+            config.output_sample_rate,
+            config.channels,
+            config.resampler_quality,
+        );
+        let frame_len = config.frame_samples * config.channels;
+
+        Self {
+            config,
+            resampler,
+            stages: Vec::new(),
+            frame_buffer: Vec::with_capacity(frame_len * 4),
+            sample_offset: 0,
+            taps: None,
+            input_rate: None,
+            format_listener: None,
+            errors: None,
+        }
+    }
+
+    /// Append a stage; stages run in the order they are added
+    pub fn with_stage(mut self, stage: Box<dyn FrameProcessor>) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    /// Append several stages in order
+    pub fn with_stages(mut self, stages: Vec<Box<dyn FrameProcessor>>) -> Self {
+        self.stages.extend(stages);
+        self
+    }
+
+    /// Tee raw input and resampled frames to whatever taps are registered
+    pub fn with_taps(mut self, taps: Arc<TapRegistry>) -> Self {
+        self.taps = Some(taps);
+        self
+    }
+
+    /// Tell `listener` when the device rate changes
+    pub fn with_format_listener(mut self, listener: Option<FormatListener>) -> Self {
+        self.format_listener = listener;
+        self
+    }
+
+    /// Report errors the backend flagged from its audio callback
+    pub fn with_errors(mut self, errors: Option<Arc<ErrorReporter>>) -> Self {
+        self.errors = errors;
+        self
+    }
+
+    pub fn config(&self) -> &PipelineConfig {
+        &self.config
+    }
+
+    /// Interleaved samples per output frame
+    fn frame_len(&self) -> usize {
+        self.config.frame_samples * self.config.channels
+    }
+
+    /// Switch to a new device rate. The output timeline (frame buffer,
+    /// sample offset) carries on; only the resampler starts over.
+    pub fn set_input_rate(&mut self, sample_rate: f64) {
+        if sample_rate <= 0.0 || sample_rate == self.config.input_sample_rate {
+            return;
+        }
+        let change = FormatChange {
+            previous_rate: self.config.input_sample_rate,
+            sample_rate,
+            sample_offset: self.sample_offset + (self.frame_buffer.len() / self.config.channels) as u64,
+        };
+        log::info!(
+            "[{}] Input rate changed: {}Hz -> {}Hz",
+            self.config.label, change.previous_rate, change.sample_rate
+        );
+
+        self.config.input_sample_rate = sample_rate;
+        self.resampler = InterleavedResampler::with_quality(
+            sample_rate,
+            self.config.output_sample_rate,
+            self.config.channels,
+            self.config.resampler_quality,
+        );
+        if let Some(listener) = self.format_listener.as_ref() {
+            listener(change);
+        }
+    }
+
+    /// Pick up a rate change published by the backend once `consumed`
+    /// (samples popped so far) reaches the position it applies from.
+    /// Returns how many samples are still at the old rate, if any.
+    fn poll_input_rate(&mut self, consumed: u64) -> Option<usize> {
+        let (rate, from) = self.input_rate.as_ref()?.change();
+        if rate as f64 == self.config.input_sample_rate {
+            return None;
+        }
+        if consumed < from {
+            return Some((from - consumed) as usize);
+        }
+        self.set_input_rate(rate as f64);
+        None
+    }
+
+    /// Resample a block of interleaved input and emit every completed frame
+    /// that survives the stages. Leftover samples are kept for the next call.
+    pub fn push_input<S>(&mut self, input: &[f32], sink: &mut S)
+    where
+        S: FnMut(Frame),
+    {
+        self.push_input_at(input, Instant::now(), sink);
+    }
+
+    /// `push_input` for a block whose last sample was captured at `now`
+    pub fn push_input_at<S>(&mut self, input: &[f32], now: Instant, sink: &mut S)
+    where
+        S: FnMut(Frame),
+    {
+        if !input.is_empty() {
+            if let Some(taps) = self.taps.as_ref() {
+                taps.input(input);
+            }
+            let resampled = self.resampler.resample(input);
+            self.frame_buffer.extend(resampled);
+        }
+
+        let frame_len = self.frame_len();
+        let rate = self.config.output_sample_rate;
+        while self.frame_buffer.len() >= frame_len {
+            // Everything still buffered (this frame included) was captured
+            // before `now`
+            let buffered = (self.frame_buffer.len() / self.config.channels) as f64 / rate;
+            let captured_at = now.checked_sub(Duration::from_secs_f64(buffered)).unwrap_or(now);
+
+            let mut frame = Frame {
+                samples: self.frame_buffer.drain(0..frame_len).collect(),
+                channels: self.config.channels,
+                sample_offset: self.sample_offset,
+                captured_at,
+                payload: None,
+            };
+            self.sample_offset += self.config.frame_samples as u64;
+
+            // Taps get every frame, before any stage can drop it
+            if let Some(taps) = self.taps.as_ref() {
+                taps.frame(&frame);
+            }
+
+            let forwarded = self.stages.iter_mut()
+                .all(|stage| stage.process(&mut frame) == StageOutput::Forward);
+
+            if forwarded {
+                sink(frame);
+            }
+        }
+    }
+
+    /// Run the pipeline on its own DSP thread until `stop_signal` is set.
+    /// The thread hands the consumer back so the source can be re-attached
+    /// to a new pipeline without reopening the device.
+    pub fn spawn<C, S>(
+        mut self,
+        mut consumer: C,
+        input: Arc<PipelineInput<C>>,
+        stop_signal: Arc<AtomicBool>,
+        mut sink: S,
+    ) -> thread::JoinHandle<C>
+    where
+        C: Consumer<Item = f32> + Send + 'static,
+        S: FrameSink + 'static,
+    {
+        thread::spawn(move || {
+            let channels = self.config.channels;
+            let batch_len = DRAIN_BATCH * channels;
+            let mut raw_batch: Vec<f32> = Vec::with_capacity(4096);
+
+            self.input_rate = Some(input.rate_handle());
+            let mut consumed = input.consumed.load(Ordering::Relaxed);
+            let stage_names: Vec<&str> = self.stages.iter().map(|s| s.name()).collect();
+            log::info!("[{}] DSP thread started (stages: {:?})", self.config.label, stage_names);
+
+            loop {
+                if stop_signal.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                // 0. Follow the backend: swapped devices, rate changes
+                if let Some((replacement, sample_rate)) = input.take_pending() {
+                    log::info!("[{}] Input device switched", self.config.label);
+                    consumer = replacement;
+                    consumed = 0;
+                    self.input_rate = Some(sample_rate);
+                }
+                let old_rate_left = self.poll_input_rate(consumed);
+                input.position.store(self.sample_offset, Ordering::Relaxed);
+                if let Some(errors) = self.errors.as_ref() {
+                    errors.flush_flags();
+                }
+
+                // 1. Drain ring buffer (lock-free), stopping on a frame
+                //    boundary so channels stay aligned, and where the rate
+                //    changes
+                let limit = old_rate_left.map_or(batch_len, |left| left.min(batch_len));
+                while raw_batch.len() < limit || !raw_batch.len().is_multiple_of(channels) {
+                    match consumer.try_pop() {
+                        Some(sample) => raw_batch.push(sample),
+                        None => break,
+                    }
+                }
+
+                // 2-5. Resample, frame, run stages, deliver
+                let drained = raw_batch.len();
+                consumed += drained as u64;
+                input.consumed.store(consumed, Ordering::Relaxed);
+                self.push_input(&raw_batch, &mut |frame| sink.frame(frame));
+                sink.idle(Instant::now());
+                raw_batch.clear();
+
+                // 6. Short sleep when the ring buffer ran dry
+                if drained < limit {
+                    thread::sleep(Duration::from_millis(DSP_POLL_MS));
+                }
+            }
+
+            log::info!("[{}] DSP thread stopped.", self.config.label);
+            consumer
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Halves every sample and drops every other frame
+    struct HalveAndThin {
+        count: usize,
+    }
+
+    impl FrameProcessor for HalveAndThin {
+        fn name(&self) -> &'static str {
+            "halve"
+        }
+
+        fn process(&mut self, frame: &mut Frame) -> StageOutput {
+            self.count += 1;
+            frame.samples.iter_mut().for_each(|s| *s /= 2);
+            if self.count.is_multiple_of(2) { StageOutput::Drop } else { StageOutput::Forward }
+        }
+    }
+
+    #[test]
+    fn test_frames_are_fixed_size() {
+        let mut pipeline = Pipeline::new(PipelineConfig::new("test", 48000.0, 1));
+        let mut frames = Vec::new();
+
+        // 100ms at 48kHz in odd-sized blocks -> 5 frames of 20ms at 16kHz
+        let input = vec![0.25f32; 4800];
+        for block in input.chunks(333) {
+            pipeline.push_input(block, &mut |f: Frame| frames.push(f));
+        }
+
+        assert!(frames.len() >= 4);
+        assert!(frames.iter().all(|f| f.samples.len() == FRAME_SAMPLES));
+    }
+
+    #[test]
+    fn test_output_format_sets_rate_and_frame_size() {
+        let output = OutputFormat::from_options(Some(48000), Some(10), Some("f32")).unwrap();
+        let mut pipeline = Pipeline::new(PipelineConfig::new("test", 44100.0, 1).with_output(&output));
+        let mut frames = Vec::new();
+
+        // 1s at 44.1kHz -> ~100 frames of 480 samples
+        let input = vec![0.25f32; 44100];
+        for block in input.chunks(441) {
+            pipeline.push_input(block, &mut |f: Frame| frames.push(f));
+        }
+        assert!((98..=100).contains(&frames.len()), "{} frames", frames.len());
+        assert!(frames.iter().all(|f| f.samples.len() == 480));
+
+        let bytes = output.encode(&frames[10].samples);
+        assert_eq!(bytes.len(), 480 * 4);
+        let first = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        assert!((first - 0.25).abs() < 0.01);
+
+        assert!(OutputFormat::from_options(Some(44100), None, None).is_err());
+        assert!(OutputFormat::from_options(None, Some(30), None).is_err());
+    }
+
+    #[test]
+    fn test_input_rate_change_keeps_timing() {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let seen = changes.clone();
+        let listener: FormatListener = Arc::new(move |change| seen.lock().unwrap().push(change));
+        let mut pipeline = Pipeline::new(PipelineConfig::new("test", 48000.0, 1))
+            .with_format_listener(Some(listener));
+        let mut frames = Vec::new();
+
+        // 100ms at 48kHz, then the device switches to 24kHz for 100ms
+        let tone = |rate: usize| -> Vec<f32> {
+            (0..rate / 10).map(|i| (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / rate as f32).sin() * 0.5).collect()
+        };
+        pipeline.push_input(&tone(48000), &mut |f: Frame| frames.push(f));
+        pipeline.set_input_rate(24000.0);
+        pipeline.push_input(&tone(24000), &mut |f: Frame| frames.push(f));
+
+        let changes = changes.lock().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].previous_rate, changes[0].sample_rate), (48000.0, 24000.0));
+        assert_eq!(changes[0].sample_offset, 1600);
+
+        // 200ms of output, and still a 1kHz tone (16 samples per period)
+        assert!(frames.len() >= 9);
+        let samples: Vec<i16> = frames.iter().flat_map(|f| f.samples.iter().copied()).collect();
+        let crossings = samples[1700..3100].windows(2).filter(|w| w[0] < 0 && w[1] >= 0).count();
+        assert!((86..=89).contains(&crossings), "{} crossings", crossings);
+    }
+
+    struct Collect(Arc<Mutex<Vec<Frame>>>);
+
+    impl FrameSink for Collect {
+        fn frame(&mut self, frame: Frame) {
+            self.0.lock().unwrap().push(frame);
+        }
+    }
+
+    #[test]
+    fn test_buffered_samples_keep_their_rate_across_a_switch() {
+        use ringbuf::{traits::{Producer, Split}, HeapRb};
+
+        let tone = |rate: usize| -> Vec<f32> {
+            (0..rate / 10).map(|i| (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / rate as f32).sin() * 0.5).collect()
+        };
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let seen = changes.clone();
+        let listener: FormatListener = Arc::new(move |change| seen.lock().unwrap().push(change));
+
+        // The backend delivers 100ms at 48kHz, switches to 24kHz and
+        // delivers 100ms more, all before the DSP thread drains anything
+        let (mut producer, consumer) = HeapRb::<f32>::new(16384).split();
+        let rate = fixed_rate(48000);
+        producer.push_slice(&tone(48000));
+        rate.set(24000, 4800);
+        producer.push_slice(&tone(24000));
+
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let pipeline = Pipeline::new(PipelineConfig::new("test", 48000.0, 1))
+            .with_format_listener(Some(listener));
+        let handle = pipeline.spawn(consumer, Arc::new(PipelineInput::new(rate)), stop.clone(), Collect(frames.clone()));
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while frames.lock().unwrap().len() < 9 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        stop.store(true, Ordering::SeqCst);
+        handle.join().unwrap();
+
+        let changes = changes.lock().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].sample_offset, 1600);
+
+        // 200ms of output, a 1kHz tone (16 samples per period) on both sides
+        // of the switch
+        let samples: Vec<i16> = frames.lock().unwrap().iter().flat_map(|f| f.samples.clone()).collect();
+        assert!(samples.len() >= 9 * FRAME_SAMPLES, "{} samples", samples.len());
+        let crossings = |range: std::ops::Range<usize>| {
+            samples[range].windows(2).filter(|w| w[0] < 0 && w[1] >= 0).count()
+        };
+        assert!((86..=89).contains(&crossings(100..1500)), "{} crossings at 48kHz", crossings(100..1500));
+        assert!((86..=89).contains(&crossings(1700..3100)), "{} crossings at 24kHz", crossings(1700..3100));
+    }
+
+    #[test]
+    fn test_stages_run_in_order_and_can_drop() {
+        let mut pipeline = Pipeline::new(PipelineConfig::new("test", 16000.0, 1))
+            .with_stage(Box::new(HalveAndThin { count: 0 }));
+        let mut frames = Vec::new();
+
+        let input = vec![0.5f32; FRAME_SAMPLES * 4];
+        pipeline.push_input(&input, &mut |f: Frame| frames.push(f));
+
+        assert_eq!(frames.len(), 2);
+        assert!(frames.iter().all(|f| (f.samples[10] - 8191).abs() <= 1));
+        // Offsets keep counting through dropped frames
+        assert_eq!(frames[1].sample_offset, 2 * FRAME_SAMPLES as u64);
+    }
+}
@@ -0,0 +1,692 @@
+// Recorder - tees a capture pipeline to WAV, FLAC or Ogg Opus files
+//
+// Architecture:
+// 1. The DSP thread hands samples to the recorder's pipeline tap
+//    (post-resample frames or raw device-rate input, both as 16-bit PCM)
+// 2. The tap forwards them over a bounded channel (try_send, never blocks
+//    the DSP thread; chunks dropped while the writer is behind are counted
+//    and flagged as Overflow). Non-real-time writers such as the stereo
+//    mixer block instead, so their files never lose time.
+// 3. A writer thread encodes segments and rotates files
+//
+// CRASH SAFETY: headers are rewritten with the current length every second
+// of audio (Ogg pages are closed every second), so a killed process leaves
+// a playable file missing at most the last second.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::{Context as _, Result};
+use flacenc::bitsink::ByteSink;
+use flacenc::component::{BitRepr, Stream, StreamInfo};
+use flacenc::error::{Verified, Verify};
+use flacenc::source::{Fill, FrameBuf};
+
+use crate::errors::{ErrorCode, ErrorReporter};
+use crate::opus::{OpusConfig, OpusEncoder};
+use crate::pipeline::{Frame, PipelineTap};
+
+/// Buffered chunks between the DSP thread and the writer (~5s of frames)
+const CHANNEL_CAPACITY: usize = 256;
+
+/// FLAC block size in samples per channel
+const FLAC_BLOCK_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordFormat {
+    Wav,
+    Flac,
+    /// Ogg Opus; needs an Opus rate (the processed 16kHz source is fine)
+    Opus,
+}
+
+/// Which point of the pipeline is recorded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordSource {
+    /// Resampled output-rate frames, before any stage can drop them
+    Processed,
+    /// Device-rate input after channel mixing
+    Raw,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    pub path: PathBuf,
+    pub format: RecordFormat,
+    pub source: RecordSource,
+    /// Start a new file after this many seconds (None = single file)
+    pub rotate_seconds: Option<u32>,
+    /// Delete the oldest rotated files beyond this count (None = keep all)
+    pub max_files: Option<usize>,
+    /// Opus bitrate in bits per second (None = encoder default)
+    pub bitrate: Option<u32>,
+}
+
+impl RecorderConfig {
+    /// Parse the JS-facing options; the format defaults from the extension
+    pub fn from_options(
+        path: &str,
+        format: Option<&str>,
+        source: Option<&str>,
+        rotate_seconds: Option<u32>,
+        max_files: Option<u32>,
+    ) -> Result<Self> {
+        let path = PathBuf::from(path);
+        let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+        let format = match format {
+            Some("wav") => RecordFormat::Wav,
+            Some("flac") => RecordFormat::Flac,
+            Some("opus") => RecordFormat::Opus,
+            Some(other) => return Err(anyhow::anyhow!("Unknown recording format: {}", other)),
+            None => match extension.as_deref() {
+                Some("flac") => RecordFormat::Flac,
+                Some("opus" | "ogg") => RecordFormat::Opus,
+                _ => RecordFormat::Wav,
+            },
+        };
+        let source = match source {
+            None | Some("processed") => RecordSource::Processed,
+            Some("raw") => RecordSource::Raw,
+            Some(other) => return Err(anyhow::anyhow!("Unknown recording source: {}", other)),
+        };
+
+        Ok(Self {
+            path,
+            format,
+            source,
+            rotate_seconds: rotate_seconds.filter(|&s| s > 0),
+            max_files: max_files.filter(|&n| n > 0).map(|n| n as usize),
+            bitrate: None,
+        })
+    }
+}
+
+// ============================================================================
+// TAP (DSP thread side)
+// ============================================================================
+
+/// Registered on a pipeline while the recording runs
+struct RecordingTap {
+    source: RecordSource,
+    sender: Mutex<Option<SyncSender<Vec<i16>>>>,
+    /// Chunks dropped because the writer fell behind
+    dropped: AtomicU64,
+    /// Flagged on every drop; flushed by the capture's DSP thread
+    errors: Option<Arc<ErrorReporter>>,
+}
+
+impl RecordingTap {
+    fn send(&self, samples: Vec<i16>) {
+        if let Ok(sender) = self.sender.lock() {
+            if let Some(sender) = sender.as_ref() {
+                // Writer stalled: drop rather than block the DSP thread
+                if let Err(TrySendError::Full(_)) = sender.try_send(samples) {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    if let Some(errors) = self.errors.as_ref() {
+                        errors.flag(ErrorCode::Overflow, false);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Wait for room in the queue instead of dropping
+    fn send_blocking(&self, samples: Vec<i16>) {
+        let sender = self.sender.lock().ok().and_then(|sender| sender.clone());
+        if let Some(sender) = sender {
+            let _ = sender.send(samples);
+        }
+    }
+
+    fn close(&self) {
+        if let Ok(mut sender) = self.sender.lock() {
+            *sender = None;
+        }
+    }
+}
+
+impl PipelineTap for RecordingTap {
+    fn input(&self, samples: &[f32]) {
+        if self.source == RecordSource::Raw {
+            self.send(samples.iter().map(|&s| (s * 32767.0).clamp(-32768.0, 32767.0) as i16).collect());
+        }
+    }
+
+    fn frame(&self, frame: &Frame) {
+        if self.source == RecordSource::Processed {
+            self.send(frame.samples.clone());
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.sender.lock().map(|s| s.is_none()).unwrap_or(true)
+    }
+}
+
+// ============================================================================
+// RECORDER (writer thread)
+// ============================================================================
+
+/// One active recording; dropping it without `stop` still finalizes files
+pub struct Recorder {
+    tap: Arc<RecordingTap>,
+    writer: Option<thread::JoinHandle<Result<Vec<PathBuf>>>>,
+}
+
+impl Recorder {
+    /// Open the first file and start the writer thread. `sample_rate` and
+    /// `channels` describe the stream at the configured source point;
+    /// chunks the tap has to drop are flagged on `errors`.
+    pub fn start(
+        config: RecorderConfig,
+        sample_rate: u32,
+        channels: usize,
+        errors: Option<Arc<ErrorReporter>>,
+    ) -> Result<Self> {
+        let mut files = SegmentedWriter::new(config.clone(), sample_rate, channels.max(1));
+        files.open_next()?;
+
+        let (sender, receiver) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let writer = thread::spawn(move || files.run(receiver));
+        let tap = Arc::new(RecordingTap {
+            source: config.source,
+            sender: Mutex::new(Some(sender)),
+            dropped: AtomicU64::new(0),
+            errors,
+        });
+
+        log::info!("[Recorder] Recording {:?} ({:?}, {}Hz, {}ch) to {}",
+            config.format, config.source, sample_rate, channels, config.path.display());
+
+        Ok(Self { tap, writer: Some(writer) })
+    }
+
+    /// Tap to register on the recorded pipeline
+    pub fn tap(&self) -> Arc<dyn PipelineTap> {
+        self.tap.clone()
+    }
+
+    /// Queue interleaved samples directly, waiting while the writer is
+    /// behind (for writers that are not a real-time pipeline, e.g. the
+    /// stereo mixer)
+    pub fn write(&self, samples: Vec<i16>) {
+        self.tap.send_blocking(samples);
+    }
+
+    /// Chunks the pipeline tap dropped so far
+    pub fn dropped_chunks(&self) -> u64 {
+        self.tap.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Drain and finalize; returns every file written (oldest first,
+    /// excluding rotated files that were deleted)
+    pub fn stop(mut self) -> Result<Vec<PathBuf>> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<Vec<PathBuf>> {
+        // Dropping the sender ends the writer loop once the queue is drained
+        self.tap.close();
+        let dropped = self.dropped_chunks();
+        if dropped > 0 && self.writer.is_some() {
+            log::warn!("[Recorder] {} chunks were dropped while the writer was behind", dropped);
+        }
+        match self.writer.take() {
+            Some(writer) => writer.join().map_err(|_| anyhow::anyhow!("Recorder thread panicked"))?,
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            log::error!("[Recorder] Failed to finalize recording: {}", e);
+        }
+    }
+}
+
+/// Writes consecutive segments, rotating and pruning as configured
+struct SegmentedWriter {
+    config: RecorderConfig,
+    sample_rate: u32,
+    channels: usize,
+    current: Option<Box<dyn SegmentWriter>>,
+    /// Samples per channel in the current segment
+    segment_samples: u64,
+    since_flush: u64,
+    next_index: usize,
+    files: Vec<PathBuf>,
+}
+
+impl SegmentedWriter {
+    fn new(config: RecorderConfig, sample_rate: u32, channels: usize) -> Self {
+        Self {
+            config,
+            sample_rate,
+            channels,
+            current: None,
+            segment_samples: 0,
+            since_flush: 0,
+            next_index: 0,
+            files: Vec::new(),
+        }
+    }
+
+    fn run(mut self, receiver: Receiver<Vec<i16>>) -> Result<Vec<PathBuf>> {
+        for chunk in receiver {
+            self.write(&chunk)?;
+        }
+        self.close_current()?;
+        log::info!("[Recorder] Finished ({} files)", self.files.len());
+        Ok(self.files)
+    }
+
+    fn segment_path(&self, index: usize) -> PathBuf {
+        if self.config.rotate_seconds.is_none() {
+            return self.config.path.clone();
+        }
+        let path = &self.config.path;
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("recording");
+        let name = match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) => format!("{}-{:03}.{}", stem, index, ext),
+            None => format!("{}-{:03}", stem, index),
+        };
+        path.with_file_name(name)
+    }
+
+    fn open_next(&mut self) -> Result<()> {
+        self.close_current()?;
+
+        let path = self.segment_path(self.next_index);
+        self.next_index += 1;
+        let writer: Box<dyn SegmentWriter> = match self.config.format {
+            RecordFormat::Wav => Box::new(WavSegment::create(&path, self.sample_rate, self.channels)?),
+            RecordFormat::Flac => Box::new(FlacSegment::create(&path, self.sample_rate, self.channels)?),
+            RecordFormat::Opus => {
+                let mut config = OpusConfig::new(self.sample_rate, self.channels);
+                if let Some(bitrate) = self.config.bitrate {
+                    config.bitrate = bitrate;
+                }
+                Box::new(OpusSegment::create(&path, config)?)
+            }
+        };
+        self.current = Some(writer);
+        self.segment_samples = 0;
+        self.since_flush = 0;
+        self.files.push(path);
+        self.prune();
+        Ok(())
+    }
+
+    fn close_current(&mut self) -> Result<()> {
+        match self.current.take() {
+            Some(writer) => writer.finalize(),
+            None => Ok(()),
+        }
+    }
+
+    /// Delete the oldest finished segments beyond `max_files`
+    fn prune(&mut self) {
+        let Some(max_files) = self.config.max_files else { return };
+        while self.files.len() > max_files {
+            let oldest = self.files.remove(0);
+            if let Err(e) = fs::remove_file(&oldest) {
+                log::warn!("[Recorder] Failed to remove {}: {}", oldest.display(), e);
+            }
+        }
+    }
+
+    fn write(&mut self, mut samples: &[i16]) -> Result<()> {
+        let rotate_after = self.config.rotate_seconds.map(|s| s as u64 * self.sample_rate as u64);
+
+        while !samples.is_empty() {
+            if self.current.is_none() {
+                self.open_next()?;
+            }
+
+            // Split chunks on the rotation boundary (whole sample frames only)
+            let room = rotate_after.map_or(u64::MAX, |limit| limit.saturating_sub(self.segment_samples));
+            let frames = (samples.len() / self.channels).min(room.min(usize::MAX as u64) as usize);
+            let (now, rest) = samples.split_at(frames * self.channels);
+
+            if let Some(writer) = self.current.as_mut() {
+                writer.write(now)?;
+            }
+            self.segment_samples += frames as u64;
+            self.since_flush += frames as u64;
+            samples = rest;
+
+            if self.since_flush >= self.sample_rate as u64 {
+                if let Some(writer) = self.current.as_mut() {
+                    writer.flush()?;
+                }
+                self.since_flush = 0;
+            }
+            if rotate_after.is_some_and(|limit| self.segment_samples >= limit) {
+                // The next file is opened lazily so a stop never leaves an empty one
+                self.close_current()?;
+            }
+            if frames == 0 && !samples.is_empty() {
+                // Partial sample frame left over - nothing sensible to write
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One output file
+trait SegmentWriter: Send {
+    fn write(&mut self, samples: &[i16]) -> Result<()>;
+    /// Make everything written so far durable and the header consistent
+    fn flush(&mut self) -> Result<()>;
+    fn finalize(self: Box<Self>) -> Result<()>;
+}
+
+struct WavSegment {
+    writer: hound::WavWriter<BufWriter<File>>,
+}
+
+impl WavSegment {
+    fn create(path: &Path, sample_rate: u32, channels: usize) -> Result<Self> {
+        let spec = hound::WavSpec {
+            channels: channels as u16,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = hound::WavWriter::create(path, spec)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(Self { writer })
+    }
+}
+
+impl SegmentWriter for WavSegment {
+    fn write(&mut self, samples: &[i16]) -> Result<()> {
+        let mut writer = self.writer.get_i16_writer(samples.len() as u32);
+        for &sample in samples {
+            writer.write_sample(sample);
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        // hound rewrites the RIFF/data sizes on flush
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn finalize(self: Box<Self>) -> Result<()> {
+        self.writer.finalize()?;
+        Ok(())
+    }
+}
+
+/// Streaming FLAC: frames are appended as they fill, STREAMINFO is
+/// rewritten in place (it has a fixed size) on flush and finalize
+struct FlacSegment {
+    file: BufWriter<File>,
+    stream_info: StreamInfo,
+    encoder: Verified<flacenc::config::Encoder>,
+    framebuf: FrameBuf,
+    pending: Vec<i32>,
+    channels: usize,
+    frame_number: usize,
+    total_samples: usize,
+}
+
+impl FlacSegment {
+    fn create(path: &Path, sample_rate: u32, channels: usize) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut stream_info = StreamInfo::new(sample_rate as usize, channels, 16)
+            .map_err(|e| anyhow::anyhow!("Invalid FLAC stream: {:?}", e))?;
+        stream_info.set_block_sizes(FLAC_BLOCK_SIZE, FLAC_BLOCK_SIZE)
+            .map_err(|e| anyhow::anyhow!("Invalid FLAC block size: {:?}", e))?;
+        let encoder = flacenc::config::Encoder::default().into_verified()
+            .map_err(|(_, e)| anyhow::anyhow!("Invalid FLAC encoder config: {:?}", e))?;
+        let framebuf = FrameBuf::with_size(channels, FLAC_BLOCK_SIZE)
+            .map_err(|e| anyhow::anyhow!("Invalid FLAC frame buffer: {:?}", e))?;
+
+        let mut segment = Self {
+            file: BufWriter::new(file),
+            stream_info,
+            encoder,
+            framebuf,
+            pending: Vec::with_capacity(FLAC_BLOCK_SIZE * channels),
+            channels,
+            frame_number: 0,
+            total_samples: 0,
+        };
+        segment.write_header()?;
+        Ok(segment)
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        self.stream_info.set_total_samples(self.total_samples);
+        let header = Stream::with_stream_info(self.stream_info.clone());
+        let mut sink = ByteSink::new();
+        header.write(&mut sink).map_err(|e| anyhow::anyhow!("FLAC header: {:?}", e))?;
+
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(sink.as_slice())?;
+        self.file.seek(SeekFrom::End(0))?;
+        Ok(())
+    }
+
+    fn encode_block(&mut self, len: usize) -> Result<()> {
+        let samples_per_channel = len / self.channels;
+        if samples_per_channel != FLAC_BLOCK_SIZE {
+            self.framebuf.resize(samples_per_channel);
+        }
+        self.framebuf.fill_interleaved(&self.pending[..len])
+            .map_err(|e| anyhow::anyhow!("FLAC input: {:?}", e))?;
+
+        let frame = flacenc::encode_fixed_size_frame(&self.encoder, &self.framebuf, self.frame_number, &self.stream_info)
+            .map_err(|e| anyhow::anyhow!("FLAC encode: {:?}", e))?;
+        let mut sink = ByteSink::new();
+        frame.write(&mut sink).map_err(|e| anyhow::anyhow!("FLAC frame: {:?}", e))?;
+        self.file.write_all(sink.as_slice())?;
+
+        self.pending.drain(..len);
+        self.frame_number += 1;
+        self.total_samples += samples_per_channel;
+        Ok(())
+    }
+}
+
+impl SegmentWriter for FlacSegment {
+    fn write(&mut self, samples: &[i16]) -> Result<()> {
+        self.pending.extend(samples.iter().map(|&s| s as i32));
+        let block_len = FLAC_BLOCK_SIZE * self.channels;
+        while self.pending.len() >= block_len {
+            self.encode_block(block_len)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.write_header()?;
+        self.file.flush()?;
+        Ok(())
+    }
+
+    fn finalize(mut self: Box<Self>) -> Result<()> {
+        let remaining = self.pending.len() - self.pending.len() % self.channels;
+        if remaining > 0 {
+            self.encode_block(remaining)?;
+        }
+        self.write_header()?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Ogg Opus in 20ms packets; a page is closed every second of audio so a
+/// truncated file still plays up to the last full second
+struct OpusSegment {
+    file: BufWriter<File>,
+    encoder: OpusEncoder,
+    pending: Vec<i16>,
+    channels: usize,
+    /// Samples per channel encoded so far
+    total_samples: u64,
+}
+
+impl OpusSegment {
+    fn create(path: &Path, config: OpusConfig) -> Result<Self> {
+        let channels = config.channels;
+        let encoder = OpusEncoder::new(config)?;
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(Self {
+            file: BufWriter::new(file),
+            pending: Vec::with_capacity(encoder.frame_samples() * channels),
+            encoder,
+            channels,
+            total_samples: 0,
+        })
+    }
+
+    fn encode_frame(&mut self, end_page: bool) -> Result<()> {
+        let frame_len = self.encoder.frame_samples() * self.channels;
+        let bytes = self.encoder.encode(&self.pending[..frame_len], self.total_samples, end_page)?;
+        self.file.write_all(&bytes)?;
+        self.pending.drain(..frame_len);
+        self.total_samples += self.encoder.frame_samples() as u64;
+        Ok(())
+    }
+}
+
+impl SegmentWriter for OpusSegment {
+    fn write(&mut self, samples: &[i16]) -> Result<()> {
+        self.pending.extend_from_slice(samples);
+        let frame_samples = self.encoder.frame_samples();
+        let frames_per_page = self.encoder.config().sample_rate as u64 / frame_samples as u64;
+        while self.pending.len() >= frame_samples * self.channels {
+            let frame_index = self.total_samples / frame_samples as u64;
+            self.encode_frame((frame_index + 1).is_multiple_of(frames_per_page))?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.file.flush()?;
+        Ok(())
+    }
+
+    fn finalize(mut self: Box<Self>) -> Result<()> {
+        // Pad the last partial frame; the final granule position trims the
+        // padding again on decode
+        let end = self.total_samples + (self.pending.len() / self.channels) as u64;
+        if !self.pending.is_empty() {
+            self.pending.resize(self.encoder.frame_samples() * self.channels, 0);
+            self.encode_frame(false)?;
+        }
+        let bytes = self.encoder.finish(end)?;
+        self.file.write_all(&bytes)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("natively-recorder-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    fn tone(len: usize) -> Vec<i16> {
+        (0..len).map(|n| ((n as f32 * 0.05).sin() * 8000.0) as i16).collect()
+    }
+
+    fn record(config: RecorderConfig, channels: usize, chunks: &[Vec<i16>]) -> Vec<PathBuf> {
+        let recorder = Recorder::start(config, 16000, channels, None).unwrap();
+        for chunk in chunks {
+            recorder.write(chunk.clone());
+        }
+        recorder.stop().unwrap()
+    }
+
+    #[test]
+    fn test_wav_rotation_and_pruning() {
+        let path = temp_path("meeting.wav");
+        let config = RecorderConfig {
+            rotate_seconds: Some(1),
+            max_files: Some(2),
+            ..RecorderConfig::from_options(path.to_str().unwrap(), None, None, None, None).unwrap()
+        };
+
+        // 3.5s of 20ms frames -> 4 segments, oldest 2 pruned
+        let frames: Vec<Vec<i16>> = (0..175).map(|_| tone(320)).collect();
+        let files = record(config, 1, &frames);
+
+        assert_eq!(files.len(), 2);
+        assert!(files[0].ends_with("meeting-002.wav"));
+        assert!(!path.with_file_name("meeting-000.wav").exists());
+
+        let full = hound::WavReader::open(&files[0]).unwrap();
+        assert_eq!(full.duration(), 16000);
+        let last = hound::WavReader::open(&files[1]).unwrap();
+        assert_eq!(last.duration(), 8000);
+    }
+
+    #[test]
+    fn test_wav_header_is_valid_before_finalize() {
+        let path = temp_path("crash.wav");
+        let mut segment = WavSegment::create(&path, 16000, 1).unwrap();
+        segment.write(&tone(32000)).unwrap();
+        segment.flush().unwrap();
+
+        // Simulate a crash: the writer is never finalized
+        std::mem::forget(segment);
+        let reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.duration(), 32000);
+    }
+
+    #[test]
+    fn test_flac_round_trip() {
+        let path = temp_path("meeting.flac");
+        let config = RecorderConfig::from_options(path.to_str().unwrap(), None, None, None, None).unwrap();
+        assert_eq!(config.format, RecordFormat::Flac);
+
+        // Stereo, length not a multiple of the FLAC block size
+        let samples = tone(2 * 10_000);
+        let files = record(config, 2, std::slice::from_ref(&samples));
+
+        let mut reader = claxon::FlacReader::open(&files[0]).unwrap();
+        assert_eq!(reader.streaminfo().channels, 2);
+        assert_eq!(reader.streaminfo().samples, Some(10_000));
+        let decoded: Vec<i16> = reader.samples().map(|s| s.unwrap() as i16).collect();
+        assert_eq!(decoded, samples);
+    }
+
+    #[test]
+    fn test_opus_recording_ends_on_the_last_sample() {
+        let path = temp_path("meeting.opus");
+        let config = RecorderConfig::from_options(path.to_str().unwrap(), None, None, None, None).unwrap();
+        assert_eq!(config.format, RecordFormat::Opus);
+
+        // 1.51s: ends mid-frame, so the last packet is padded and trimmed
+        let samples = crate::opus::tests::speechlike(24_160);
+        let files = record(config, 1, std::slice::from_ref(&samples));
+
+        let bytes = fs::read(&files[0]).unwrap();
+        assert!(bytes.len() * 8 < samples.len() * 2);
+
+        let (head, decoded, granule) = crate::opus::tests::decode_ogg(&bytes);
+        let pre_skip = u16::from_le_bytes([head[10], head[11]]) as u64;
+        assert_eq!(granule, pre_skip + 24_160 * 3);
+        assert!(decoded.len() >= samples.len());
+    }
+}
@@ -13,7 +13,7 @@ impl Resampler {
     pub fn new(input_sample_rate: f64) -> Result<Self> {
         let output_sample_rate = 16000.0;
         
-        println!("[Resampler] Created: {}Hz -> {}Hz (high-quality rubato)", 
+        log::info!("[Resampler] Created: {}Hz -> {}Hz (high-quality rubato)", 
                  input_sample_rate, output_sample_rate);
         
         // FftFixedIn: Fixed input chunk size, variable output size
@@ -67,7 +67,7 @@ impl Resampler {
                     }
                 }
                 Err(e) => {
-                    println!("[Resampler] Process error: {}", e);
+                    log::warn!("[Resampler] Process error: {}", e);
                 }
             }
         }
@@ -0,0 +1,339 @@
+// Pipeline Runner - DSP thread lifecycle shared by the capture classes
+//
+// A source's pipeline runs while anything needs its audio: the JS frame
+// callback (STT) and/or taps (a recording, the stereo mixer). Each can start
+// and stop on its own; taps attach to a running DSP thread, the callback
+// restarts it, and the ring buffer consumer is parked in between so the
+// device never has to be reopened.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use anyhow::Result;
+use ringbuf::HeapCons;
+
+use crate::aec::FarEndReference;
+use crate::delivery::{DeliveredFrame, DeliveryConfig, FrameBatcher};
+use crate::errors::ErrorReporter;
+use crate::metrics::LevelMeter;
+use crate::pipeline::{
+    FormatListener, FrameProcessor, OutputFormat, Pipeline, PipelineConfig, PipelineInput, PipelineTap, TapRegistry,
+};
+use crate::recorder::{RecordSource, Recorder, RecorderConfig};
+use crate::stages::{self, LevelStage, SourceKind, StageMetrics, StageOptions};
+
+/// Receives frames (or batches of them) on the DSP thread, converted per
+/// the `DeliveryConfig`; the capture classes queue them for the JS frame
+/// callback
+pub type FrameCallback = Box<dyn FnMut(DeliveredFrame) + Send>;
+
+/// Backend feeding the pipeline; shared with the device monitor
+pub type SourceInput = Arc<PipelineInput<HeapCons<f32>>>;
+
+/// Input format of the parked consumer
+#[derive(Clone)]
+struct InputFormat {
+    /// Current device and rate, updated by the backend
+    input: SourceInput,
+    channels: usize,
+}
+
+impl InputFormat {
+    fn sample_rate(&self) -> f64 {
+        self.input.sample_rate() as f64
+    }
+}
+
+pub struct PipelineRunner {
+    label: &'static str,
+    source: SourceKind,
+    stages: Vec<StageOptions>,
+    output: OutputFormat,
+    delivery: DeliveryConfig,
+    /// Published to (system audio) or cancelled against (microphone `aec`)
+    echo_reference: Option<Arc<FarEndReference>>,
+    levels: Arc<LevelMeter>,
+    metrics: StageMetrics,
+    taps: Arc<TapRegistry>,
+    recorder: Option<Recorder>,
+    /// Whether the JS frame callback is attached
+    streaming: bool,
+    /// Receives device rate changes while streaming (the JS event callback)
+    format_listener: Option<FormatListener>,
+    /// Flushed by the DSP thread (errors flagged from audio callbacks)
+    errors: Option<Arc<ErrorReporter>>,
+    stop_signal: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<HeapCons<f32>>>,
+    /// Consumer waiting for the next DSP thread
+    consumer: Option<HeapCons<f32>>,
+    format: Option<InputFormat>,
+}
+
+impl PipelineRunner {
+    pub fn new(label: &'static str, source: SourceKind, stages: Vec<StageOptions>, output: OutputFormat) -> Self {
+        Self {
+            label,
+            source,
+            stages,
+            output,
+            delivery: DeliveryConfig::default(),
+            echo_reference: None,
+            levels: Arc::new(LevelMeter::new(output.frame_ms)),
+            metrics: StageMetrics::default(),
+            taps: Arc::new(TapRegistry::default()),
+            recorder: None,
+            streaming: false,
+            format_listener: None,
+            errors: None,
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            thread: None,
+            consumer: None,
+            format: None,
+        }
+    }
+
+    /// Report errors the source flags from its audio callback
+    pub fn with_errors(mut self, errors: Arc<ErrorReporter>) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    /// Deliver frames to the callback as `delivery` says
+    pub fn with_delivery(mut self, delivery: DeliveryConfig) -> Self {
+        self.delivery = delivery;
+        self
+    }
+
+    /// Publish to, or cancel against, `reference` (see `build_stages`)
+    pub fn with_echo_reference(mut self, reference: Arc<FarEndReference>) -> Self {
+        self.echo_reference = Some(reference);
+        self
+    }
+
+    /// Change the echo reference; used by the next DSP thread
+    pub fn set_echo_reference(&mut self, reference: Option<Arc<FarEndReference>>) {
+        self.echo_reference = reference;
+    }
+
+    pub fn echo_reference(&self) -> Option<Arc<FarEndReference>> {
+        self.echo_reference.clone()
+    }
+
+    pub fn levels(&self) -> &Arc<LevelMeter> {
+        &self.levels
+    }
+
+    pub fn metrics(&self) -> &StageMetrics {
+        &self.metrics
+    }
+
+    /// Whether the STT callback or any tap still needs the source
+    pub fn is_active(&self) -> bool {
+        self.streaming || !self.taps.is_empty()
+    }
+
+    pub fn output(&self) -> &OutputFormat {
+        &self.output
+    }
+
+    fn pipeline_config(&self, format: &InputFormat) -> PipelineConfig {
+        PipelineConfig::new(self.label, format.sample_rate(), format.channels).with_output(&self.output)
+    }
+
+    /// The attached backend, for the device monitor
+    pub fn input(&self) -> Option<SourceInput> {
+        self.format.as_ref().map(|format| format.input.clone())
+    }
+
+    /// Whether a consumer is attached (running or parked)
+    pub fn has_input(&self) -> bool {
+        self.format.is_some()
+    }
+
+    /// Hand over the source's ring buffer consumer and its device rate
+    pub fn attach_input(&mut self, consumer: HeapCons<f32>, input: SourceInput, channels: usize) {
+        self.halt();
+        self.consumer = Some(consumer);
+        self.format = Some(InputFormat { input, channels: channels.max(1) });
+    }
+
+    /// Stop the DSP thread and drop the consumer (the source is closing)
+    pub fn detach_input(&mut self) {
+        self.halt();
+        self.consumer = None;
+        self.format = None;
+    }
+
+    /// Start delivering frames to JS, restarting the DSP thread if a
+    /// recording already runs it
+    pub fn start_streaming(
+        &mut self,
+        callback: FrameCallback,
+        vad_stage: Option<Box<dyn FrameProcessor>>,
+        format_listener: Option<FormatListener>,
+    ) -> Result<()> {
+        self.halt();
+        self.streaming = true;
+        self.format_listener = format_listener;
+        if let Err(e) = self.spawn(Some(callback), vad_stage) {
+            self.streaming = false;
+            self.format_listener = None;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Stop delivering frames; attached taps keep receiving audio
+    pub fn stop_streaming(&mut self) -> Result<()> {
+        self.halt();
+        self.streaming = false;
+        self.format_listener = None;
+        if self.is_active() {
+            self.spawn(None, None)?;
+        }
+        Ok(())
+    }
+
+    /// Attach a tap, starting the DSP thread if nothing else runs it
+    pub fn attach_tap(&mut self, tap: Arc<dyn PipelineTap>) -> Result<()> {
+        if self.format.is_none() {
+            return Err(anyhow::anyhow!("Capture source is not open"));
+        }
+        self.taps.add(tap.clone());
+        if self.thread.is_none() {
+            if let Err(e) = self.spawn(None, None) {
+                self.taps.remove(&tap);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Detach a tap, stopping the DSP thread once nothing needs it
+    pub fn detach_tap(&mut self, tap: &Arc<dyn PipelineTap>) {
+        self.taps.remove(tap);
+        if !self.is_active() {
+            self.halt();
+        }
+    }
+
+    /// Tee the pipeline to a file, starting the DSP thread if needed
+    pub fn start_recording(&mut self, config: RecorderConfig) -> Result<()> {
+        let format = self.format.clone().ok_or_else(|| anyhow::anyhow!("Capture source is not open"))?;
+        if self.recorder.is_some() {
+            return Err(anyhow::anyhow!("A recording is already running"));
+        }
+
+        let (sample_rate, channels) = match config.source {
+            RecordSource::Processed => {
+                let config = self.pipeline_config(&format);
+                (config.output_sample_rate as u32, config.channels)
+            }
+            // Raw files are written at the device rate when recording started
+            RecordSource::Raw => (format.sample_rate() as u32, format.channels),
+        };
+        let recorder = Recorder::start(config, sample_rate, channels, self.errors.clone())?;
+        self.attach_tap(recorder.tap())?;
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    /// Finalize the recording; returns the files written
+    pub fn stop_recording(&mut self) -> Result<Vec<PathBuf>> {
+        let recorder = self.recorder.take()
+            .ok_or_else(|| anyhow::anyhow!("No recording is running"))?;
+        self.detach_tap(&recorder.tap());
+        recorder.stop()
+    }
+
+    fn spawn(&mut self, callback: Option<FrameCallback>, vad_stage: Option<Box<dyn FrameProcessor>>) -> Result<()> {
+        let format = self.format.clone().ok_or_else(|| anyhow::anyhow!("Capture source is not open"))?;
+        // A device switched while parked replaces the parked consumer
+        if let Some((replacement, _)) = format.input.take_pending() {
+            self.consumer = Some(replacement);
+        }
+        let consumer = self.consumer.take()
+            .ok_or_else(|| anyhow::anyhow!("Failed to get consumer"))?;
+
+        let config = self.pipeline_config(&format);
+        let (stages, metrics) = match stages::build_stages(
+            &self.stages,
+            self.source,
+            config.output_sample_rate as u32,
+            config.channels,
+            self.echo_reference.as_ref(),
+        ) {
+            Ok(built) => built,
+            Err(e) => {
+                self.consumer = Some(consumer);
+                return Err(e);
+            }
+        };
+
+        // Observers (levels, VAD) see every frame, so they run before
+        // anything can drop one
+        let mut observers: Vec<Box<dyn FrameProcessor>> = vec![Box::new(LevelStage::new(self.levels.clone()))];
+        observers.extend(vad_stage);
+
+        let pipeline = Pipeline::new(config)
+            .with_stages(observers)
+            .with_stages(stages)
+            .with_taps(self.taps.clone())
+            .with_format_listener(self.format_listener.clone())
+            .with_errors(self.errors.clone());
+
+        self.stop_signal.store(false, Ordering::SeqCst);
+        let sink = callback.map(|callback| FrameBatcher::new(self.output, self.delivery, callback));
+        let handle = pipeline.spawn(consumer, format.input.clone(), self.stop_signal.clone(), sink);
+        self.thread = Some(handle);
+        self.metrics = metrics;
+        Ok(())
+    }
+
+    /// Stop the DSP thread and park its consumer
+    fn halt(&mut self) {
+        self.stop_signal.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.thread.take() {
+            match handle.join() {
+                Ok(consumer) => self.consumer = Some(consumer),
+                Err(_) => log::error!("[{}] DSP thread panicked", self.label),
+            }
+        }
+    }
+}
+
+impl Drop for PipelineRunner {
+    fn drop(&mut self) {
+        self.halt();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ringbuf::{traits::Split, HeapRb};
+
+    use crate::mixer::Mixer;
+    use crate::pipeline::fixed_rate;
+
+    #[test]
+    fn test_runner_goes_idle_when_mixer_lane_is_detached() {
+        let (_producer, consumer) = HeapRb::<f32>::new(4096).split();
+        let mut runner = PipelineRunner::new("Test", SourceKind::Microphone, Vec::new(), OutputFormat::default());
+        runner.attach_input(consumer, Arc::new(PipelineInput::new(fixed_rate(48000))), 1);
+
+        let mixer = Mixer::start(None, None).unwrap();
+        let lanes = mixer.lanes();
+        runner.attach_tap(lanes[0].clone()).unwrap();
+        assert!(runner.is_active() && runner.thread.is_some());
+
+        // What StereoMixer::stop does for each capture
+        mixer.stop().unwrap();
+        runner.detach_tap(&lanes[0]);
+        assert!(!runner.is_active());
+        assert!(runner.thread.is_none(), "DSP thread still running");
+        assert!(runner.consumer.is_some(), "consumer not parked");
+    }
+}
@@ -9,9 +9,16 @@
 // LATENCY BUDGET:
 // - Speech onset: 0ms delay (immediate)
 // - Hangover: Only affects AFTER speech ends (no latency impact)
+//
+// TIMING: as a pipeline stage, hangover and keepalives run on stream time
+// (frame sample offsets), so a file played faster than real time is gated
+// exactly like a live device
 
 use std::time::{Duration, Instant};  // Added for timing
 
+use crate::pipeline::{Frame, FrameProcessor, StageOutput};
+use crate::speech_detector::{RmsDetector, SpeechDetector};
+
 /// Configuration for silence suppression
 /// Optimized for low latency
 pub struct SilenceSuppressionConfig {
@@ -60,9 +67,16 @@ impl SilenceSuppressionConfig {
 /// Silence suppression state machine
 pub struct SilenceSuppressor {
     config: SilenceSuppressionConfig,
+    detector: Box<dyn SpeechDetector>,
+    /// Rate of the frames' sample offsets, for stream time
+    sample_rate: u32,
     state: SuppressionState,
-    last_speech_time: Instant,
-    last_keepalive_time: Instant,
+    /// Stream time of the last speech frame and keepalive; creation is time
+    /// zero, after a reset they are taken from the next frame
+    last_speech_time: Option<Duration>,
+    last_keepalive_time: Option<Duration>,
+    /// Clock for `process`, which has no stream position
+    started: Instant,
     frames_sent: u64,
     frames_suppressed: u64,
 }
@@ -86,18 +100,29 @@ pub enum FrameAction {
 }
 
 impl SilenceSuppressor {
-    pub fn new(config: SilenceSuppressionConfig) -> Self {
-        let now = Instant::now();
-        println!("[SilenceSuppressor] Created with threshold={}, hangover={}ms, keepalive={}ms",
+    /// Gate on the fixed RMS threshold from `config`; frames run at
+    /// `sample_rate`
+    pub fn new(config: SilenceSuppressionConfig, sample_rate: u32) -> Self {
+        let detector = Box::new(RmsDetector::new(config.speech_threshold_rms));
+        Self::with_detector(config, detector, sample_rate)
+    }
+
+    /// Gate on a custom detector (`speech_threshold_rms` is then unused)
+    pub fn with_detector(config: SilenceSuppressionConfig, detector: Box<dyn SpeechDetector>, sample_rate: u32) -> Self {
+        log::info!("[SilenceSuppressor] Created with detector={}, threshold={}, hangover={}ms, keepalive={}ms",
+            detector.name(),
             config.speech_threshold_rms,
             config.speech_hangover.as_millis(),
             config.silence_keepalive_interval.as_millis()
         );
         Self {
             config,
+            detector,
+            sample_rate,
             state: SuppressionState::Active, // Start in active to not miss first words
-            last_speech_time: now,
-            last_keepalive_time: now,
+            last_speech_time: Some(Duration::ZERO),
+            last_keepalive_time: Some(Duration::ZERO),
+            started: Instant::now(),
             frames_sent: 0,
             frames_suppressed: 0,
         }
@@ -106,14 +131,22 @@ impl SilenceSuppressor {
     /// Process a frame and determine what to do with it
     /// CRITICAL: Speech frames are NEVER delayed
     pub fn process(&mut self, frame: &[i16]) -> FrameAction {
-        let now = Instant::now();
-        let rms = calculate_rms(frame);
-        let has_speech = rms >= self.config.speech_threshold_rms;
+        let now = self.started.elapsed();
+        self.process_at(frame, now)
+    }
+
+    /// Same as `process`, with the caller supplying the clock (stream time
+    /// derived from sample offsets) so hangover and keepalives are
+    /// sample-accurate whatever the playback speed
+    pub fn process_at(&mut self, frame: &[i16], now: Duration) -> FrameAction {
+        let has_speech = self.detector.analyze(frame).is_speech();
+        let last_speech_time = *self.last_speech_time.get_or_insert(now);
+        let last_keepalive_time = *self.last_keepalive_time.get_or_insert(now);
         
         // ALWAYS check for speech first - immediate response
         if has_speech {
             self.state = SuppressionState::Active;
-            self.last_speech_time = now;
+            self.last_speech_time = Some(now);
             self.frames_sent += 1;
             return FrameAction::Send(frame.to_vec());
         }
@@ -122,7 +155,7 @@ impl SilenceSuppressor {
         match self.state {
             SuppressionState::Active | SuppressionState::Hangover => {
                 // Check if hangover period has elapsed
-                if now.duration_since(self.last_speech_time) > self.config.speech_hangover {
+                if now.saturating_sub(last_speech_time) > self.config.speech_hangover {
                     self.state = SuppressionState::Suppressed;
                     // Fall through to check keepalive
                 } else {
@@ -138,8 +171,8 @@ impl SilenceSuppressor {
         }
         
         // In suppressed state - check if time for keepalive
-        if now.duration_since(self.last_keepalive_time) >= self.config.silence_keepalive_interval {
-            self.last_keepalive_time = now;
+        if now.saturating_sub(last_keepalive_time) >= self.config.silence_keepalive_interval {
+            self.last_keepalive_time = Some(now);
             self.frames_sent += 1;
             FrameAction::SendSilence
         } else {
@@ -160,27 +193,35 @@ impl SilenceSuppressor {
     
     /// Reset state (e.g., when meeting ends)
     pub fn reset(&mut self) {
-        let now = Instant::now();
         self.state = SuppressionState::Active;
-        self.last_speech_time = now;
-        self.last_keepalive_time = now;
+        self.last_speech_time = None;
+        self.last_keepalive_time = None;
+        self.detector.reset();
     }
 }
 
-/// Calculate RMS of i16 samples efficiently
-fn calculate_rms(samples: &[i16]) -> f32 {
-    if samples.is_empty() {
-        return 0.0;
+/// Pipeline stage: keepalive frames are zeroed in place, suppressed frames
+/// dropped, timed by the frames' sample offsets
+impl FrameProcessor for SilenceSuppressor {
+    fn name(&self) -> &'static str {
+        "suppression"
+    }
+
+    fn process(&mut self, frame: &mut Frame) -> StageOutput {
+        let stream_time = Duration::from_micros(frame.sample_offset * 1_000_000 / self.sample_rate.max(1) as u64);
+        match self.process_at(&frame.samples, stream_time) {
+            FrameAction::Send(_) => StageOutput::Forward,
+            FrameAction::SendSilence => {
+                frame.samples.iter_mut().for_each(|s| *s = 0);
+                StageOutput::Forward
+            }
+            FrameAction::Suppress => StageOutput::Drop,
+        }
+    }
+
+    fn reset(&mut self) {
+        SilenceSuppressor::reset(self);
     }
-    
-    // Sample every 4th sample for speed (320/4 = 80 samples is plenty for RMS)
-    let sum_of_squares: f64 = samples.iter()
-        .step_by(4)
-        .map(|&s| (s as f64) * (s as f64))
-        .sum();
-    
-    let count = (samples.len() + 3) / 4;
-    (sum_of_squares / count as f64).sqrt() as f32
 }
 
 /// Generate a silence frame of given size
@@ -191,10 +232,20 @@ pub fn generate_silence_frame(size: usize) -> Vec<i16> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ringbuf::{traits::Split, HeapRb};
+    use std::fs;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use crate::audio_config::{FRAME_SAMPLES, RING_BUFFER_SAMPLES, SAMPLE_RATE};
+    use crate::channel_mix::{ChannelMixer, ChannelPolicy};
+    use crate::file_source::{decode_file, DecodedAudio, FilePlayer, Playback};
+    use crate::pipeline::{fixed_rate, FrameSink, Pipeline, PipelineConfig, PipelineInput};
     
     #[test]
     fn test_speech_immediate() {
-        let mut suppressor = SilenceSuppressor::new(SilenceSuppressionConfig::default());
+        let mut suppressor = SilenceSuppressor::new(SilenceSuppressionConfig::default(), 16000);
         
         // Loud frame should be sent immediately
         let loud_frame: Vec<i16> = vec![500; 320];
@@ -211,10 +262,78 @@ mod tests {
             speech_threshold_rms: 100.0,
             speech_hangover: Duration::from_millis(0),
             silence_keepalive_interval: Duration::from_millis(50),
-        });
+        }, 16000);
         
         let silent_frame: Vec<i16> = vec![0; 320];
         let action = suppressor.process(&silent_frame);
         assert!(matches!(action, FrameAction::SendSilence | FrameAction::Suppress));
     }
+
+    /// Forwarded frames as (sample offset, zeroed keepalive) of a file
+    /// played through a suppressing pipeline at `speed`
+    fn gated_frames(audio: &Arc<DecodedAudio>, speed: f64) -> Vec<(u64, bool)> {
+        struct Collect(Arc<Mutex<Vec<(u64, bool)>>>);
+        impl FrameSink for Collect {
+            fn frame(&mut self, frame: Frame) {
+                let zeroed = frame.samples.iter().all(|&s| s == 0);
+                self.0.lock().unwrap().push((frame.sample_offset, zeroed));
+            }
+        }
+
+        let (producer, consumer) = HeapRb::<f32>::new(RING_BUFFER_SAMPLES).split();
+        let mixer = ChannelMixer::new(ChannelPolicy::Downmix, 1).unwrap();
+        let suppressor = SilenceSuppressor::new(SilenceSuppressionConfig::for_microphone(), SAMPLE_RATE);
+        let pipeline = Pipeline::new(PipelineConfig::new("test", SAMPLE_RATE as f64, 1))
+            .with_stage(Box::new(suppressor));
+
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let input = Arc::new(PipelineInput::new(fixed_rate(SAMPLE_RATE)));
+        let handle = pipeline.spawn(consumer, input, stop.clone(), Collect(frames.clone()));
+        let _player = FilePlayer::start(audio.clone(), mixer, producer, Playback { speed, repeat: false }, Box::new(|| {}));
+
+        // The file ends on speech, so its last frame is always forwarded
+        let last = (audio.samples.len() - FRAME_SAMPLES) as u64;
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !frames.lock().unwrap().iter().any(|&(offset, _)| offset == last) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        stop.store(true, Ordering::SeqCst);
+        handle.join().unwrap();
+        let frames = frames.lock().unwrap().clone();
+        frames
+    }
+
+    #[test]
+    fn test_gaps_do_not_depend_on_playback_speed() {
+        let dir = std::env::temp_dir().join(format!("natively-suppression-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("speech.wav");
+
+        // 200ms tone, 800ms silence, 200ms tone; the tones stop short of
+        // the frame edges so resampler chunking can't move them across one
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for n in 0..SAMPLE_RATE as usize * 6 / 5 {
+            let speaking = !(3200 - 16..16000 + 16).contains(&n);
+            let sample = if speaking { ((n as f32 * 0.17).sin() * 8000.0) as i16 } else { 0 };
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        let audio = Arc::new(decode_file(&path).unwrap());
+
+        let realtime = gated_frames(&audio, 1.0);
+        let fast = gated_frames(&audio, 0.0);
+        fs::remove_dir_all(&dir).unwrap();
+
+        // The silence is gated down to keepalives 100ms of stream time apart
+        assert!(realtime.len() < audio.samples.len() / FRAME_SAMPLES - 20, "{:?}", realtime);
+        assert!(realtime.windows(2).any(|w| w[1].0 - w[0].0 == 1600 && w[1].1), "{:?}", realtime);
+        assert_eq!(fast, realtime);
+    }
 }
@@ -5,6 +5,9 @@ use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 use std::task::{Waker};
 use ca::aggregate_device_keys as agg_keys;
+use crate::backend::{BackendCapabilities, CaptureBackend};
+use crate::errors::{ErrorCode, ErrorReporter};
+use crate::pipeline::{DeviceRate, RateHandle};
 
 struct WakerState {
     waker: Option<Waker>,
@@ -15,9 +18,12 @@ struct Ctx {
     format: arc::R<av::AudioFormat>,
     producer: HeapProd<f32>,
     waker_state: Arc<Mutex<WakerState>>,
-    current_sample_rate: Arc<AtomicU32>,
+    current_sample_rate: RateHandle,
+    /// Samples pushed into the ring buffer so far (rate change positions)
+    pushed: u64,
     consecutive_drops: Arc<AtomicU32>,
     should_terminate: Arc<AtomicBool>,
+    errors: Arc<ErrorReporter>,
 }
 
 pub struct SpeakerInput {
@@ -40,7 +46,7 @@ impl SpeakerInput {
         };
 
         let output_uid = output_device.uid()?;
-        println!("[CoreAudioTap] Target device UID: {}", output_uid);
+        log::info!("[CoreAudioTap] Target device UID: {}", output_uid);
 
         // 2. Create global tap
         let sub_device = cf::DictionaryOf::with_keys_values(
@@ -52,7 +58,7 @@ impl SpeakerInput {
         // NOTE: Using mono tap. If audio quality issues persist, revisit this.
         let tap_desc = ca::TapDesc::with_mono_global_tap_excluding_processes(&ns::Array::new());
         let tap = tap_desc.create_process_tap()?;
-        println!("[CoreAudioTap] Tap created: {:?}", tap.uid());
+        log::info!("[CoreAudioTap] Tap created: {:?}", tap.uid());
 
         let sub_tap = cf::DictionaryOf::with_keys_values(
             &[ca::sub_device_keys::uid()],
@@ -104,13 +110,11 @@ impl SpeakerInput {
         ) -> os::Status {
             let ctx = ctx.unwrap();
 
-            // Update sample rate if needed
-            ctx.current_sample_rate.store(
-                device
-                    .actual_sample_rate()
-                    .unwrap_or(ctx.format.absd().sample_rate) as u32,
-                Ordering::Release,
-            );
+            // Tag a rate change with the first sample pushed at the new rate
+            let rate = device
+                .actual_sample_rate()
+                .unwrap_or(ctx.format.absd().sample_rate) as u32;
+            ctx.current_sample_rate.set(rate, ctx.pushed);
 
             // Extract audio data
             if let Some(view) =
@@ -138,16 +142,18 @@ impl SpeakerInput {
         let agg_device = ca::AggregateDevice::with_desc(&self.agg_desc)?;
         let proc_id = agg_device.create_io_proc_id(proc, Some(ctx))?;
         let started_device = ca::device_start(agg_device, Some(proc_id))?;
-        println!("[CoreAudioTap] Aggregate device started successfully");
+        log::info!("[CoreAudioTap] Aggregate device started successfully");
 
         Ok(started_device)
     }
 
-    pub fn stream(self) -> SpeakerStream {
-         let asbd = self.tap.asbd().expect("Failed to get ASBD from tap");
+    pub fn stream(self, errors: Arc<ErrorReporter>) -> Result<SpeakerStream> {
+        let asbd = self.tap.asbd()
+            .map_err(|e| anyhow::anyhow!("Failed to get ASBD from tap: {:?}", e))?;
         
-        let format = av::AudioFormat::with_asbd(&asbd).unwrap();
-        println!("[CoreAudioTap] Format: {}Hz, {}ch", asbd.sample_rate, asbd.channels_per_frame);
+        let format = av::AudioFormat::with_asbd(&asbd)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported tap format"))?;
+        log::info!("[CoreAudioTap] Format: {}Hz, {}ch", asbd.sample_rate, asbd.channels_per_frame);
 
         let buffer_size = 1024 * 128; // ~340ms at 48k
         let rb = HeapRb::<f32>::new(buffer_size);
@@ -158,61 +164,49 @@ impl SpeakerInput {
             has_data: false,
         }));
 
-        let current_sample_rate = Arc::new(AtomicU32::new(asbd.sample_rate as u32));
+        let current_sample_rate = Arc::new(DeviceRate::new(asbd.sample_rate as u32));
 
         let mut ctx = Box::new(Ctx {
             format,
             producer,
             waker_state: waker_state.clone(),
             current_sample_rate: current_sample_rate.clone(),
+            pushed: 0,
             consecutive_drops: Arc::new(AtomicU32::new(0)),
             should_terminate: Arc::new(AtomicBool::new(false)),
+            errors,
         });
 
         // Start!
-        let device = self.start_device(&mut ctx).expect("Failed to start CoreAudio tap");
+        let device = self.start_device(&mut ctx)
+            .map_err(|e| anyhow::anyhow!("Failed to start CoreAudio tap: {}", e))?;
 
-        SpeakerStream {
+        Ok(SpeakerStream {
             consumer: Some(consumer),
             _device: device,
             _ctx: ctx,
             _tap: self.tap,
             current_sample_rate,
-        }
+        })
     }
 }
 
 fn process_audio_data(ctx: &mut Ctx, data: &[f32]) {
-    // Debug Logging for signal analysis
-    static mut LOG_COUNTER: usize = 0;
-    unsafe {
-        LOG_COUNTER += 1;
-        if LOG_COUNTER % 100 == 0 { // Log every ~100th callback (approx every 1-2 sec)
-            let mut min = 0.0;
-            let mut max = 0.0;
-            let mut sum_sq = 0.0;
-            for &s in data {
-                if s < min { min = s; }
-                if s > max { max = s; }
-                sum_sq += s * s;
-            }
-            let rms = (sum_sq / data.len() as f32).sqrt();
-            println!("[CoreAudioTap] Chunk: {} samples, Min: {:.4}, Max: {:.4}, RMS: {:.4}", data.len(), min, max, rms);
-        }
-    }
-
     // Processing Logic
     let buffer_size = data.len();
     let pushed = ctx.producer.push_slice(data);
+    ctx.pushed += pushed as u64;
 
     if pushed < buffer_size {
         let consecutive = ctx.consecutive_drops.fetch_add(1, Ordering::AcqRel) + 1;
+        // Reported by the DSP thread; no logging on the IO proc
         if consecutive == 25 {
-            eprintln!("Warning: Audio buffer experiencing drops - system may be overloaded");
+            ctx.errors.flag(ErrorCode::Overflow, false);
         }
         if consecutive > 50 {
-            eprintln!("Critical: Audio buffer overflow - capture stopping");
-            ctx.should_terminate.store(true, Ordering::Release);
+            if !ctx.should_terminate.swap(true, Ordering::AcqRel) {
+                ctx.errors.flag(ErrorCode::Overflow, true);
+            }
             return;
         }
     } else {
@@ -239,15 +233,40 @@ pub struct SpeakerStream {
     _device: ca::hardware::StartedDevice<ca::AggregateDevice>,
     _ctx: Box<Ctx>,
     _tap: ca::TapGuard,
-    current_sample_rate: Arc<AtomicU32>,
+    current_sample_rate: RateHandle,
 }
 
-impl SpeakerStream {
-    pub fn sample_rate(&self) -> u32 {
-        self.current_sample_rate.load(Ordering::Acquire)
+impl CaptureBackend for SpeakerStream {
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            name: "coreAudioTap",
+            pausable: false,
+            variable_rate: true,
+            selects_device: true,
+        }
+    }
+
+    /// The aggregate device is started by `SpeakerInput::stream`
+    fn start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Updated by the IO proc before each buffer is pushed, tagged with the
+    /// first sample at the new rate, so a switch (e.g. output to AirPods)
+    /// takes effect exactly at that sample however much is still buffered
+    fn sample_rate(&self) -> RateHandle {
+        self.current_sample_rate.clone()
+    }
+
+    fn channels(&self) -> usize {
+        1
     }
 
-    pub fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+    fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
         self.consumer.take()
     }
 }
@@ -1,4 +1,5 @@
-// removed unused anyhow::Result
+// System audio backends, one per OS, all opened through `open`
+// (see `backend::OutputBackend` for the names accepted by the JS option)
 
 #[cfg(target_os = "macos")]
 mod core_audio;
@@ -7,35 +8,54 @@ mod sck;
 #[cfg(target_os = "macos")]
 pub mod macos;
 #[cfg(target_os = "macos")]
-#[cfg(target_os = "macos")]
-#[cfg(target_os = "macos")]
-pub use macos::SpeakerInput;
-#[cfg(target_os = "macos")]
-pub use macos::SpeakerStream;
+pub use macos::open;
 #[cfg(target_os = "macos")]
 pub use macos::list_output_devices;
+#[cfg(target_os = "macos")]
+pub use macos::default_output_device;
 
 #[cfg(target_os = "windows")]
 pub mod windows;
 #[cfg(target_os = "windows")]
-pub use windows::SpeakerInput;
+pub use windows::open;
 #[cfg(target_os = "windows")]
 pub use windows::list_output_devices;
+#[cfg(target_os = "windows")]
+pub use windows::default_output_device;
+
+#[cfg(target_os = "linux")]
+pub mod linux;
+#[cfg(target_os = "linux")]
+pub use linux::open;
+#[cfg(target_os = "linux")]
+pub use linux::list_output_devices;
+#[cfg(target_os = "linux")]
+pub use linux::default_output_device;
 
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
 pub mod fallback {
     use anyhow::Result;
-    pub struct SpeakerInput;
-    impl SpeakerInput {
-        pub fn new(_device_id: Option<String>) -> Result<Self> {
-            Err(anyhow::anyhow!("Unsupported platform"))
-        }
+    use std::sync::Arc;
+    use crate::backend::{CaptureBackend, OutputBackend};
+    use crate::errors::ErrorReporter;
+
+    pub fn open(
+        _backend: OutputBackend,
+        _device_id: Option<String>,
+        _errors: Arc<ErrorReporter>,
+    ) -> Result<Box<dyn CaptureBackend>> {
+        Err(anyhow::anyhow!("Unsupported platform"))
     }
     pub fn list_output_devices() -> Result<Vec<(String, String)>> {
         Ok(Vec::new())
     }
+    pub fn default_output_device() -> Option<String> {
+        None
+    }
 }
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
-pub use fallback::SpeakerInput;
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub use fallback::open;
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
 pub use fallback::list_output_devices;
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub use fallback::default_output_device;
@@ -6,6 +6,12 @@ use cidre::{arc, sc, cm, dispatch, ns, objc, define_obj_type};
 use cidre::sc::StreamOutput;
 use ringbuf::{traits::{Producer, Split}, HeapProd, HeapRb, HeapCons};
 
+use std::sync::Arc;
+
+use crate::backend::{BackendCapabilities, CaptureBackend};
+use crate::errors::{ErrorCode, ErrorReporter};
+use crate::pipeline::{fixed_rate, RateHandle};
+
 // keep for compatibility
 use cidre::core_audio as ca;
 
@@ -26,8 +32,15 @@ pub fn list_output_devices() -> Result<Vec<(String, String)>> {
     Ok(list)
 }
 
+/// UID of the current default output device
+pub fn default_output_device() -> Option<String> {
+    let device = ca::System::default_output_device().ok()?;
+    device.uid().ok().map(|uid| uid.to_string())
+}
+
 pub struct AudioHandlerInner {
     producer: HeapProd<f32>,
+    errors: Arc<ErrorReporter>,
 }
 
 define_obj_type!(
@@ -73,13 +86,16 @@ impl sc::stream::OutputImpl for AudioHandler {
                         unsafe {
                             let slice = std::slice::from_raw_parts(data_ptr, float_count);
                             // Push audio to ring buffer
-                            let _pushed = inner.producer.push_slice(slice);
+                            if inner.producer.push_slice(slice) < slice.len() {
+                                inner.errors.flag(ErrorCode::Overflow, false);
+                            }
                         }
                     }
                 }
             }
-            Err(e) => {
-                println!("[SystemAudio-SCK] Failed to get audio buffer: {:?}", e);
+            Err(_) => {
+                // No logging here: this runs on the capture queue
+                inner.errors.flag(ErrorCode::StreamError, false);
             }
         }
     }
@@ -92,7 +108,7 @@ pub struct SpeakerInput {
 
 impl SpeakerInput {
     pub fn new(_device_id: Option<String>) -> Result<Self> {
-        println!("[SpeakerInput] Initializing ScreenCaptureKit audio capture...");
+        log::info!("[SpeakerInput] Initializing ScreenCaptureKit audio capture...");
         
         // NOTE: ScreenCaptureKit captures ALL system audio, not per-device
         // The device_id parameter is ignored
@@ -112,7 +128,7 @@ impl SpeakerInput {
         
         sc::ShareableContent::current_with_ch(move |content_opt, error_opt| {
             if let Some(e) = error_opt {
-                println!("[SpeakerInput] ERROR: ScreenCaptureKit access denied: {:?}", e);
+                log::error!("[SpeakerInput] ERROR: ScreenCaptureKit access denied: {:?}", e);
                 error_clone.store(true, Ordering::SeqCst);
             } else if let Some(c) = content_opt {
                 // Retain the content
@@ -130,7 +146,7 @@ impl SpeakerInput {
         }
         
         if content_error.load(Ordering::SeqCst) {
-            println!("[SpeakerInput] Please grant Screen Recording permission in System Settings > Privacy & Security");
+            log::error!("[SpeakerInput] Please grant Screen Recording permission in System Settings > Privacy & Security");
             return Err(anyhow::anyhow!("ScreenCaptureKit access denied"));
         }
         
@@ -143,7 +159,7 @@ impl SpeakerInput {
         }
         
         let display = &displays[0];
-        println!("[SpeakerInput] Using display: {}x{}", display.width(), display.height());
+        log::info!("[SpeakerInput] Using display: {}x{}", display.width(), display.height());
         
         // Create filter for desktop audio capture (entire display, no excluded windows)
         let empty_windows = ns::Array::<sc::Window>::new();
@@ -162,7 +178,7 @@ impl SpeakerInput {
         cfg.set_height(2);
         cfg.set_minimum_frame_interval(cm::Time::new(1, 1)); // 1 FPS
         
-        println!("[SpeakerInput] Config: 48kHz mono, queue_depth=8");
+        log::info!("[SpeakerInput] Config: 48kHz mono, queue_depth=8");
         
         Ok(Self { cfg, filter })
     }
@@ -171,7 +187,8 @@ impl SpeakerInput {
         self.cfg.sample_rate() as f64
     }
 
-    pub fn stream(self) -> SpeakerStream {
+    /// Add the audio output and wait for the stream to start
+    pub fn stream(self, errors: Arc<ErrorReporter>) -> Result<SpeakerStream> {
         let buffer_size = 1024 * 128;
         let rb = HeapRb::<f32>::new(buffer_size);
         let (producer, consumer) = rb.split();
@@ -179,19 +196,18 @@ impl SpeakerInput {
         let stream = sc::Stream::new(&self.filter, &self.cfg);
         
         // Initialize handler
-        let inner = AudioHandlerInner { producer };
+        let inner = AudioHandlerInner { producer, errors: errors.clone() };
         let handler = AudioHandler::with(inner);
         
         let queue = dispatch::Queue::serial_with_ar_pool();
         
-        if let Err(e) = stream.add_stream_output(handler.as_ref(), sc::stream::OutputType::Audio, Some(&queue)) {
-            println!("[SpeakerInput] ERROR: Failed to add audio output: {:?}", e);
-        }
+        stream.add_stream_output(handler.as_ref(), sc::stream::OutputType::Audio, Some(&queue))
+            .map_err(|e| anyhow::anyhow!("Failed to add ScreenCaptureKit audio output: {:?}", e))?;
         
         // Start with completion handler to detect errors
-        println!("[SpeakerInput] Starting ScreenCaptureKit stream...");
+        log::info!("[SpeakerInput] Starting ScreenCaptureKit stream...");
         
-        use std::sync::{Arc, atomic::{AtomicBool, AtomicU8, Ordering}};
+        use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
         
         let start_complete = Arc::new(AtomicBool::new(false));
         let start_error = Arc::new(AtomicU8::new(0)); // 0 = pending, 1 = success, 2 = error
@@ -201,11 +217,11 @@ impl SpeakerInput {
         
         stream.start_with_ch(move |err| {
             if let Some(e) = err {
-                println!("[SpeakerInput] ERROR: Stream start FAILED: {:?}", e);
-                println!("[SpeakerInput] Check Screen Recording permission in System Settings!");
+                log::error!("[SpeakerInput] ERROR: Stream start FAILED: {:?}", e);
+                log::error!("[SpeakerInput] Check Screen Recording permission in System Settings!");
                 error_clone.store(2, Ordering::SeqCst);
             } else {
-                println!("[SpeakerInput] ✅ Stream started successfully!");
+                log::info!("[SpeakerInput] ✅ Stream started successfully!");
                 error_clone.store(1, Ordering::SeqCst);
             }
             complete_clone.store(true, Ordering::SeqCst);
@@ -221,18 +237,21 @@ impl SpeakerInput {
         
         let status = start_error.load(Ordering::SeqCst);
         if status == 0 {
-            println!("[SpeakerInput] WARNING: Start callback not received after 2s");
+            stream.stop_with_ch(|_| {});
+            return Err(anyhow::anyhow!("ScreenCaptureKit start callback not received after 2s"));
         } else if status == 2 {
-            println!("[SpeakerInput] WARNING: Stream started with error - audio may not work");
+            return Err(anyhow::anyhow!(
+                "ScreenCaptureKit stream failed to start; check Screen Recording permission"
+            ));
         }
         
-        SpeakerStream {
+        Ok(SpeakerStream {
             consumer: Some(consumer),
             stream,
             _handler: handler,
             _filter: self.filter,
             _cfg: self.cfg,
-        }
+        })
     }
 }
 
@@ -244,21 +263,44 @@ pub struct SpeakerStream {
     _cfg: arc::R<sc::StreamCfg>,
 }
 
-impl SpeakerStream {
-    pub fn sample_rate(&self) -> u32 {
-        48000
+impl CaptureBackend for SpeakerStream {
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            name: "screenCaptureKit",
+            pausable: false,
+            variable_rate: false,
+            selects_device: false,
+        }
+    }
+
+    /// The capture is started by `SpeakerInput::stream`
+    fn start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// ScreenCaptureKit resamples to the configured rate itself
+    fn sample_rate(&self) -> RateHandle {
+        fixed_rate(48000)
+    }
+
+    fn channels(&self) -> usize {
+        1
     }
-    
-    pub fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+
+    fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
         self.consumer.take()
     }
 }
 
 impl Drop for SpeakerStream {
     fn drop(&mut self) {
-        println!("[SpeakerStream] Stopping ScreenCaptureKit stream...");
+        log::info!("[SpeakerStream] Stopping ScreenCaptureKit stream...");
         self.stream.stop_with_ch(|_| {
-            println!("[SpeakerStream] Stream stopped");
+            log::info!("[SpeakerStream] Stream stopped");
         });
         std::thread::sleep(std::time::Duration::from_millis(100));
     }
@@ -1,44 +1,71 @@
-// Ported logic
+// Windows system audio capture via WASAPI loopback
+//
+// Architecture (same as the PulseAudio backend):
+// 1. SpeakerInput::new remembers the requested render endpoint
+// 2. stream() spawns a capture thread that owns the event-driven audio client
+// 3. The capture thread pushes f32 mono samples into a lock-free ring buffer
+// 4. When the endpoint invalidates the client (its mix format changed, or the
+//    audio service restarted), the thread reopens it at the new rate
+
 use anyhow::Result;
+use ringbuf::{traits::{Producer, Split}, HeapCons, HeapProd, HeapRb};
 use std::collections::VecDeque;
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
 use std::time::Duration;
-use tracing::error;
-use wasapi::{get_default_device, DeviceCollection, Direction, SampleType, StreamMode, WaveFormat};
-
-struct WakerState {
-    // waker: Option<Waker>, // Not used in NAPI context directly same way
-    shutdown: bool,
-}
+use crate::audio_config::RING_BUFFER_SAMPLES;
+use crate::backend::{BackendCapabilities, CaptureBackend, OutputBackend};
+use crate::errors::{CaptureError, ErrorCode, ErrorReporter};
+use crate::pipeline::{fixed_rate, DeviceRate, RateHandle};
+use wasapi::{
+    get_default_device, AudioCaptureClient, AudioClient, DeviceCollection, Direction, Handle, SampleType, StreamMode,
+    WaveFormat,
+};
 
 pub struct SpeakerInput {
     device_id: Option<String>,
 }
 
 pub struct SpeakerStream {
-    sample_queue: Arc<Mutex<VecDeque<f32>>>,
-    waker_state: Arc<Mutex<WakerState>>,
+    consumer: Option<HeapCons<f32>>,
+    shutdown: Arc<AtomicBool>,
     capture_thread: Option<thread::JoinHandle<()>>,
-    actual_sample_rate: u32,
+    sample_rate: RateHandle,
 }
 
-impl SpeakerStream {
-    pub fn sample_rate(&self) -> u32 {
-        self.actual_sample_rate
-    }
-    
-    // Read available samples
-    pub fn read_chunk(&mut self, max_samples: usize) -> Vec<f32> {
-        let mut queue = self.sample_queue.lock().unwrap();
-        let count = std::cmp::min(queue.len(), max_samples);
-        let mut samples = Vec::with_capacity(count);
-        for _ in 0..count {
-            if let Some(s) = queue.pop_front() {
-                samples.push(s);
-            }
+impl CaptureBackend for SpeakerStream {
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            name: "wasapi",
+            pausable: false,
+            variable_rate: true,
+            selects_device: true,
         }
-        samples
+    }
+
+    /// The capture thread is started by `SpeakerInput::stream`
+    fn start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// The client is initialized at the mix format's rate; a reopened
+    /// client's rate applies from its first sample
+    fn sample_rate(&self) -> RateHandle {
+        self.sample_rate.clone()
+    }
+
+    /// The client is initialized as mono
+    fn channels(&self) -> usize {
+        1
+    }
+
+    fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+        self.consumer.take()
     }
 }
 
@@ -76,146 +103,195 @@ pub fn list_output_devices() -> Result<Vec<(String, String)>> {
     Ok(list)
 }
 
+/// Endpoint id of the current default render device
+pub fn default_output_device() -> Option<String> {
+    get_default_device(&Direction::Render).ok()?.get_id().ok()
+}
+
+/// Open loopback capture of `device_id` (or of the default render device)
+pub fn open(
+    backend: OutputBackend,
+    device_id: Option<String>,
+    errors: Arc<ErrorReporter>,
+) -> Result<Box<dyn CaptureBackend>> {
+    match backend {
+        OutputBackend::Auto | OutputBackend::Wasapi => {
+            Ok(Box::new(SpeakerInput::new(device_id)?.stream(errors)?))
+        }
+        other => Err(anyhow::anyhow!("System audio backend {} is not available on Windows", other.name())),
+    }
+}
+
 impl SpeakerInput {
     pub fn new(device_id: Option<String>) -> Result<Self> {
         let device_id = device_id.filter(|id| !id.is_empty() && id != "default");
         Ok(Self { device_id })
     }
 
-    pub fn stream(self) -> SpeakerStream {
-        let sample_queue = Arc::new(Mutex::new(VecDeque::new()));
-        let waker_state = Arc::new(Mutex::new(WakerState {
-            shutdown: false,
-        }));
+    /// Spawn the capture thread and wait for the loopback client to start
+    pub fn stream(self, errors: Arc<ErrorReporter>) -> Result<SpeakerStream> {
+        let (producer, consumer) = HeapRb::<f32>::new(RING_BUFFER_SAMPLES).split();
+        let shutdown = Arc::new(AtomicBool::new(false));
         let (init_tx, init_rx) = mpsc::channel();
 
-        let queue_clone = sample_queue.clone();
-        let waker_clone = waker_state.clone();
+        let shutdown_clone = shutdown.clone();
         let device_id = self.device_id;
 
         let capture_thread = thread::spawn(move || {
-            if let Err(e) = Self::capture_audio_loop(queue_clone, waker_clone, init_tx, device_id) {
-                error!("Audio capture loop failed: {}", e);
-            }
+            Self::capture_audio_loop(producer, shutdown_clone, init_tx, device_id, &errors);
         });
 
-        let actual_sample_rate = match init_rx.recv_timeout(Duration::from_secs(5)) {
-            Ok(Ok(rate)) => rate,
-            Ok(Err(e)) => {
-                error!("Audio initialization failed: {}", e);
-                44100
+        let mut stream = SpeakerStream {
+            consumer: Some(consumer),
+            shutdown,
+            capture_thread: Some(capture_thread),
+            // Replaced by the capture thread's handle once the client starts
+            sample_rate: fixed_rate(0),
+        };
+
+        match init_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Ok(sample_rate)) => {
+                stream.sample_rate = sample_rate;
+                Ok(stream)
             }
+            // The thread has already returned; dropping the stream joins it
+            Ok(Err(e)) => Err(anyhow::anyhow!("Audio initialization failed: {}", e)),
             Err(_) => {
-                error!("Audio initialization timeout");
-                44100
+                // Still blocked in COM; let it exit on its own
+                stream.capture_thread = None;
+                Err(anyhow::anyhow!("Audio initialization timeout"))
             }
-        };
-
-        SpeakerStream {
-            sample_queue,
-            waker_state,
-            capture_thread: Some(capture_thread),
-            actual_sample_rate,
         }
     }
 
     fn capture_audio_loop(
-        sample_queue: Arc<Mutex<VecDeque<f32>>>,
-        waker_state: Arc<Mutex<WakerState>>,
-        init_tx: mpsc::Sender<Result<u32>>,
+        mut producer: HeapProd<f32>,
+        shutdown: Arc<AtomicBool>,
+        init_tx: mpsc::Sender<Result<RateHandle>>,
         device_id: Option<String>,
-    ) -> Result<()> {
+        errors: &ErrorReporter,
+    ) {
         let init_result = (|| -> Result<_> {
             let device = match device_id {
                 Some(ref id) => match find_device_by_id(&Direction::Render, id) {
                     Some(d) => d,
-                    None => get_default_device(&Direction::Render).expect("No default render device"),
+                    None => get_default_device(&Direction::Render)?,
                 },
                 None => get_default_device(&Direction::Render)?,
             };
-
-            let mut audio_client = device.get_iaudioclient()?;
-            let device_format = audio_client.get_mixformat()?;
-            let actual_rate = device_format.get_samplespersec();
-            let desired_format = WaveFormat::new(32, 32, &SampleType::Float, actual_rate as usize, 1, None);
-
-            let (_def_time, min_time) = audio_client.get_device_period()?;
-            let mode = StreamMode::EventsShared {
-                autoconvert: true,
-                buffer_duration_hns: min_time,
-            };
-
-            audio_client.initialize_client(&desired_format, &Direction::Capture, &mode)?;
-            let h_event = audio_client.set_get_eventhandle()?;
-            let render_client = audio_client.get_audiocaptureclient()?;
-            audio_client.start_stream()?;
-
-            Ok((h_event, render_client, actual_rate))
+            let client = LoopbackClient::open(&device)?;
+            Ok((device, client))
         })();
 
-        match init_result {
-            Ok((h_event, render_client, sample_rate)) => {
-                let _ = init_tx.send(Ok(sample_rate));
-                loop {
-                    {
-                        let state = waker_state.lock().unwrap();
-                        if state.shutdown {
-                            break;
-                        }
-                    }
+        let (device, mut client) = match init_result {
+            Ok(opened) => opened,
+            Err(e) => {
+                let _ = init_tx.send(Err(e));
+                return;
+            }
+        };
+        let sample_rate = Arc::new(DeviceRate::new(client.sample_rate));
+        let _ = init_tx.send(Ok(sample_rate.clone()));
+        // Samples pushed so far, the position a reopened client's rate applies from
+        let mut pushed = 0u64;
 
-                    if h_event.wait_for_event(3000).is_err() {
-                        error!("Timeout error, stopping capture");
-                        break;
-                    }
+        while !shutdown.load(Ordering::Relaxed) {
+            // Loopback only signals while something is playing, so a
+            // timeout just means silence; wake up to check shutdown
+            if client.event.wait_for_event(3000).is_err() {
+                continue;
+            }
 
-                    let mut temp_queue = VecDeque::new();
-                    if let Err(e) = render_client.read_from_device_to_deque(&mut temp_queue) {
-                        error!("Failed to read audio data: {}", e);
+            // GetBuffer only fails once the client is unusable (endpoint
+            // invalidated by a mix format change, audio service restarted)
+            let mut temp_queue = VecDeque::new();
+            if let Err(e) = client.capture.read_from_device_to_deque(client.block_align, &mut temp_queue) {
+                log::warn!("[WASAPI] Capture client invalidated ({}), reopening", e);
+                match LoopbackClient::open(&device) {
+                    Ok(reopened) => {
+                        log::info!("[WASAPI] Reopened at {}Hz", reopened.sample_rate);
+                        sample_rate.set(reopened.sample_rate, pushed);
+                        client = reopened;
                         continue;
                     }
-
-                    if temp_queue.is_empty() {
-                        continue;
+                    Err(e) => {
+                        errors.report(CaptureError::new(
+                            ErrorCode::DeviceLost,
+                            format!("Failed to reopen loopback capture: {}", e),
+                            true,
+                        ));
+                        break;
                     }
+                }
+            }
 
-                    let mut samples = Vec::new();
-                    while temp_queue.len() >= 4 {
-                        let bytes = [
-                            temp_queue.pop_front().unwrap(),
-                            temp_queue.pop_front().unwrap(),
-                            temp_queue.pop_front().unwrap(),
-                            temp_queue.pop_front().unwrap(),
-                        ];
-                        let sample = f32::from_le_bytes(bytes);
-                        samples.push(sample);
-                    }
+            if temp_queue.is_empty() {
+                continue;
+            }
 
-                    if !samples.is_empty() {
-                         let mut queue = sample_queue.lock().unwrap();
-                         let max_buffer_size = 131072; // 128KB
-                         queue.extend(samples.iter());
-                         if queue.len() > max_buffer_size {
-                             let to_drop = queue.len() - max_buffer_size;
-                             queue.drain(0..to_drop);
-                         }
-                    }
-                }
+            let mut samples = Vec::new();
+            while temp_queue.len() >= 4 {
+                let bytes = [
+                    temp_queue.pop_front().unwrap(),
+                    temp_queue.pop_front().unwrap(),
+                    temp_queue.pop_front().unwrap(),
+                    temp_queue.pop_front().unwrap(),
+                ];
+                let sample = f32::from_le_bytes(bytes);
+                samples.push(sample);
             }
-            Err(e) => {
-                let _ = init_tx.send(Err(e));
+
+            let written = producer.push_slice(&samples);
+            pushed += written as u64;
+            if written < samples.len() {
+                errors.flag(ErrorCode::Overflow, false);
             }
         }
-        Ok(())
+    }
+}
+
+/// A started loopback client, mono f32 at the endpoint's mix format rate
+struct LoopbackClient {
+    /// Owns the stream the capture client reads from
+    _audio_client: AudioClient,
+    event: Handle,
+    capture: AudioCaptureClient,
+    sample_rate: u32,
+    block_align: usize,
+}
+
+impl LoopbackClient {
+    fn open(device: &wasapi::Device) -> Result<Self> {
+        let mut audio_client = device.get_iaudioclient()?;
+        let device_format = audio_client.get_mixformat()?;
+        let sample_rate = device_format.get_samplespersec();
+        let desired_format = WaveFormat::new(32, 32, &SampleType::Float, sample_rate as usize, 1, None);
+
+        let (_def_time, min_time) = audio_client.get_device_period()?;
+        let mode = StreamMode::EventsShared {
+            autoconvert: true,
+            buffer_duration_hns: min_time,
+        };
+
+        audio_client.initialize_client(&desired_format, &Direction::Capture, &mode)?;
+        let event = audio_client.set_get_eventhandle()?;
+        let capture = audio_client.get_audiocaptureclient()?;
+        audio_client.start_stream()?;
+
+        Ok(Self {
+            _audio_client: audio_client,
+            event,
+            capture,
+            sample_rate,
+            block_align: desired_format.get_blockalign() as usize,
+        })
     }
 }
 
 // Implement Drop to stop the thread
 impl Drop for SpeakerStream {
     fn drop(&mut self) {
-        if let Ok(mut state) = self.waker_state.lock() {
-            state.shutdown = true;
-        }
+        self.shutdown.store(true, Ordering::SeqCst);
         if let Some(handle) = self.capture_thread.take() {
              let _ = handle.join();
         }
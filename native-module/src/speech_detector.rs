@@ -0,0 +1,483 @@
+// Speech Detection - shared by SilenceSuppressor (gating) and VadIndicator (UI)
+//
+// Two detectors behind one trait:
+// - RmsDetector: the original fixed RMS threshold. Cheap, but fans, typing
+//   and HVAC all count as "speech" once they are loud enough.
+// - FeatureDetector: energy relative to a learned noise floor, combined
+//   with zero-crossing rate and spectral flatness so that stationary noise
+//   and broadband clicks are rejected regardless of level.
+//
+// Detectors return a normalized score: >= 1.0 means speech. Consumers that
+// need hysteresis (VadIndicator) compare against a lower end score.
+
+use std::sync::Arc;
+
+use realfft::{RealFftPlanner, RealToComplex};
+use realfft::num_complex::Complex;
+
+/// Result of analyzing one frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    /// Frame RMS (i16 scale: 0-32767)
+    pub rms: f32,
+    /// Speech score normalized to the detector's threshold (>= 1.0 = speech)
+    pub score: f32,
+}
+
+impl Detection {
+    pub fn is_speech(&self) -> bool {
+        self.score >= 1.0
+    }
+}
+
+/// Frame-level speech/non-speech classifier
+pub trait SpeechDetector: Send {
+    /// Short name used in logs
+    fn name(&self) -> &'static str;
+
+    /// Analyze one mono frame
+    fn analyze(&mut self, frame: &[i16]) -> Detection;
+
+    /// Forget adaptive state (e.g. when the stream restarts)
+    fn reset(&mut self) {}
+}
+
+/// JS-facing detector selection ("rms" | "features")
+pub fn detector_from_name(name: Option<&str>, rms_threshold: f32, sample_rate: u32) -> anyhow::Result<Box<dyn SpeechDetector>> {
+    match name {
+        None | Some("rms") => Ok(Box::new(RmsDetector::new(rms_threshold))),
+        Some("features") => Ok(Box::new(FeatureDetector::new(sample_rate))),
+        Some(other) => Err(anyhow::anyhow!("Unknown speech detector: {}", other)),
+    }
+}
+
+// ============================================================================
+// RMS (legacy)
+// ============================================================================
+
+/// Fixed RMS threshold on a strided subset of samples
+pub struct RmsDetector {
+    threshold: f32,
+    stride: usize,
+}
+
+impl RmsDetector {
+    pub fn new(threshold: f32) -> Self {
+        Self::with_stride(threshold, 4)
+    }
+
+    /// Sample every `stride`th sample (320/4 = 80 samples is plenty for RMS)
+    pub fn with_stride(threshold: f32, stride: usize) -> Self {
+        Self { threshold: threshold.max(f32::EPSILON), stride: stride.max(1) }
+    }
+}
+
+impl SpeechDetector for RmsDetector {
+    fn name(&self) -> &'static str {
+        "rms"
+    }
+
+    fn analyze(&mut self, frame: &[i16]) -> Detection {
+        let rms = strided_rms(frame, self.stride);
+        Detection { rms, score: rms / self.threshold }
+    }
+}
+
+fn strided_rms(samples: &[i16], stride: usize) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+
+    let sum_of_squares: f64 = samples.iter()
+        .step_by(stride)
+        .map(|&s| (s as f64) * (s as f64))
+        .sum();
+
+    let count = samples.len().div_ceil(stride);
+    (sum_of_squares / count as f64).sqrt() as f32
+}
+
+// ============================================================================
+// FEATURES (energy + zero-crossing + spectral flatness)
+// ============================================================================
+
+/// Frames quieter than this are never speech (~-60dBFS)
+const MIN_RMS: f32 = 30.0;
+
+/// Energy above the noise floor counted as speech
+const SNR_SPEECH_DB: f32 = 9.0;
+
+/// Upper limit for voiced speech; noise and clicks sit well above
+const FLATNESS_MAX: f32 = 0.3;
+
+/// Zero crossings per sample; voiced speech stays low, hiss is ~0.5
+const ZCR_MAX: f32 = 0.25;
+
+/// How fast the noise floor may rise, whatever the frame duration
+const FLOOR_RISE_DB_PER_S: f32 = 2.0;
+
+/// Smoothing when the frame is quieter than the floor
+const FLOOR_FALL: f32 = 0.7;
+
+/// Flatness is measured over the voice band only
+const BAND_LOW_HZ: f32 = 250.0;
+const BAND_HIGH_HZ: f32 = 4000.0;
+
+pub struct FeatureDetector {
+    sample_rate: u32,
+    /// Mean-square energy of the noise floor; None until the first frame
+    floor: Option<f32>,
+    /// Per-frame floor rise factor for the current frame length
+    floor_rise: f32,
+    fft_len: usize,
+    fft: Option<Arc<dyn RealToComplex<f32>>>,
+    window: Vec<f32>,
+    time_buf: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+}
+
+impl FeatureDetector {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            floor: None,
+            floor_rise: 1.0,
+            fft_len: 0,
+            fft: None,
+            window: Vec::new(),
+            time_buf: Vec::new(),
+            spectrum: Vec::new(),
+        }
+    }
+
+    /// Plan the FFT and scale the floor rise for the frame length on first
+    /// use (frames are fixed-size)
+    fn prepare(&mut self, len: usize) {
+        if self.fft_len == len {
+            return;
+        }
+        let frame_s = len as f32 / self.sample_rate as f32;
+        self.floor_rise = 10f32.powf(FLOOR_RISE_DB_PER_S * frame_s / 10.0);
+        let fft = RealFftPlanner::<f32>::new().plan_fft_forward(len);
+        self.window = (0..len)
+            .map(|n| 0.5 - 0.5 * (std::f32::consts::TAU * n as f32 / len as f32).cos())
+            .collect();
+        self.time_buf = vec![0.0; len];
+        self.spectrum = fft.make_output_vec();
+        self.fft = Some(fft);
+        self.fft_len = len;
+    }
+
+    fn spectral_flatness(&mut self, frame: &[i16]) -> f32 {
+        self.prepare(frame.len());
+        let Some(fft) = self.fft.as_ref() else { return 1.0 };
+
+        for ((dst, &src), &w) in self.time_buf.iter_mut().zip(frame).zip(&self.window) {
+            *dst = src as f32 * w;
+        }
+        if fft.process(&mut self.time_buf, &mut self.spectrum).is_err() {
+            return 1.0;
+        }
+
+        let bin_hz = self.sample_rate as f32 / frame.len() as f32;
+        let low = (BAND_LOW_HZ / bin_hz).ceil() as usize;
+        let high = ((BAND_HIGH_HZ / bin_hz) as usize).min(self.spectrum.len() - 1);
+        if high <= low {
+            return 1.0;
+        }
+
+        let band = &self.spectrum[low..=high];
+        let (log_sum, sum) = band.iter()
+            .map(|c| c.norm_sqr() + 1e-3)
+            .fold((0.0f32, 0.0f32), |(l, s), p| (l + p.ln(), s + p));
+        let n = band.len() as f32;
+        (log_sum / n).exp() / (sum / n)
+    }
+
+    fn update_floor(&mut self, energy: f32) -> f32 {
+        let floor = match self.floor {
+            None => energy,
+            Some(floor) if energy < floor => FLOOR_FALL * floor + (1.0 - FLOOR_FALL) * energy,
+            Some(floor) => (floor * self.floor_rise).min(energy),
+        };
+        // Keep the floor off zero so digital silence never yields infinite SNR
+        let floor = floor.max(1.0);
+        self.floor = Some(floor);
+        floor
+    }
+}
+
+impl SpeechDetector for FeatureDetector {
+    fn name(&self) -> &'static str {
+        "features"
+    }
+
+    fn analyze(&mut self, frame: &[i16]) -> Detection {
+        if frame.len() < 2 {
+            return Detection { rms: 0.0, score: 0.0 };
+        }
+
+        self.prepare(frame.len());
+        let energy = frame.iter().map(|&s| (s as f32).powi(2)).sum::<f32>() / frame.len() as f32;
+        let rms = energy.sqrt();
+        let floor = self.update_floor(energy);
+
+        let crossings = frame.windows(2).filter(|w| (w[0] >= 0) != (w[1] >= 0)).count();
+        let zcr = crossings as f32 / (frame.len() - 1) as f32;
+        let flatness = self.spectral_flatness(frame);
+        let snr_db = 10.0 * (energy / floor).max(1e-6).log10();
+
+        // Every feature must agree; each is scaled so 1.0 is its threshold
+        let score = (snr_db / SNR_SPEECH_DB)
+            .min(FLATNESS_MAX / flatness.max(1e-6))
+            .min(ZCR_MAX / zcr.max(1e-6))
+            .min(rms / MIN_RMS);
+
+        Detection { rms, score }
+    }
+
+    fn reset(&mut self) {
+        self.floor = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    const RATE: usize = 16_000;
+    const FRAME: usize = 320;
+
+    /// One labeled clip of the benchmark corpus
+    struct Clip {
+        name: String,
+        samples: Vec<i16>,
+        /// Per-frame ground truth
+        labels: Vec<bool>,
+    }
+
+    /// Brown-ish noise (integrated white noise), the shape of fans and HVAC
+    fn rumble(rng: &mut StdRng, len: usize, rms: f32) -> Vec<f32> {
+        let mut state = 0.0f32;
+        let raw: Vec<f32> = (0..len)
+            .map(|_| {
+                state = 0.98 * state + rng.gen_range(-1.0..1.0);
+                state
+            })
+            .collect();
+        scale_to_rms(raw, rms)
+    }
+
+    fn hiss(rng: &mut StdRng, len: usize, rms: f32) -> Vec<f32> {
+        scale_to_rms((0..len).map(|_| rng.gen_range(-1.0..1.0)).collect(), rms)
+    }
+
+    fn scale_to_rms(mut samples: Vec<f32>, rms: f32) -> Vec<f32> {
+        let current = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        samples.iter_mut().for_each(|s| *s *= rms / current.max(1e-9));
+        samples
+    }
+
+    /// Keyboard clatter: 4ms decaying broadband bursts at irregular intervals
+    fn typing(rng: &mut StdRng, len: usize, peak: f32) -> Vec<f32> {
+        let mut out = vec![0.0; len];
+        let mut pos = 800;
+        while pos + 64 < len {
+            for (i, sample) in out[pos..pos + 64].iter_mut().enumerate() {
+                *sample = peak * rng.gen_range(-1.0..1.0) * (-(i as f32) / 12.0).exp();
+            }
+            pos += rng.gen_range(1200..3200);
+        }
+        out
+    }
+
+    /// Voiced "syllables": glottal pulse train through two formant resonators
+    /// with a 4Hz syllabic envelope. Returns (signal, envelope).
+    fn voice(rng: &mut StdRng, len: usize, rms: f32) -> (Vec<f32>, Vec<f32>) {
+        let formants = [(700.0f32, 80.0f32), (1200.0, 120.0)];
+        let mut states = [[0.0f32; 2]; 2];
+        let mut phase = 0.0f32;
+        let mut f0 = 140.0f32;
+        let mut signal = Vec::with_capacity(len);
+        let mut envelope = Vec::with_capacity(len);
+
+        for n in 0..len {
+            let t = n as f32 / RATE as f32;
+            // Syllables of ~180ms separated by short pauses
+            let syllable = (t * 4.0 * std::f32::consts::TAU).sin();
+            let env = if syllable > 0.3 { ((syllable - 0.3) / 0.7).sqrt() } else { 0.0 };
+            f0 = (f0 + rng.gen_range(-0.2..0.2)).clamp(110.0, 200.0);
+
+            phase += f0 / RATE as f32;
+            let mut x = if phase >= 1.0 { phase -= 1.0; 1.0 } else { 0.0 };
+            for (state, &(freq, bw)) in states.iter_mut().zip(&formants) {
+                let r = (-std::f32::consts::PI * bw / RATE as f32).exp();
+                let a1 = 2.0 * r * (std::f32::consts::TAU * freq / RATE as f32).cos();
+                let y = x + a1 * state[0] - r * r * state[1];
+                state[1] = state[0];
+                state[0] = y;
+                x = y;
+            }
+            signal.push(x * env);
+            envelope.push(env);
+        }
+
+        (scale_to_rms(signal, rms), envelope)
+    }
+
+    fn mix(parts: &[&[f32]]) -> Vec<i16> {
+        (0..parts[0].len())
+            .map(|i| parts.iter().map(|p| p[i]).sum::<f32>().clamp(-32768.0, 32767.0) as i16)
+            .collect()
+    }
+
+    /// Music: a sawtooth chord progression with a kick on every beat
+    fn music(len: usize, rms: f32) -> Vec<f32> {
+        let chords = [[220.0f32, 277.2, 329.6], [196.0, 246.9, 293.7], [174.6, 220.0, 261.6], [196.0, 246.9, 329.6]];
+        let beat = RATE / 2;
+        let raw = (0..len)
+            .map(|n| {
+                let t = n as f32 / RATE as f32;
+                let chord = chords[(n / (2 * RATE)) % chords.len()];
+                let tone: f32 = chord.iter().map(|f| 2.0 * (t * f).fract() - 1.0).sum();
+                let since_beat = (n % beat) as f32 / RATE as f32;
+                let kick = (since_beat * 60.0 * std::f32::consts::TAU).sin() * (-since_beat * 30.0).exp();
+                tone + 3.0 * kick
+            })
+            .collect();
+        scale_to_rms(raw, rms)
+    }
+
+    fn frame_labels(envelope: Option<&[f32]>, len: usize) -> Vec<bool> {
+        (0..len / FRAME)
+            .map(|f| envelope.is_some_and(|env| {
+                let chunk = &env[f * FRAME..(f + 1) * FRAME];
+                chunk.iter().sum::<f32>() / FRAME as f32 > 0.5
+            }))
+            .collect()
+    }
+
+    /// Synthetic stand-ins for each kind of clip, labeled from the voice
+    /// envelope: a smoke test that runs without any recordings
+    fn synthetic_corpus() -> Vec<Clip> {
+        let mut rng = StdRng::seed_from_u64(42);
+        let len = 4 * RATE;
+        let silence = vec![0.0f32; len];
+
+        let room = hiss(&mut rng, len, 15.0);
+        let fan = rumble(&mut rng, len, 600.0);
+        let hvac = hiss(&mut rng, len, 250.0);
+        let keys = typing(&mut rng, len, 6000.0);
+        let song = music(len, 1500.0);
+        let (talk, envelope) = voice(&mut rng, len, 2500.0);
+        let (talk_quiet, envelope_quiet) = voice(&mut rng, len, 600.0);
+
+        let clips = [
+            ("quiet-room", mix(&[&room, &silence]), None),
+            ("laptop-fan", mix(&[&room, &fan]), None),
+            ("hvac-hiss", mix(&[&room, &hvac]), None),
+            ("typing", mix(&[&room, &keys]), None),
+            ("music", mix(&[&room, &song]), None),
+            ("speech", mix(&[&room, &talk]), Some(envelope.as_slice())),
+            ("speech-over-fan", mix(&[&fan, &talk]), Some(envelope.as_slice())),
+            ("quiet-speech", mix(&[&room, &talk_quiet]), Some(envelope_quiet.as_slice())),
+        ];
+        clips.into_iter()
+            .map(|(name, samples, env)| Clip { name: name.to_string(), samples, labels: frame_labels(env, len) })
+            .collect()
+    }
+
+    /// Recorded clips: `<name>.wav` (16kHz mono 16-bit) next to
+    /// `<name>.labels`, one 0/1 per 20ms frame (see the README there)
+    fn recorded_corpus() -> Vec<Clip> {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/vad");
+        let mut clips: Vec<Clip> = std::fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "wav"))
+            .map(|path| {
+                let mut reader = hound::WavReader::open(&path).unwrap();
+                let spec = reader.spec();
+                assert_eq!((spec.channels, spec.sample_rate, spec.bits_per_sample), (1, RATE as u32, 16), "{}", path.display());
+                let labels = std::fs::read_to_string(path.with_extension("labels")).unwrap();
+                Clip {
+                    name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+                    samples: reader.samples::<i16>().map(Result::unwrap).collect(),
+                    labels: labels.split_whitespace().flat_map(str::chars).map(|c| c == '1').collect(),
+                }
+            })
+            .collect();
+        clips.sort_by(|a, b| a.name.cmp(&b.name));
+        clips
+    }
+
+    /// Frame accuracy over the corpus, plus a per-clip breakdown
+    fn accuracy(corpus: &[Clip], make: &dyn Fn() -> Box<dyn SpeechDetector>) -> (f32, String) {
+        let mut correct = 0;
+        let mut total = 0;
+        let mut report = String::new();
+
+        for clip in corpus {
+            let mut detector = make();
+            let mut clip_correct = 0;
+            for (frame, &label) in clip.samples.chunks_exact(FRAME).zip(&clip.labels) {
+                if detector.analyze(frame).is_speech() == label {
+                    clip_correct += 1;
+                }
+            }
+            report.push_str(&format!("\n  {}: {}/{}", clip.name, clip_correct, clip.labels.len()));
+            correct += clip_correct;
+            total += clip.labels.len();
+        }
+
+        (correct as f32 / total as f32, report)
+    }
+
+    fn assert_features_beat_rms(corpus: &[Clip]) {
+        let (rms, rms_report) = accuracy(corpus, &|| Box::new(RmsDetector::new(100.0)));
+        let (features, report) = accuracy(corpus, &|| Box::new(FeatureDetector::new(RATE as u32)));
+
+        assert!(features > 0.9, "feature detector accuracy {:.2}:{}", features, report);
+        assert!(features > rms + 0.1, "features {:.2} vs rms {:.2}:{}\nrms:{}", features, rms, report, rms_report);
+    }
+
+    #[test]
+    fn test_feature_detector_beats_rms_on_synthetic_clips() {
+        assert_features_beat_rms(&synthetic_corpus());
+    }
+
+    #[test]
+    #[ignore = "requires recorded clips in tests/fixtures/vad"]
+    fn test_feature_detector_beats_rms_on_recordings() {
+        let corpus = recorded_corpus();
+        assert!(!corpus.is_empty(), "no recordings in tests/fixtures/vad");
+        assert_features_beat_rms(&corpus);
+    }
+
+    #[test]
+    fn test_floor_rise_does_not_depend_on_frame_duration() {
+        // 2s of noise 6dB above the learned floor, in 10ms and 100ms frames
+        let floor_after = |frame_ms: usize| {
+            let len = RATE * frame_ms / 1000;
+            let mut detector = FeatureDetector::new(RATE as u32);
+            detector.analyze(&vec![1000; len]);
+            for _ in 0..2000 / frame_ms {
+                detector.analyze(&vec![2000; len]);
+            }
+            10.0 * (detector.floor.unwrap() / 1000f32.powi(2)).log10()
+        };
+
+        for frame_ms in [10, 100] {
+            let rise = floor_after(frame_ms);
+            assert!((rise - 4.0).abs() < 0.1, "{}ms frames: floor rose {:.2}dB", frame_ms, rise);
+        }
+    }
+
+    #[test]
+    fn test_rms_detector_keeps_legacy_threshold() {
+        let mut detector = RmsDetector::new(100.0);
+        assert!(detector.analyze(&[500; 320]).is_speech());
+        assert!(!detector.analyze(&[50; 320]).is_speech());
+    }
+}
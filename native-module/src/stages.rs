@@ -0,0 +1,321 @@
+// Stage Registry
+//
+// Builds the ordered list of pipeline stages for a capture source from the
+// `stages` array passed to the JS constructors. Each entry names a stage
+// kind plus the parameters that kind understands; unknown kinds are
+// rejected up front instead of being silently skipped.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::aec::{EchoCancellerConfig, EchoCancellerStage, FarEndPublisher, FarEndReference};
+use crate::agc::{AgcConfig, AgcStage};
+use crate::metrics::{AgcStats, EchoStats, LevelMeter};
+use crate::noise_suppression::NoiseSuppressor;
+use crate::opus::{OpusConfig, OpusContainer, OpusStage};
+use crate::pipeline::{Frame, FrameProcessor, OutputFormat, StageOutput};
+use crate::silence_suppression::{SilenceSuppressionConfig, SilenceSuppressor};
+use crate::speech_detector;
+
+/// Which capture class a pipeline belongs to (selects per-source defaults)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SourceKind {
+    Microphone,
+    SystemAudio,
+}
+
+impl SourceKind {
+    /// Parse the JS-facing name ("microphone" | "systemAudio")
+    pub fn from_name(name: Option<&str>) -> Result<Self> {
+        match name {
+            None | Some("microphone") => Ok(SourceKind::Microphone),
+            Some("systemAudio") => Ok(SourceKind::SystemAudio),
+            Some(other) => Err(anyhow::anyhow!("Unknown source: {}", other)),
+        }
+    }
+}
+
+/// One entry of the JS `stages` option
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct StageOptions {
+    /// "noiseSuppression" | "suppression" | "agc" | "gain" |
+    /// "aec" (microphone only, see `setEchoReference`) | "opus"
+    pub kind: String,
+    /// Skip this stage without removing it from the list. Defaults to true.
+    pub enabled: Option<bool>,
+    /// gain: fixed gain in dB (may be negative)
+    pub gain_db: Option<f64>,
+    /// aec: echo tail covered by the adaptive filter in ms (default 16)
+    pub tail_ms: Option<u32>,
+    /// aec: largest speaker -> microphone delay searched in ms (default 500)
+    pub max_delay_ms: Option<u32>,
+    /// noiseSuppression: 0.0 (off) .. 1.0 (strongest, default 0.7)
+    pub strength: Option<f64>,
+    /// agc: loudness the output is steered to in dBFS (default -20)
+    pub target_dbfs: Option<f64>,
+    /// agc: largest boost in dB (default 30)
+    pub max_gain_db: Option<f64>,
+    /// agc: time constant for reducing gain in ms (default 20)
+    pub attack_ms: Option<u32>,
+    /// agc: time constant for increasing gain in ms (default 1000)
+    pub release_ms: Option<u32>,
+    /// agc: peak limiter at -1 dBFS instead of hard clipping (default true)
+    pub limiter: Option<bool>,
+    /// suppression: speech detector, "rms" (fixed threshold, default) or
+    /// "features" (energy + zero-crossing + spectral flatness)
+    pub detector: Option<String>,
+    /// opus: target bitrate in bits per second (default 24000)
+    pub bitrate: Option<u32>,
+    /// opus: encoder effort 0 (fastest) .. 10 (best, default 5)
+    pub complexity: Option<u32>,
+    /// opus: "ogg" (Ogg Opus stream, default) | "raw" (one bare packet per
+    /// frame)
+    pub container: Option<String>,
+}
+
+/// Handles to metrics published by the stages of one pipeline
+#[derive(Debug, Clone, Default)]
+pub struct StageMetrics {
+    pub echo: Option<Arc<EchoStats>>,
+    pub agc: Option<Arc<AgcStats>>,
+}
+
+/// Every stage kind `build_stages` understands
+pub const STAGE_KINDS: &[&str] = &["noiseSuppression", "suppression", "agc", "gain", "aec", "opus"];
+
+/// Stages used when the constructor options do not specify any
+pub fn default_stages() -> Vec<StageOptions> {
+    vec![StageOptions {
+        kind: "suppression".to_string(),
+        ..Default::default()
+    }]
+}
+
+/// Reject unknown stage kinds, detectors and encoder settings before
+/// anything starts; `channels` is the interleaved channel count the stages
+/// will see
+pub fn validate_stages(options: &[StageOptions], source: SourceKind, output: &OutputFormat, channels: usize) -> Result<()> {
+    if let Some(unknown) = options.iter().find(|o| !STAGE_KINDS.contains(&o.kind.as_str())) {
+        return Err(anyhow::anyhow!("Unknown stage kind: {}", unknown.kind));
+    }
+    // System audio is the far end; it would cancel itself
+    if source == SourceKind::SystemAudio && options.iter().any(|o| o.kind == "aec") {
+        return Err(anyhow::anyhow!("The aec stage only runs on microphone pipelines"));
+    }
+    for opts in options {
+        speech_detector::detector_from_name(opts.detector.as_deref(), 1.0, 16000)?;
+        if opts.kind == "opus" {
+            opus_config(opts, output.sample_rate, channels)?.validate()?;
+            if ![10, 20, 40].contains(&output.frame_ms) {
+                return Err(anyhow::anyhow!("Opus needs 10, 20 or 40ms frames, not {}ms", output.frame_ms));
+            }
+        }
+    }
+
+    // Nothing can process PCM once it has been encoded
+    let mut enabled = options.iter().filter(|o| o.enabled.unwrap_or(true));
+    if enabled.by_ref().position(|o| o.kind == "opus").is_some() && enabled.next().is_some() {
+        return Err(anyhow::anyhow!("The opus stage must be the last stage"));
+    }
+    Ok(())
+}
+
+fn opus_config(opts: &StageOptions, sample_rate: u32, channels: usize) -> Result<OpusConfig> {
+    let mut config = OpusConfig::new(sample_rate, channels);
+    config.container = OpusContainer::from_name(opts.container.as_deref())?;
+    if let Some(bitrate) = opts.bitrate {
+        config.bitrate = bitrate;
+    }
+    if let Some(complexity) = opts.complexity {
+        config.complexity = complexity;
+    }
+    Ok(config)
+}
+
+/// Instantiate the configured stages in order
+///
+/// List `noiseSuppression` before `suppression` so the RMS gate sees the
+/// denoised signal, and `agc` after it so the gate still sees raw levels.
+/// `opus` goes last: frames leave it encoded.
+///
+/// `echo_reference` is the far end of echo cancellation: system audio
+/// pipelines publish to it (while a linked `aec` stage reads), microphone
+/// `aec` stages cancel against it.
+pub fn build_stages(
+    options: &[StageOptions],
+    source: SourceKind,
+    sample_rate: u32,
+    channels: usize,
+    echo_reference: Option<&Arc<FarEndReference>>,
+) -> Result<(Vec<Box<dyn FrameProcessor>>, StageMetrics)> {
+    let mut stages: Vec<Box<dyn FrameProcessor>> = Vec::new();
+    let mut metrics = StageMetrics::default();
+
+    if let (SourceKind::SystemAudio, Some(reference)) = (source, echo_reference) {
+        stages.push(Box::new(FarEndPublisher::new(reference.clone(), sample_rate)));
+    }
+
+    for opts in options {
+        if !opts.enabled.unwrap_or(true) {
+            continue;
+        }
+
+        let stage: Box<dyn FrameProcessor> = match opts.kind.as_str() {
+            "noiseSuppression" => {
+                let strength = opts.strength.unwrap_or(0.7) as f32;
+                Box::new(NoiseSuppressor::new(sample_rate, strength))
+            }
+            "suppression" => {
+                let config = match source {
+                    SourceKind::Microphone => SilenceSuppressionConfig::for_microphone(),
+                    SourceKind::SystemAudio => SilenceSuppressionConfig::for_system_audio(),
+                };
+                let detector = speech_detector::detector_from_name(
+                    opts.detector.as_deref(),
+                    config.speech_threshold_rms,
+                    sample_rate,
+                )?;
+                Box::new(SilenceSuppressor::with_detector(config, detector, sample_rate))
+            }
+            "agc" => {
+                let mut config = AgcConfig::new(sample_rate);
+                if let Some(target) = opts.target_dbfs {
+                    config.target_dbfs = target as f32;
+                }
+                if let Some(max_gain) = opts.max_gain_db {
+                    config.max_gain_db = max_gain as f32;
+                }
+                if let Some(attack) = opts.attack_ms {
+                    config.attack_ms = attack as f32;
+                }
+                if let Some(release) = opts.release_ms {
+                    config.release_ms = release as f32;
+                }
+                config.limiter = opts.limiter.unwrap_or(true);
+
+                let stage = AgcStage::new(config);
+                metrics.agc = Some(stage.stats());
+                Box::new(stage)
+            }
+            "gain" => Box::new(GainStage::new(opts.gain_db.unwrap_or(0.0) as f32)),
+            "aec" => {
+                let config = EchoCancellerConfig::new(
+                    sample_rate,
+                    opts.tail_ms.unwrap_or(16),
+                    opts.max_delay_ms.unwrap_or(500),
+                );
+                let reference = match echo_reference {
+                    Some(reference) => reference.clone(),
+                    None => {
+                        log::warn!("[Stages] aec stage has no echo reference (see setEchoReference), passing audio through");
+                        Arc::new(FarEndReference::default())
+                    }
+                };
+                let stage = EchoCancellerStage::new(config, &reference);
+                metrics.echo = Some(stage.stats());
+                Box::new(stage)
+            }
+            "opus" => Box::new(OpusStage::new(opus_config(opts, sample_rate, channels)?)?),
+            other => return Err(anyhow::anyhow!("Unknown stage kind: {}", other)),
+        };
+        stages.push(stage);
+    }
+
+    Ok((stages, metrics))
+}
+
+/// Fixed gain with hard clipping to the i16 range
+pub struct GainStage {
+    gain: f32,
+}
+
+impl GainStage {
+    pub fn new(gain_db: f32) -> Self {
+        Self { gain: 10f32.powf(gain_db / 20.0) }
+    }
+}
+
+impl FrameProcessor for GainStage {
+    fn name(&self) -> &'static str {
+        "gain"
+    }
+
+    fn process(&mut self, frame: &mut Frame) -> StageOutput {
+        for sample in frame.samples.iter_mut() {
+            *sample = (*sample as f32 * self.gain).clamp(-32768.0, 32767.0) as i16;
+        }
+        StageOutput::Forward
+    }
+}
+
+/// Feeds the source's level meter; always the first stage so dropped
+/// frames are still measured
+pub struct LevelStage {
+    meter: Arc<LevelMeter>,
+}
+
+impl LevelStage {
+    pub fn new(meter: Arc<LevelMeter>) -> Self {
+        Self { meter }
+    }
+}
+
+impl FrameProcessor for LevelStage {
+    fn name(&self) -> &'static str {
+        "levels"
+    }
+
+    fn process(&mut self, frame: &mut Frame) -> StageOutput {
+        self.meter.record(&frame.samples);
+        StageOutput::Forward
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_stage_is_rejected() {
+        let options = vec![StageOptions { kind: "reverb".to_string(), ..Default::default() }];
+        assert!(validate_stages(&options, SourceKind::Microphone, &OutputFormat::default(), 1).is_err());
+        assert!(build_stages(&options, SourceKind::Microphone, 16000, 1, None).is_err());
+
+        let aec = vec![StageOptions { kind: "aec".to_string(), ..Default::default() }];
+        assert!(validate_stages(&aec, SourceKind::Microphone, &OutputFormat::default(), 1).is_ok());
+        assert!(validate_stages(&aec, SourceKind::SystemAudio, &OutputFormat::default(), 1).is_err());
+    }
+
+    #[test]
+    fn test_opus_must_be_last() {
+        let stage = |kind: &str| StageOptions { kind: kind.to_string(), ..Default::default() };
+        let output = OutputFormat::default();
+        assert!(validate_stages(&[stage("suppression"), stage("opus")], SourceKind::Microphone, &output, 1).is_ok());
+        assert!(validate_stages(&[stage("opus"), stage("gain")], SourceKind::Microphone, &output, 1).is_err());
+
+        let disabled_gain = StageOptions { enabled: Some(false), ..stage("gain") };
+        assert!(validate_stages(&[stage("opus"), disabled_gain], SourceKind::Microphone, &output, 1).is_ok());
+
+        let bad_bitrate = StageOptions { bitrate: Some(1000), ..stage("opus") };
+        assert!(validate_stages(&[bad_bitrate], SourceKind::Microphone, &output, 1).is_err());
+
+        let long_frames = OutputFormat { frame_ms: 100, ..output };
+        assert!(validate_stages(&[stage("opus")], SourceKind::Microphone, &long_frames, 1).is_err());
+
+        // Interleaved microphones ("all" policy) only encode up to stereo
+        assert!(validate_stages(&[stage("opus")], SourceKind::Microphone, &output, 2).is_ok());
+        assert!(validate_stages(&[stage("opus")], SourceKind::Microphone, &output, 4).is_err());
+    }
+
+    #[test]
+    fn test_gain_clips_instead_of_wrapping() {
+        let mut stage = GainStage::new(12.0);
+        let mut frame = Frame { samples: vec![20000, -20000, 1000], channels: 1, sample_offset: 0, captured_at: std::time::Instant::now(), payload: None };
+        stage.process(&mut frame);
+        assert_eq!(frame.samples[0], 32767);
+        assert_eq!(frame.samples[1], -32768);
+        assert!((frame.samples[2] - 3981).abs() <= 1);
+    }
+}
@@ -12,6 +12,12 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::audio_config::{VAD_START_RMS, VAD_END_RMS, VAD_HANGOVER_MS};
+use crate::events::{CaptureEvent, EventSink};
+use crate::pipeline::{Frame, FrameProcessor, StageOutput};
+use crate::speech_detector::{RmsDetector, SpeechDetector};
+
+/// Detector score below which speech ends (same hysteresis as the RMS pair)
+const END_SCORE: f32 = VAD_END_RMS / VAD_START_RMS;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum VadState {
@@ -24,19 +30,29 @@ pub enum VadState {
 /// Does NOT gate audio - only reports state
 pub struct VadIndicator {
     state: VadState,
-    start_threshold: f32,
-    end_threshold: f32,
+    detector: Box<dyn SpeechDetector>,
     hangover_duration_ms: u128,
     hangover_start_time: u128,
     pub last_rms: f32,
 }
 
+impl Default for VadIndicator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl VadIndicator {
     pub fn new() -> Self {
+        Self::with_detector(Box::new(RmsDetector::with_stride(VAD_START_RMS, 10)))
+    }
+
+    /// Drive the indicator from a custom detector; speech starts at score
+    /// 1.0 and ends below END_SCORE
+    pub fn with_detector(detector: Box<dyn SpeechDetector>) -> Self {
         Self {
             state: VadState::Idle,
-            start_threshold: VAD_START_RMS,
-            end_threshold: VAD_END_RMS,
+            detector,
             hangover_duration_ms: VAD_HANGOVER_MS,
             hangover_start_time: 0,
             last_rms: 0.0,
@@ -47,31 +63,39 @@ impl VadIndicator {
     /// Returns current state for UI display
     /// DOES NOT affect audio flow to STT
     pub fn update(&mut self, chunk: &[i16]) -> VadState {
-        let rms = self.calculate_rms(chunk);
-        self.last_rms = rms;
         let now = self.current_time_ms();
+        self.update_at(chunk, now)
+    }
+
+    /// Same as `update`, with the caller supplying the clock (e.g. stream
+    /// time derived from sample offsets) so hangover is sample-accurate
+    pub fn update_at(&mut self, chunk: &[i16], now: u128) -> VadState {
+        let detection = self.detector.analyze(chunk);
+        let rms = detection.rms;
+        let score = detection.score;
+        self.last_rms = rms;
 
         match self.state {
             VadState::Idle => {
-                if rms > self.start_threshold {
+                if score > 1.0 {
                     self.state = VadState::Speech;
-                    println!("[VAD-UI] Speech detected (RMS: {})", rms as i32);
+                    log::debug!("[VAD-UI] Speech detected (RMS: {})", rms as i32);
                 }
             }
             VadState::Speech => {
-                if rms < self.end_threshold {
+                if score < END_SCORE {
                     self.state = VadState::Hangover;
                     self.hangover_start_time = now;
                 }
             }
             VadState::Hangover => {
-                if rms > self.start_threshold {
+                if score > 1.0 {
                     self.state = VadState::Speech;
                 } else {
-                    let time_in_hangover = now - self.hangover_start_time;
+                    let time_in_hangover = now.saturating_sub(self.hangover_start_time);
                     if time_in_hangover > self.hangover_duration_ms {
                         self.state = VadState::Idle;
-                        println!("[VAD-UI] Speech ended");
+                        log::debug!("[VAD-UI] Speech ended");
                     }
                 }
             }
@@ -80,6 +104,10 @@ impl VadIndicator {
         self.state
     }
 
+    pub fn state(&self) -> VadState {
+        self.state
+    }
+
     /// Check if currently in speech state (for UI)
     pub fn is_speech(&self) -> bool {
         matches!(self.state, VadState::Speech | VadState::Hangover)
@@ -87,37 +115,79 @@ impl VadIndicator {
 
     pub fn reset(&mut self) {
         self.state = VadState::Idle;
+        self.detector.reset();
     }
 
-    fn calculate_rms(&self, data: &[i16]) -> f32 {
-        if data.is_empty() {
-            return 0.0;
-        }
+    fn current_time_ms(&self) -> u128 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+    }
+}
 
-        let step = 10;
-        let mut sum: f32 = 0.0;
-        let mut count = 0;
-        
-        let mut i = 0;
-        while i < data.len() {
-            let sample = data[i] as f32;
-            sum += sample * sample;
-            count += 1;
-            i += step;
+/// Pipeline stage: reports speechStart/speechEnd to an event sink.
+/// Observes only - frames are always forwarded unchanged.
+pub struct VadStage {
+    indicator: VadIndicator,
+    sample_rate: u32,
+    sink: EventSink,
+    /// Where the current hangover began (reported as the speechEnd offset)
+    hangover_offset: u64,
+    mono: Vec<i16>,
+}
+
+impl VadStage {
+    pub fn new(indicator: VadIndicator, sample_rate: u32, sink: EventSink) -> Self {
+        Self {
+            indicator,
+            sample_rate,
+            sink,
+            hangover_offset: 0,
+            mono: Vec::new(),
         }
+    }
+}
 
-        if count == 0 {
-            return 0.0;
+impl FrameProcessor for VadStage {
+    fn name(&self) -> &'static str {
+        "vad"
+    }
+
+    fn process(&mut self, frame: &mut Frame) -> StageOutput {
+        let samples = if frame.channels > 1 {
+            self.mono.clear();
+            self.mono.extend(frame.samples.chunks_exact(frame.channels).map(|f| {
+                (f.iter().map(|&s| s as i32).sum::<i32>() / frame.channels as i32) as i16
+            }));
+            &self.mono
+        } else {
+            &frame.samples
+        };
+
+        let stream_ms = frame.sample_offset as u128 * 1000 / self.sample_rate.max(1) as u128;
+        let before = self.indicator.state();
+        let after = self.indicator.update_at(samples, stream_ms);
+        let rms = self.indicator.last_rms;
+
+        match (before, after) {
+            (VadState::Idle, VadState::Speech) => {
+                (self.sink)(CaptureEvent::new("speechStart", frame.sample_offset, self.sample_rate, rms));
+            }
+            (VadState::Speech, VadState::Hangover) => {
+                self.hangover_offset = frame.sample_offset;
+            }
+            (VadState::Hangover, VadState::Idle) => {
+                (self.sink)(CaptureEvent::new("speechEnd", self.hangover_offset, self.sample_rate, rms));
+            }
+            _ => {}
         }
 
-        (sum / count as f32).sqrt()
+        StageOutput::Forward
     }
 
-    fn current_time_ms(&self) -> u128 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis()
+    fn reset(&mut self) {
+        self.indicator.reset();
     }
 }
 
@@ -138,3 +208,35 @@ impl VadGate {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_stage_reports_sample_accurate_boundaries() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink_events = events.clone();
+        let mut stage = VadStage::new(
+            VadIndicator::new(),
+            16000,
+            Box::new(move |event| sink_events.lock().unwrap().push(event)),
+        );
+
+        // 10 silent frames, 25 loud frames (500ms), then 50 silent frames
+        let levels = [0i16; 10].iter().chain(&[2000; 25]).chain(&[0; 50]).copied().collect::<Vec<_>>();
+        for (i, &level) in levels.iter().enumerate() {
+            let mut frame = Frame { samples: vec![level; 320], channels: 1, sample_offset: i as u64 * 320, captured_at: std::time::Instant::now(), payload: None };
+            stage.process(&mut frame);
+        }
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, "speechStart");
+        assert_eq!(events[0].sample_offset, 3200.0);
+        assert_eq!(events[0].timestamp_ms, 200.0);
+        assert_eq!(events[1].kind, "speechEnd");
+        assert_eq!(events[1].sample_offset, 35.0 * 320.0);
+    }
+}
@@ -0,0 +1,269 @@
+// Virtual Input - JS-fed microphones for integration tests
+//
+// A `VirtualDevice` is registered under "virtual:<name>" and listed by
+// `list_input_devices` next to the real microphones. Opening it with
+// `MicrophoneStream::new` attaches the stream's ring buffer producer and
+// channel mixer to the device; every `push` from JS then lands in that ring
+// buffer exactly as a cpal callback would, so resampling, stages and delivery
+// run unchanged. Audio pushed while no capture is attached (or while it is
+// paused) is discarded, like a real microphone nobody is listening to.
+// Unregistering the device detaches the capture and reports it as lost, like
+// a microphone being unplugged.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use ringbuf::traits::Observer;
+use ringbuf::HeapProd;
+
+use crate::channel_mix::ChannelMixer;
+use crate::errors::{CaptureError, ErrorCode, ErrorReporter};
+
+/// Id prefix of every virtual device
+pub const VIRTUAL_PREFIX: &str = "virtual:";
+
+/// Sample type of the PCM pushed from JS (little-endian, interleaved)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PcmFormat {
+    #[default]
+    I16,
+    I32,
+    F32,
+    U8,
+}
+
+impl PcmFormat {
+    /// Parse the JS-facing name ("i16" | "i32" | "f32" | "u8")
+    pub fn from_name(name: Option<&str>) -> Result<Self> {
+        match name {
+            None | Some("i16") => Ok(PcmFormat::I16),
+            Some("i32") => Ok(PcmFormat::I32),
+            Some("f32") => Ok(PcmFormat::F32),
+            Some("u8") => Ok(PcmFormat::U8),
+            Some(other) => Err(anyhow::anyhow!("Unknown sample format: {}", other)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PcmFormat::I16 => "i16",
+            PcmFormat::I32 => "i32",
+            PcmFormat::F32 => "f32",
+            PcmFormat::U8 => "u8",
+        }
+    }
+
+    pub fn bytes(self) -> usize {
+        match self {
+            PcmFormat::I16 => 2,
+            PcmFormat::I32 | PcmFormat::F32 => 4,
+            PcmFormat::U8 => 1,
+        }
+    }
+}
+
+/// The capture currently reading a virtual device
+struct Attachment {
+    producer: HeapProd<f32>,
+    mixer: ChannelMixer,
+    is_running: Arc<AtomicBool>,
+    /// Told when the device is unregistered underneath the capture
+    errors: Arc<ErrorReporter>,
+}
+
+pub struct VirtualDevice {
+    id: String,
+    name: String,
+    sample_rate: u32,
+    channels: usize,
+    format: PcmFormat,
+    attachment: Mutex<Option<Attachment>>,
+}
+
+/// Every registered device, in registration order
+static DEVICES: Lazy<Mutex<Vec<Arc<VirtualDevice>>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+impl VirtualDevice {
+    /// Make "virtual:<name>" available as an input device
+    pub fn register(name: &str, sample_rate: u32, channels: usize, format: PcmFormat) -> Result<Arc<Self>> {
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("Virtual device name must not be empty"));
+        }
+        if sample_rate == 0 || channels == 0 {
+            return Err(anyhow::anyhow!("Virtual device needs a sample rate and at least one channel"));
+        }
+        let device = Arc::new(Self {
+            id: format!("{}{}", VIRTUAL_PREFIX, name),
+            name: name.to_string(),
+            sample_rate,
+            channels,
+            format,
+            attachment: Mutex::new(None),
+        });
+
+        let mut devices = DEVICES.lock().unwrap_or_else(|e| e.into_inner());
+        if devices.iter().any(|d| d.id == device.id) {
+            return Err(anyhow::anyhow!("Virtual device already exists: {}", device.id));
+        }
+        devices.push(device.clone());
+        log::info!("[VirtualInput] Registered {} ({}Hz, {}ch, {})", device.id, sample_rate, channels, format.name());
+        Ok(device)
+    }
+
+    /// Remove the device from the device list; an attached capture is
+    /// detached and reports the device as lost, as if it were unplugged
+    pub fn unregister(&self) {
+        DEVICES.lock().unwrap_or_else(|e| e.into_inner()).retain(|d| d.id != self.id);
+        log::info!("[VirtualInput] Removed {}", self.id);
+
+        let attachment = self.attachment.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(attachment) = attachment {
+            attachment.errors.report(CaptureError::new(
+                ErrorCode::DeviceLost,
+                format!("Virtual device {} was removed", self.id),
+                true,
+            ));
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn format(&self) -> PcmFormat {
+        self.format
+    }
+
+    /// Route pushed audio into a capture's ring buffer (one capture at a time)
+    pub fn attach(
+        &self,
+        producer: HeapProd<f32>,
+        mixer: ChannelMixer,
+        is_running: Arc<AtomicBool>,
+        errors: Arc<ErrorReporter>,
+    ) -> Result<()> {
+        let mut attachment = self.attachment.lock().unwrap_or_else(|e| e.into_inner());
+        if attachment.is_some() {
+            return Err(anyhow::anyhow!("Virtual device {} is already being captured", self.id));
+        }
+        *attachment = Some(Attachment { producer, mixer, is_running, errors });
+        Ok(())
+    }
+
+    pub fn detach(&self) {
+        *self.attachment.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Feed little-endian interleaved PCM in the device format. Returns the
+    /// number of frames accepted: fewer than pushed when the ring buffer is
+    /// full (retry the rest later), 0 while nothing is capturing.
+    pub fn push(&self, bytes: &[u8]) -> Result<usize> {
+        let frame_bytes = self.format.bytes() * self.channels;
+        if !bytes.len().is_multiple_of(frame_bytes) {
+            return Err(anyhow::anyhow!(
+                "Pushed {} bytes, not a whole number of {}-byte frames", bytes.len(), frame_bytes
+            ));
+        }
+
+        let mut attachment = self.attachment.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(Attachment { producer, mixer, is_running, .. }) = attachment.as_mut() else { return Ok(0) };
+        if !is_running.load(Ordering::Relaxed) {
+            return Ok(0);
+        }
+
+        let frames = (bytes.len() / frame_bytes).min(producer.vacant_len() / mixer.output_channels());
+        let data = &bytes[..frames * frame_bytes];
+        match self.format {
+            PcmFormat::I16 => mixer.push(&decode(data, |b| i16::from_le_bytes([b[0], b[1]])), producer),
+            PcmFormat::I32 => mixer.push(&decode(data, |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]])), producer),
+            PcmFormat::F32 => mixer.push(&decode(data, |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])), producer),
+            PcmFormat::U8 => mixer.push(data, producer),
+        };
+        Ok(frames)
+    }
+}
+
+fn decode<T>(bytes: &[u8], sample: impl Fn(&[u8]) -> T) -> Vec<T> {
+    bytes.chunks_exact(std::mem::size_of::<T>()).map(sample).collect()
+}
+
+/// Look up a registered device by id
+pub fn find(id: &str) -> Option<Arc<VirtualDevice>> {
+    DEVICES.lock().unwrap_or_else(|e| e.into_inner()).iter().find(|d| d.id == id).cloned()
+}
+
+/// Every registered device
+pub fn devices() -> Vec<Arc<VirtualDevice>> {
+    DEVICES.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel_mix::ChannelPolicy;
+    use ringbuf::traits::{Consumer, Split};
+    use ringbuf::HeapRb;
+
+    #[test]
+    fn test_pushed_pcm_reaches_the_attached_ring_buffer() {
+        let device = VirtualDevice::register("test-stereo", 8000, 2, PcmFormat::I16).unwrap();
+        assert!(VirtualDevice::register("test-stereo", 8000, 2, PcmFormat::I16).is_err());
+        assert_eq!(find("virtual:test-stereo").unwrap().channels(), 2);
+
+        let pcm: Vec<u8> = [16384i16, -16384, 8192, 8192, 0, 0]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        // Nothing attached yet
+        assert_eq!(device.push(&pcm).unwrap(), 0);
+        assert!(device.push(&pcm[..3]).is_err());
+
+        let (producer, mut consumer) = HeapRb::<f32>::new(2).split();
+        let running = Arc::new(AtomicBool::new(true));
+        let mixer = ChannelMixer::new(ChannelPolicy::Downmix, 2).unwrap();
+        device.attach(producer, mixer, running, Arc::new(ErrorReporter::new("test"))).unwrap();
+
+        // Only two mono frames fit; the third is left for the caller
+        assert_eq!(device.push(&pcm).unwrap(), 2);
+        let received: Vec<f32> = consumer.pop_iter().collect();
+        assert_eq!(received, vec![0.0, 0.25]);
+
+        device.unregister();
+        assert!(find("virtual:test-stereo").is_none());
+    }
+
+    #[test]
+    fn test_unregister_reports_the_attached_capture_lost() {
+        let device = VirtualDevice::register("test-unplugged", 16000, 1, PcmFormat::I16).unwrap();
+        let (producer, _consumer) = HeapRb::<f32>::new(64).split();
+        let mixer = ChannelMixer::new(ChannelPolicy::Downmix, 1).unwrap();
+        let errors = Arc::new(ErrorReporter::new("test"));
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let seen = reported.clone();
+        errors.set_listener(Some(Arc::new(move |error: CaptureError| seen.lock().unwrap().push(error))));
+        device.attach(producer, mixer, Arc::new(AtomicBool::new(true)), errors).unwrap();
+
+        device.unregister();
+        let reported = reported.lock().unwrap();
+        assert_eq!(
+            *reported,
+            vec![CaptureError::new(ErrorCode::DeviceLost, "Virtual device virtual:test-unplugged was removed", true)]
+        );
+        // Detached: nothing lands in the ring buffer any more
+        assert_eq!(device.push(&[0, 0]).unwrap(), 0);
+    }
+}